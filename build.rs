@@ -1,40 +1,727 @@
-use std::{env, fs, io::Write, path::PathBuf};
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+/// Most lanes a map can have, any more and they don't fit on the screen
+const MAX_LANES: u64 = 4;
+
+/// Most times a unit file can let its unit level up
+const MAX_LEVELS: u64 = 10;
 
 fn main() {
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let units_dir = PathBuf::from(&manifest_dir).join("units");
+    let family_names = generate_families(&manifest_dir);
+    let units = read_json_dir(&PathBuf::from(&manifest_dir).join("units"));
+
+    let out_file_path = PathBuf::from(&manifest_dir).join("src/game/card_gen/cards.rs");
+    let mut out_file = fs::File::create(out_file_path).unwrap();
+
+    writeln!(out_file, "/// THIS FILE IS AUTOGENERATED BY BUILD.RS").unwrap();
+    writeln!(
+        out_file,
+        "/// TO ADD NEW UNITS, ADD A NEW FILE TO THE UNITS DIRECTORY"
+    )
+    .unwrap();
+    writeln!(out_file, "use crate::game::entity::{{").unwrap();
+    writeln!(
+        out_file,
+        "    AttackType, LevelCurve, Rarity, Tag, TagBonus, Tags, Targeting, Unit, UnitId,"
+    )
+    .unwrap();
+    writeln!(out_file, "}};").unwrap();
+    writeln!(out_file).unwrap();
+
+    writeln!(out_file, "pub static UNITS: &[Unit<'static>] = &[").unwrap();
+    for (id, (path, unit)) in units.iter().enumerate() {
+        let field = |name: &str| {
+            unit.get(name)
+                .unwrap_or_else(|| panic!("{} is missing the '{}' field", path.display(), name))
+        };
+
+        let name = field("name").as_str().unwrap();
+        let emoji = field("emoji").as_str().unwrap();
+        let mut emoji_chars = emoji.chars();
+        let emoji = match (emoji_chars.next(), emoji_chars.next()) {
+            (Some(emoji), None) => emoji,
+            _ => panic!("{} has an emoji that is not a single char", path.display()),
+        };
+        let attack_type = match field("attack_type").as_str().unwrap() {
+            "Area" => "Area",
+            "Single" => "Single",
+            other => panic!("{} has unknown attack type '{}'", path.display(), other),
+        };
+
+        // The same checks UnitPack::validate runs, so the built in pack always loads
+        let health = field("health").as_u64().unwrap();
+        let size = field("size").as_f64().unwrap() as f32;
+        let speed = field("speed").as_f64().unwrap() as f32;
+        if name.trim().is_empty() {
+            panic!("{} has an empty name", path.display());
+        }
+        if health == 0 {
+            panic!("{} has no health", path.display());
+        }
+        if !(size.is_finite() && size > 0.0) {
+            panic!("{} has a size that isn't a positive number", path.display());
+        }
+        if !(speed.is_finite() && speed > 0.0) {
+            panic!(
+                "{} has a speed that isn't a positive number",
+                path.display()
+            );
+        }
+
+        writeln!(out_file, "    Unit {{").unwrap();
+        writeln!(out_file, "        id: {},", id).unwrap();
+        writeln!(out_file, "        name: {:?},", name).unwrap();
+        writeln!(out_file, "        emoji: {:?},", emoji).unwrap();
+        writeln!(
+            out_file,
+            "        cost: {},",
+            field("cost").as_u64().unwrap()
+        )
+        .unwrap();
+        writeln!(out_file, "        health: {},", health).unwrap();
+        writeln!(
+            out_file,
+            "        power: {},",
+            field("power").as_u64().unwrap()
+        )
+        .unwrap();
+        writeln!(out_file, "        size: {:?},", size).unwrap();
+        writeln!(out_file, "        speed: {:?},", speed).unwrap();
+        writeln!(
+            out_file,
+            "        attack_type: AttackType::{},",
+            attack_type
+        )
+        .unwrap();
+        // Rarity is optional in unit files, anything without one is common
+        let rarity = match unit.get("rarity").and_then(Value::as_str) {
+            None | Some("Common") => "Common",
+            Some("Rare") => "Rare",
+            Some("Epic") => "Epic",
+            Some("Legendary") => "Legendary",
+            Some(other) => panic!("{} has unknown rarity '{}'", path.display(), other),
+        };
+        writeln!(out_file, "        rarity: Rarity::{},", rarity).unwrap();
+        writeln!(
+            out_file,
+            "        switches_lanes: {},",
+            unit.get("switches_lanes")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        )
+        .unwrap();
+
+        // Tags, targeting and bonuses are all optional, units without them attack the closest
+        // enemy and deal the same damage to everything
+        let tag = |tag: &Value| match tag.as_str() {
+            Some(tag @ ("Flying" | "Heavy" | "Undead")) => tag.to_string(),
+            _ => panic!("{} has unknown tag {}", path.display(), tag),
+        };
+        let tags: Vec<String> = match unit.get("tags") {
+            None => vec![],
+            Some(Value::Array(tags)) => tags.iter().map(|t| format!("Tag::{}", tag(t))).collect(),
+            Some(_) => panic!("{} has tags that aren't a list", path.display()),
+        };
+        writeln!(out_file, "        tags: Tags::of(&[{}]),", tags.join(", ")).unwrap();
+        let targeting = match unit.get("targeting").and_then(Value::as_str) {
+            None | Some("Closest") => "Closest",
+            Some("LowestHealth") => "LowestHealth",
+            Some("TowerOnly") => "TowerOnly",
+            Some("HighestCost") => "HighestCost",
+            Some(other) => panic!("{} has unknown targeting '{}'", path.display(), other),
+        };
+        writeln!(out_file, "        targeting: Targeting::{},", targeting).unwrap();
+        match unit.get("bonus") {
+            None => writeln!(out_file, "        bonus: None,").unwrap(),
+            Some(bonus) => {
+                let against = tag(&bonus["against"]);
+                let damage = bonus["damage"].as_f64().unwrap_or_else(|| {
+                    panic!("{} has a bonus without a damage multiplier", path.display())
+                });
+                if !(damage.is_finite() && damage > 0.0) {
+                    panic!("{} has a bonus that doesn't deal damage", path.display());
+                }
+                writeln!(out_file, "        bonus: Some(TagBonus {{").unwrap();
+                writeln!(out_file, "            against: Tag::{},", against).unwrap();
+                writeln!(out_file, "            damage: {:?},", damage as f32).unwrap();
+                writeln!(out_file, "        }}),").unwrap();
+            }
+        }
+
+        let family = unit.get("family").filter(|family| !family.is_null());
+        match family.map(Value::as_str) {
+            None => writeln!(out_file, "        family: None,").unwrap(),
+            Some(Some(family)) if family_names.iter().any(|name| name == family) => {
+                writeln!(out_file, "        family: Some({:?}),", family).unwrap()
+            }
+            Some(_) => panic!("{} is in a family that doesn't exist", path.display()),
+        }
+
+        // Units without a level curve grow like LevelCurve::STANDARD
+        let levels = unit.get("levels");
+        let level_field = |name: &str, default: f64| {
+            let growth = levels
+                .and_then(|levels| levels.get(name))
+                .map_or(Some(default), Value::as_f64)
+                .unwrap_or_else(|| panic!("{} has a non-numeric level {}", path.display(), name));
+            if !(growth.is_finite() && growth >= 0.0) {
+                panic!("{} has a negative level {}", path.display(), name);
+            }
+            growth as f32
+        };
+        let max_level = levels
+            .and_then(|levels| levels.get("max"))
+            .map_or(Some(3), Value::as_u64)
+            .unwrap_or_else(|| panic!("{} has a level max that isn't a number", path.display()));
+        if max_level > MAX_LEVELS {
+            panic!(
+                "{} can level up more than {} times",
+                path.display(),
+                MAX_LEVELS
+            );
+        }
+        writeln!(out_file, "        levels: LevelCurve {{").unwrap();
+        writeln!(out_file, "            max: {},", max_level).unwrap();
+        writeln!(
+            out_file,
+            "            health: {:?},",
+            level_field("health", 0.25)
+        )
+        .unwrap();
+        writeln!(
+            out_file,
+            "            power: {:?},",
+            level_field("power", 0.25)
+        )
+        .unwrap();
+        writeln!(
+            out_file,
+            "            speed: {:?},",
+            level_field("speed", 0.0)
+        )
+        .unwrap();
+        writeln!(out_file, "        }},").unwrap();
+        writeln!(out_file, "        level: 0,").unwrap();
+        writeln!(out_file, "    }},").unwrap();
+    }
+    writeln!(out_file, "];").unwrap();
+    writeln!(out_file).unwrap();
+
+    writeln!(out_file, "pub fn unit_id(name: &str) -> Option<UnitId> {{").unwrap();
+    writeln!(out_file, "    match name {{").unwrap();
+    for (id, (_, unit)) in units.iter().enumerate() {
+        writeln!(
+            out_file,
+            "        {:?} => Some({}),",
+            unit["name"].as_str().unwrap(),
+            id
+        )
+        .unwrap();
+    }
+    writeln!(out_file, "        _ => None,").unwrap();
+    writeln!(out_file, "    }}").unwrap();
+    writeln!(out_file, "}}").unwrap();
 
-    let mut contents = vec![];
-    if units_dir.exists() && units_dir.is_dir() {
-        for entry in fs::read_dir(units_dir).unwrap() {
+    let unit_names: Vec<&str> = units
+        .iter()
+        .map(|(_, unit)| unit["name"].as_str().unwrap())
+        .collect();
+    generate_stages(&manifest_dir, &unit_names);
+    generate_spells(&manifest_dir, &unit_names);
+    generate_maps(&manifest_dir);
+
+    println!("cargo:rerun-if-changed=units");
+    println!("cargo:rerun-if-changed=families");
+    println!("cargo:rerun-if-changed=spells");
+    println!("cargo:rerun-if-changed=maps");
+    println!("cargo:rerun-if-changed=stages");
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Compiles the family files into `FAMILIES`, returning their names so units can be checked
+/// against them
+fn generate_families(manifest_dir: &str) -> Vec<String> {
+    let families = read_json_dir(&PathBuf::from(manifest_dir).join("families"));
+
+    let out_file_path = PathBuf::from(manifest_dir).join("src/game/family/families.rs");
+    let mut out_file = fs::File::create(out_file_path).unwrap();
+
+    writeln!(out_file, "/// THIS FILE IS AUTOGENERATED BY BUILD.RS").unwrap();
+    writeln!(
+        out_file,
+        "/// TO ADD NEW FAMILIES, ADD A NEW FILE TO THE FAMILIES DIRECTORY"
+    )
+    .unwrap();
+    writeln!(out_file, "use crate::game::family::{{Family, Tier}};").unwrap();
+    writeln!(out_file).unwrap();
+
+    let mut names = vec![];
+    writeln!(out_file, "pub static FAMILIES: &[Family] = &[").unwrap();
+    for (path, family) in &families {
+        let field = |name: &str| {
+            family
+                .get(name)
+                .unwrap_or_else(|| panic!("{} is missing the '{}' field", path.display(), name))
+        };
+
+        let name = field("name").as_str().unwrap().to_string();
+        if names.contains(&name) {
+            panic!("{} has the same name as another family", path.display());
+        }
+        let emoji = field("emoji").as_str().unwrap();
+        let mut emoji_chars = emoji.chars();
+        let emoji = match (emoji_chars.next(), emoji_chars.next()) {
+            (Some(emoji), None) => emoji,
+            _ => panic!("{} has an emoji that is not a single char", path.display()),
+        };
+
+        writeln!(out_file, "    Family {{").unwrap();
+        writeln!(out_file, "        name: {:?},", name).unwrap();
+        writeln!(out_file, "        emoji: {:?},", emoji).unwrap();
+        writeln!(out_file, "        tiers: &[").unwrap();
+        let tiers = field("tiers")
+            .as_array()
+            .filter(|tiers| !tiers.is_empty())
+            .unwrap_or_else(|| panic!("{} needs at least one tier", path.display()));
+        let mut fewest = 1;
+        for tier in tiers {
+            let units = tier.get("units").and_then(Value::as_u64).unwrap_or(0);
+            if units <= fewest {
+                panic!(
+                    "{} has tiers that don't each need more units than the last, starting from 2",
+                    path.display()
+                );
+            }
+            fewest = units;
+
+            // Buffs a tier leaves out are 0
+            let buff = |name: &str| {
+                let buff = tier.get(name).map_or(Some(0.0), Value::as_f64);
+                match buff {
+                    Some(buff) if buff.is_finite() && buff >= 0.0 => buff as f32,
+                    _ => panic!("{} has a {} buff that isn't positive", path.display(), name),
+                }
+            };
+            writeln!(
+                out_file,
+                "            Tier {{ units: {}, speed: {:?}, power: {:?} }},",
+                units,
+                buff("speed"),
+                buff("power")
+            )
+            .unwrap();
+        }
+        writeln!(out_file, "        ],").unwrap();
+        writeln!(out_file, "    }},").unwrap();
+
+        names.push(name);
+    }
+    writeln!(out_file, "];").unwrap();
+
+    names
+}
+
+/// Parses every file in a directory as json, sorted by path
+fn read_json_dir(dir: &Path) -> Vec<(PathBuf, Value)> {
+    let mut paths = vec![];
+    if dir.exists() && dir.is_dir() {
+        for entry in fs::read_dir(dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
 
             if path.is_file() {
-                let content = fs::read_to_string(path).unwrap();
-                contents.push(content);
+                paths.push(path);
             }
         }
     } else {
-        panic!("The 'units' directory does not exist.");
+        panic!("The '{}' directory does not exist.", dir.display());
     }
 
-    let out_file_path = PathBuf::from(&manifest_dir).join("src/game/card_gen/cards.rs");
+    // Unit ids are indices into the generated array and stages are played in file order, so
+    // keep them stable across platforms and rebuilds by always reading the files in order
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let content = fs::read_to_string(&path).unwrap();
+            let value: Value = serde_json::from_str(&content)
+                .unwrap_or_else(|e| panic!("Failed to parse {}: {}", path.display(), e));
+
+            (path, value)
+        })
+        .collect()
+}
+
+/// Reads a number of seconds from a stage or spell file as whole milliseconds
+fn millis(path: &Path, value: Option<&Value>, default: f64) -> u64 {
+    let seconds = match value {
+        None => default,
+        Some(value) => value
+            .as_f64()
+            .unwrap_or_else(|| panic!("{} has a duration that isn't a number", path.display())),
+    };
+    if seconds < 0.0 {
+        panic!("{} has a negative duration", path.display());
+    }
+
+    (seconds * 1000.0) as u64
+}
+
+/// Compiles the stage files into `STAGES`, checking every unit they spawn exists
+fn generate_stages(manifest_dir: &str, unit_names: &[&str]) {
+    let stages = read_json_dir(&PathBuf::from(manifest_dir).join("stages"));
+
+    let out_file_path = PathBuf::from(manifest_dir).join("src/game/stage/stages.rs");
     let mut out_file = fs::File::create(out_file_path).unwrap();
 
     writeln!(out_file, "/// THIS FILE IS AUTOGENERATED BY BUILD.RS").unwrap();
     writeln!(
         out_file,
-        "/// TO ADD NEW UNITS, ADD A NEW FILE TO THE UNITS DIRECTORY"
+        "/// TO ADD NEW STAGES, ADD A NEW FILE TO THE STAGES DIRECTORY"
+    )
+    .unwrap();
+    writeln!(out_file, "use std::time::Duration;").unwrap();
+    writeln!(out_file).unwrap();
+    writeln!(
+        out_file,
+        "use crate::game::stage::{{Spawn, Stage, Victory, Wave}};"
     )
     .unwrap();
+    writeln!(out_file).unwrap();
+
+    let mut names = vec![];
+    writeln!(out_file, "pub static STAGES: &[Stage] = &[").unwrap();
+    for (path, stage) in &stages {
+        let field = |name: &str| {
+            stage
+                .get(name)
+                .unwrap_or_else(|| panic!("{} is missing the '{}' field", path.display(), name))
+        };
+
+        let name = field("name").as_str().unwrap();
+        if names.contains(&name) {
+            panic!("{} has the same name as another stage", path.display());
+        }
+        names.push(name);
 
-    writeln!(out_file, "pub static CARDS: &[&str] = &[").unwrap();
-    for content in &contents {
-        writeln!(out_file, "\tr#\"{}\"#,", content.trim()).unwrap();
+        let victory = match field("victory").as_str().unwrap() {
+            "Survive" => "Survive",
+            "DestroyBase" => "DestroyBase",
+            other => panic!(
+                "{} has unknown victory condition '{}'",
+                path.display(),
+                other
+            ),
+        };
+        let tower_health = field("tower_health").as_u64().unwrap();
+        if tower_health == 0 {
+            panic!("{} has a tower with no health", path.display());
+        }
+        let waves = field("waves").as_array().unwrap();
+        if waves.is_empty() {
+            panic!("{} has no waves", path.display());
+        }
+
+        writeln!(out_file, "    Stage {{").unwrap();
+        writeln!(out_file, "        name: {:?},", name).unwrap();
+        writeln!(out_file, "        victory: Victory::{},", victory).unwrap();
+        writeln!(out_file, "        tower_health: {},", tower_health).unwrap();
+        writeln!(out_file, "        waves: &[").unwrap();
+        for wave in waves {
+            let spawns = wave
+                .get("units")
+                .and_then(Value::as_array)
+                .unwrap_or_else(|| panic!("{} has a wave without units", path.display()));
+            if spawns.is_empty() {
+                panic!("{} has a wave without units", path.display());
+            }
+
+            writeln!(out_file, "            Wave {{").unwrap();
+            writeln!(
+                out_file,
+                "                delay: Duration::from_millis({}),",
+                millis(path, wave.get("delay"), 0.0)
+            )
+            .unwrap();
+            writeln!(
+                out_file,
+                "                boss: {},",
+                wave.get("boss").and_then(Value::as_bool).unwrap_or(false)
+            )
+            .unwrap();
+            writeln!(out_file, "                spawns: &[").unwrap();
+            for spawn in spawns {
+                let unit = spawn["unit"].as_str().unwrap();
+                if !unit_names.contains(&unit) {
+                    panic!("{} spawns unknown unit '{}'", path.display(), unit);
+                }
+                let count = spawn.get("count").and_then(Value::as_u64).unwrap_or(1);
+                if count == 0 {
+                    panic!("{} spawns no {}", path.display(), unit);
+                }
+
+                writeln!(out_file, "                    Spawn {{").unwrap();
+                writeln!(out_file, "                        unit: {:?},", unit).unwrap();
+                writeln!(out_file, "                        count: {},", count).unwrap();
+                writeln!(
+                    out_file,
+                    "                        interval: Duration::from_millis({}),",
+                    millis(path, spawn.get("interval"), 1.0)
+                )
+                .unwrap();
+                writeln!(out_file, "                    }},").unwrap();
+            }
+            writeln!(out_file, "                ],").unwrap();
+            writeln!(out_file, "            }},").unwrap();
+        }
+        writeln!(out_file, "        ],").unwrap();
+        writeln!(out_file, "    }},").unwrap();
     }
     writeln!(out_file, "];").unwrap();
+}
 
-    println!("cargo:rerun-if-changed=units");
-    println!("cargo:rerun-if-changed=build.rs");
+/// Compiles the spell files into `SPELLS`, checking no spell shares a name with a unit
+fn generate_spells(manifest_dir: &str, unit_names: &[&str]) {
+    let spells = read_json_dir(&PathBuf::from(manifest_dir).join("spells"));
+
+    let out_file_path = PathBuf::from(manifest_dir).join("src/game/card_gen/spells.rs");
+    let mut out_file = fs::File::create(out_file_path).unwrap();
+
+    writeln!(out_file, "/// THIS FILE IS AUTOGENERATED BY BUILD.RS").unwrap();
+    writeln!(
+        out_file,
+        "/// TO ADD NEW SPELLS, ADD A NEW FILE TO THE SPELLS DIRECTORY"
+    )
+    .unwrap();
+    writeln!(
+        out_file,
+        "use crate::game::{{entity::Rarity, spell::{{Spell, SpellEffect}}}};"
+    )
+    .unwrap();
+    writeln!(out_file).unwrap();
+
+    let mut names = vec![];
+    writeln!(out_file, "pub static SPELLS: &[Spell<'static>] = &[").unwrap();
+    for (id, (path, spell)) in spells.iter().enumerate() {
+        let field = |name: &str| {
+            spell
+                .get(name)
+                .unwrap_or_else(|| panic!("{} is missing the '{}' field", path.display(), name))
+        };
+        let number = |value: &Value, name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .filter(|number| *number > 0.0)
+                .unwrap_or_else(|| panic!("{} needs a positive '{}'", path.display(), name))
+        };
+
+        let name = field("name").as_str().unwrap();
+        if names.contains(&name) || unit_names.contains(&name) {
+            panic!("{} has the same name as another card", path.display());
+        }
+        names.push(name);
+
+        let emoji = field("emoji").as_str().unwrap();
+        let mut emoji_chars = emoji.chars();
+        let emoji = match (emoji_chars.next(), emoji_chars.next()) {
+            (Some(emoji), None) => emoji,
+            _ => panic!("{} has an emoji that is not a single char", path.display()),
+        };
+        let rarity = match spell.get("rarity").and_then(Value::as_str) {
+            None | Some("Common") => "Common",
+            Some("Rare") => "Rare",
+            Some("Epic") => "Epic",
+            Some("Legendary") => "Legendary",
+            Some(other) => panic!("{} has unknown rarity '{}'", path.display(), other),
+        };
+
+        let effect = field("effect");
+        let effect = match effect["type"].as_str() {
+            Some("Freeze") => format!(
+                "SpellEffect::Freeze {{ millis: {} }}",
+                millis(path, effect.get("seconds"), 0.0)
+            ),
+            Some("Heal") => format!(
+                "SpellEffect::Heal {{ amount: {} }}",
+                number(effect, "amount") as u64
+            ),
+            Some("Meteor") => {
+                let radius = number(effect, "radius");
+                if radius > 1.0 {
+                    panic!("{} has a meteor wider than the lane", path.display());
+                }
+                format!(
+                    "SpellEffect::Meteor {{ damage: {}, radius: {:?} }}",
+                    number(effect, "damage") as u64,
+                    radius as f32
+                )
+            }
+            other => panic!("{} has unknown effect {:?}", path.display(), other),
+        };
+
+        writeln!(out_file, "    Spell {{").unwrap();
+        writeln!(out_file, "        id: {},", id).unwrap();
+        writeln!(out_file, "        name: {:?},", name).unwrap();
+        writeln!(out_file, "        emoji: {:?},", emoji).unwrap();
+        writeln!(
+            out_file,
+            "        cost: {},",
+            field("cost").as_u64().unwrap()
+        )
+        .unwrap();
+        writeln!(
+            out_file,
+            "        cooldown: {},",
+            millis(path, spell.get("cooldown"), 0.0)
+        )
+        .unwrap();
+        writeln!(out_file, "        rarity: Rarity::{},", rarity).unwrap();
+        writeln!(out_file, "        effect: {},", effect).unwrap();
+        writeln!(out_file, "    }},").unwrap();
+    }
+    writeln!(out_file, "];").unwrap();
+}
+
+/// Compiles the map files into `MAPS`, in file order so the first map is the default
+fn generate_maps(manifest_dir: &str) {
+    let maps = read_json_dir(&PathBuf::from(manifest_dir).join("maps"));
+    if maps.is_empty() {
+        panic!("There has to be at least one map");
+    }
+
+    let out_file_path = PathBuf::from(manifest_dir).join("src/game/map/maps.rs");
+    let mut out_file = fs::File::create(out_file_path).unwrap();
+
+    writeln!(out_file, "/// THIS FILE IS AUTOGENERATED BY BUILD.RS").unwrap();
+    writeln!(
+        out_file,
+        "/// TO ADD NEW MAPS, ADD A NEW FILE TO THE MAPS DIRECTORY"
+    )
+    .unwrap();
+    writeln!(
+        out_file,
+        "use crate::game::map::{{Map, Obstacle, Point, Zone}};"
+    )
+    .unwrap();
+    writeln!(out_file).unwrap();
+
+    let mut names = vec![];
+    writeln!(out_file, "pub static MAPS: &[Map] = &[").unwrap();
+    for (path, map) in &maps {
+        let field = |name: &str| {
+            map.get(name)
+                .unwrap_or_else(|| panic!("{} is missing the '{}' field", path.display(), name))
+        };
+        // Spots and screen coordinates are all fractions from 0 to 1
+        let fraction = |value: &Value, name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .filter(|fraction| (0.0..=1.0).contains(fraction))
+                .unwrap_or_else(|| panic!("{} needs a '{}' from 0 to 1", path.display(), name))
+                as f32
+        };
+
+        let name = field("name").as_str().unwrap();
+        if names.contains(&name) {
+            panic!("{} has the same name as another map", path.display());
+        }
+        names.push(name);
+
+        let lanes = field("lanes").as_u64().unwrap();
+        if !(1..=MAX_LANES).contains(&lanes) {
+            panic!("{} needs between 1 and {} lanes", path.display(), MAX_LANES);
+        }
+        let lane = |value: &Value| {
+            let lane = value.get("lane").and_then(Value::as_u64);
+            if lane.is_some_and(|lane| lane >= lanes) {
+                panic!("{} refers to a lane it doesn't have", path.display());
+            }
+            lane
+        };
+
+        let length = field("length").as_f64().unwrap();
+        if length <= 0.0 {
+            panic!("{} needs lanes longer than 0", path.display());
+        }
+        let towers = field("towers").as_array().unwrap();
+        if towers.len() != 2 {
+            panic!("{} needs a position for both towers", path.display());
+        }
+
+        writeln!(out_file, "    Map {{").unwrap();
+        writeln!(out_file, "        name: {:?},", name).unwrap();
+        writeln!(out_file, "        lanes: {},", lanes).unwrap();
+        writeln!(out_file, "        length: {:?},", length as f32).unwrap();
+        writeln!(out_file, "        towers: [").unwrap();
+        for tower in towers {
+            writeln!(
+                out_file,
+                "            Point {{ x: {:?}, y: {:?} }},",
+                fraction(tower, "x"),
+                fraction(tower, "y")
+            )
+            .unwrap();
+        }
+        writeln!(out_file, "        ],").unwrap();
+
+        writeln!(out_file, "        zones: &[").unwrap();
+        let zones = map.get("zones").and_then(Value::as_array);
+        for zone in zones.into_iter().flatten() {
+            let (start, end) = (fraction(zone, "start"), fraction(zone, "end"));
+            if start >= end {
+                panic!("{} has a zone that ends before it starts", path.display());
+            }
+            let speed = zone["speed"].as_f64().unwrap();
+            if speed <= 0.0 {
+                panic!("{} has a zone that stops units dead", path.display());
+            }
+
+            writeln!(
+                out_file,
+                "            Zone {{ lane: {:?}, start: {:?}, end: {:?}, speed: {:?} }},",
+                lane(zone).map(|lane| lane as usize),
+                start,
+                end,
+                speed as f32
+            )
+            .unwrap();
+        }
+        writeln!(out_file, "        ],").unwrap();
+
+        writeln!(out_file, "        obstacles: &[").unwrap();
+        let obstacles = map.get("obstacles").and_then(Value::as_array);
+        for obstacle in obstacles.into_iter().flatten() {
+            let position = fraction(obstacle, "position");
+            if position == 0.0 || position == 1.0 {
+                panic!("{} has an obstacle on top of a tower", path.display());
+            }
+            let health = obstacle["health"].as_u64().unwrap();
+            if health == 0 {
+                panic!("{} has an obstacle with no health", path.display());
+            }
+
+            writeln!(
+                out_file,
+                "            Obstacle {{ lane: {}, position: {:?}, health: {} }},",
+                lane(obstacle)
+                    .unwrap_or_else(|| panic!("{} has an obstacle without a lane", path.display())),
+                position,
+                health
+            )
+            .unwrap();
+        }
+        writeln!(out_file, "        ],").unwrap();
+        writeln!(out_file, "    }},").unwrap();
+    }
+    writeln!(out_file, "];").unwrap();
 }
@@ -4,14 +4,47 @@ export type MessageType =
   | { type: "ConnectReq"; data: string }
   | { type: "Text"; data: string }
   | { type: "Disconnect" }
-  | { type: "BeginGame" }
+  | { type: "BeginGame"; data?: HandMode }
+  | { type: "BeginBotGame"; data?: BotStrategy }
+  | { type: "BeginTeamGame"; data?: HandMode }
+  | { type: "BeginFreeForAll" }
+  | { type: "Target"; data: string }
+  | { type: "TeamText"; data: string }
+  | { type: "PartyInvite"; data: string }
+  | { type: "LeaveParty" }
+  | { type: "BeginStage"; data: string }
+  | { type: "ListStages" }
   | { type: "SpawnUnit"; data: string }
-  | { type: "DmgPing"; data: string };
+  | { type: "CastSpell"; data: string }
+  | { type: "Mulligan"; data: string }
+  | { type: "MulliganSpell"; data: string }
+  | { type: "MulliganDone" }
+  | { type: "FireCannon" }
+  | { type: "BuyUpgrade"; data: Upgrade }
+  | { type: "Surrender" }
+  | { type: "OfferDraw" }
+  | { type: "AcceptDraw" }
+  | { type: "Rematch" }
+  | { type: "ReloadUnits"; data: string }
+  | { type: "GetCatalog" }
+  | { type: "SaveDeck"; data: string }
+  | { type: "DeleteDeck"; data: string }
+  | { type: "ListDecks" }
+  | { type: "SelectDeck"; data?: string }
+  | { type: "SetRules"; data?: string };
 
 interface Chat {
   Chat: [string, string];
 }
 
+interface TeamChat {
+  TeamChat: [string, string];
+}
+
+interface Party {
+  Party: Array<string>;
+}
+
 interface GameStart {
   GameStart: Uuid;
 }
@@ -25,11 +58,33 @@ interface UserLeave {
 }
 
 interface StartGame {
-  StartGame: [Uuid, Uuid];
+  StartGame: [Uuid, Uuid, MatchRules, BattleMap];
+}
+
+interface TeamNames {
+  TeamNames: Array<string>;
 }
 
 interface DrawnHand {
-  DrawnHand: Array<Unit>;
+  DrawnHand: Array<Card>;
+}
+
+interface MulliganStart {
+  MulliganStart: [number, number];
+}
+
+interface MulliganHand {
+  MulliganHand: [Array<Card>, number];
+}
+
+type BattleBegin = "BattleBegin";
+
+type SuddenDeath = "SuddenDeath";
+
+type TimeUp = "TimeUp";
+
+interface HandUpdate {
+  HandUpdate: [Array<Card>, Card | null];
 }
 
 interface UnitSpawned {
@@ -40,11 +95,73 @@ interface NewTowerHealth {
   NewTowerHealth: [boolean, number];
 }
 
+interface SpellCast {
+  SpellCast: [number, Spell, number, number];
+}
+
+interface CannonFired {
+  CannonFired: [number, number];
+}
+
+interface Upgraded {
+  Upgraded: [Upgrade, number];
+}
+
+interface LeveledUp {
+  LeveledUp: [number, number];
+}
+
+interface DrawOffered {
+  DrawOffered: string;
+}
+
+interface RematchRequested {
+  RematchRequested: string;
+}
+
+interface Eliminated {
+  Eliminated: string;
+}
+
+interface WaveStarted {
+  WaveStarted: [number, number, boolean];
+}
+
+interface Stages {
+  Stages: Array<StageSummary>;
+}
+
+interface BattleSnapshot {
+  BattleSnapshot: Snapshot;
+}
+
 interface Win { Win: Uuid; }
 interface Lose { Lose: Uuid; }
+interface Draw { Draw: Uuid; }
 interface WinByDisconnect { WinByDisconnect: Uuid; }
 
+interface Catalog {
+  Catalog: Array<Unit>;
+}
+
+interface Decks {
+  Decks: Array<[string, Array<Unit>]>;
+}
+
+interface DeckSelected {
+  DeckSelected: string | null;
+}
+
+interface RulesSet {
+  RulesSet: MatchRules;
+}
+
+interface ServerError {
+  Error: string;
+}
+
 export type Unit = {
+  id: number;
   name: string;
   emoji: string;
   cost: number;
@@ -53,23 +170,206 @@ export type Unit = {
   size: number;
   speed: number;
   attack_type: Attack;
+  rarity: Rarity;
+  switches_lanes: boolean;
+  tags: Array<Tag>;
+  targeting: Targeting;
+  bonus: TagBonus | null;
+  family: string | null;
+  levels: LevelCurve;
+  level: number;
+};
+
+export type Tag = "Flying" | "Heavy" | "Undead";
+
+export type Targeting = "Closest" | "LowestHealth" | "TowerOnly" | "HighestCost";
+
+export type TagBonus = {
+  against: Tag;
+  damage: number;
 };
 
+export type LevelCurve = {
+  max: number;
+  health: number;
+  power: number;
+  speed: number;
+};
+
+export type Spell = {
+  id: number;
+  name: string;
+  emoji: string;
+  cost: number;
+  cooldown: number;
+  rarity: Rarity;
+  effect: SpellEffect;
+};
+
+export type SpellEffect =
+  | { Freeze: { millis: number } }
+  | { Heal: { amount: number } }
+  | { Meteor: { damage: number; radius: number } };
+
+// Cards in a hand say which kind of card they are
+export type Card = (Unit & { card: "Unit" }) | (Spell & { card: "Spell" });
+
+export type MatchRules = {
+  tower_health: number;
+  hand_size: number;
+  starting_money: number;
+  income_ticks: number;
+  time_limit: number | null;
+  sudden_death: number;
+  map: number;
+};
+
+// Named so it doesn't shadow the built in Map
+export type BattleMap = {
+  name: string;
+  lanes: number;
+  length: number;
+  towers: [Point, Point];
+  zones: Array<Zone>;
+  obstacles: Array<Obstacle>;
+};
+
+// Fractions of the screen, from the top left corner
+export type Point = {
+  x: number;
+  y: number;
+};
+
+export type Zone = {
+  lane: number | null;
+  start: number;
+  end: number;
+  speed: number;
+};
+
+export type Obstacle = {
+  lane: number;
+  position: number;
+  health: number;
+};
+
+export type Snapshot = {
+  money: number;
+  tower_health: number;
+  max_tower_health: number;
+  cannon_charge: number;
+  wallet: number;
+  upgrades: Array<UpgradeSnapshot>;
+  enemy_tower_health: number;
+  side: number;
+  target: number;
+  spectating: boolean;
+  towers: Array<number>;
+  seconds_left: number | null;
+  sudden_death: boolean;
+  obstacles: Array<number>;
+  synergies: Array<Synergy>;
+  units: Array<UnitSnapshot>;
+};
+
+export type Upgrade = "MaxHealth" | "Income" | "Wallet";
+
+export type Synergy = {
+  family: string;
+  emoji: string;
+  units: number;
+  tier: SynergyTier;
+};
+
+export type SynergyTier = {
+  units: number;
+  speed: number;
+  power: number;
+};
+
+export type UpgradeSnapshot = {
+  upgrade: Upgrade;
+  level: number;
+  cost: number | null;
+};
+
+export type UnitSnapshot = {
+  id: number;
+  unit: number;
+  emoji: string;
+  size: number;
+  ours: boolean;
+  side: number;
+  target: number;
+  lane: number;
+  level: number;
+  progress: number;
+  health: number;
+  max_health: number;
+  attack_charge: number;
+};
+
+export type StageSummary = {
+  index: number;
+  name: string;
+  victory: Victory;
+  waves: number;
+  unlocked: boolean;
+  cleared: boolean;
+};
+
+export type Victory = "Survive" | "DestroyBase";
+
 export type Attack = "Area" | "Single";
 
+export type Rarity = "Common" | "Rare" | "Epic" | "Legendary";
+
+export type HandMode = "Fixed" | "Cycle";
+
+// Strategies the server has built in, servers can register more
+export type BotStrategy =
+  | "Easy"
+  | "Normal"
+  | "Hard"
+  | "Random"
+  | "Greedy"
+  | "Counter";
+
 export type ServerResponseType =
   | GameStart
   | Chat
+  | TeamChat
+  | Party
   | UserJoin
   | UserLeave
   | StartGame
+  | TeamNames
   | DrawnHand
+  | MulliganStart
+  | MulliganHand
+  | HandUpdate
   | UnitSpawned
   | NewTowerHealth
+  | Eliminated
+  | SpellCast
+  | CannonFired
+  | Upgraded
+  | LeveledUp
+  | BattleSnapshot
+  | WaveStarted
+  | Stages
   | Win
   | WinByDisconnect
-  | Lose;
+  | Lose
+  | Draw
+  | DrawOffered
+  | RematchRequested
+  | Catalog
+  | Decks
+  | DeckSelected
+  | RulesSet
+  | ServerError;
 
 export interface ServerResponse {
-  message: ServerResponseType;
+  message: ServerResponseType | BattleBegin | SuddenDeath | TimeUp;
 }
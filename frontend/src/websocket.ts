@@ -1,48 +1,114 @@
 import {
+  BattleMap,
+  BotStrategy,
+  Card,
+  MatchRules,
   MessageType,
   ServerResponse,
-  Unit,
+  Synergy,
+  UnitSnapshot,
+  Upgrade,
+  UpgradeSnapshot,
 } from "./messages";
 
 export const socket = new WebSocket("/");
 
-type UnitMetadata = {
-  unit: Unit;
-  isOurs: boolean;
-  position: [number, number];
-  target: [number, number];
-
-  attackCooldown: number;
-
-  t: number
+// A unit from the latest battle snapshot, drawn sliding towards where the server says it is
+type RenderedUnit = {
+  unit: UnitSnapshot;
+  x: number;
+  t: number;
 };
 
 let gameDone: boolean = false;
-let newUnitSpawned: boolean = false;
 
-let playerUnits: Array<UnitMetadata> = [];
-let enemyUnits: Array<UnitMetadata> = [];
-let allUnits: Array<UnitMetadata> = [];
+// Tears down the battle view's draw loop and listeners once the battle is over
+let drawLoop: number | null = null;
+let battleListeners: AbortController | null = null;
+
+// Keyed by the unit's field id from the server
+let units: Map<number, RenderedUnit> = new Map();
+
+let drawnHand: Array<Card> | null = null;
+let nextCard: Card | null = null;
 
-let drawnHand: Array<Unit> | null = null;
-let drawnHandSetup = false;
+// Side by side lanes on the battle's map, and the one our units are sent down
+let lanes: number = 1;
+let selectedLane: number = 0;
+// The battle's map, and health left on its obstacles between us and our target
+let battleMap: BattleMap | null = null;
+let obstacles: Array<number> = [];
+// Family buffs our side has earned with the units it has on the field
+let synergies: Array<Synergy> = [];
 
-let cooldownStartTimes: Array<number>;
-let cooldowns: Array<number>;
+// An aimed spell waiting for the player to click where on the lane it should land
+let aimingSpell: Card | null = null;
+let meteorFlashUntil: number = 0;
+let meteorFlashX: number = 0;
+let meteorFlashWidth: number = 0;
+
+// Set while players can still redraw cards, before the battle begins
+let mulliganEndsAt: number | null = null;
+let redrawsLeft: number = 0;
+
+// Every card starts the battle cooling down, played cards are keyed by cardKey
+let battleStartedAt: number | null = null;
+let cooldownEnds: Map<string, number> = new Map();
 
 let userMoney: number = 50;
 
-let opponentTowerX: number;
-let opponentTowerY: number;
+// Banner shown for a few seconds when a stage sends out a new wave
+let waveBanner: string | null = null;
+let waveBannerEndsAt: number = 0;
 
-let userTowerX: number;
-let userTowerY: number;
+const rarityColors = {
+  Common: "#654321",
+  Rare: "#3b7dd8",
+  Epic: "#9b4dca",
+  Legendary: "#e0a526",
+};
 
-let userTowerHealth: number = 15000;
-let enemyTowerHealth: number = 15000;
+// Full health of a tower, from the rules the battle is played with
+let maxTowerHealth: number = 15000;
+let userTowerHealth: number = maxTowerHealth;
+let enemyTowerHealth: number = maxTowerHealth;
+
+// Every team's name and tower health by side, there are more than two in a free-for-all
+let teamNames: Array<string> = [];
+let towers: Array<number> = [];
+let ourSide: number = 0;
+let targetSide: number = 1;
+let spectating: boolean = false;
+
+// Our own tower's max health, cannon and upgrades from the latest snapshot
+let userMaxTowerHealth: number = 15000;
+let cannonCharge: number = 0;
+let wallet: number = 0;
+let upgrades: Array<UpgradeSnapshot> = [];
+let cannonFlashUntil: number = 0;
+let cannonFlashSide: number = 0;
+
+// Keys that buy each upgrade during a battle
+const upgradeKeys: Record<Upgrade, string> = {
+  MaxHealth: "H",
+  Income: "I",
+  Wallet: "W",
+};
+
+// Countdown to sudden death, or to the tiebreak once sudden death has begun
+let secondsLeft: number | null = null;
+let suddenDeath: boolean = false;
 
 function handleServerResponse(response: ServerResponse) {
-  if ("Chat" in response.message) {
+  if (response.message === "BattleBegin") {
+    mulliganEndsAt = null;
+    battleStartedAt = Date.now();
+  } else if (response.message === "SuddenDeath") {
+    waveBanner = "Sudden death! Double income, double tower damage";
+    waveBannerEndsAt = Date.now() + 3000;
+  } else if (response.message === "TimeUp") {
+    // The result follows right after, nothing to draw
+  } else if ("Chat" in response.message) {
     let message: string =
       response.message.Chat[0] + ": " + response.message.Chat[1];
     if (response.message.Chat[0] == "Server") {
@@ -50,6 +116,15 @@ function handleServerResponse(response: ServerResponse) {
     } else {
       displayMessage(message);
     }
+  } else if ("TeamChat" in response.message) {
+    const [name, text] = response.message.TeamChat;
+    displayColoredMessage(`[Team] ${name}: ${text}`, "#4caf50");
+  } else if ("Party" in response.message) {
+    const members = response.message.Party;
+    displayColoredMessage(
+      members.length > 0 ? `Your party: ${members.join(", ")}` : "You're not in a party",
+      "#4caf50",
+    );
   } else if ("UserJoin" in response.message) {
     let message: string = response.message.UserJoin + " has joined the server";
     displayColoredMessage(message, "#80a4bf");
@@ -60,58 +135,139 @@ function handleServerResponse(response: ServerResponse) {
     } else {
       enemyTowerHealth = health;
     }
+  } else if ("BattleSnapshot" in response.message) {
+    const snapshot = response.message.BattleSnapshot;
+    userMoney = snapshot.money;
+    userTowerHealth = snapshot.tower_health;
+    enemyTowerHealth = snapshot.enemy_tower_health;
+    towers = snapshot.towers;
+    ourSide = snapshot.side;
+    targetSide = snapshot.target;
+    spectating = snapshot.spectating;
+    secondsLeft = snapshot.seconds_left;
+    userMaxTowerHealth = snapshot.max_tower_health;
+    cannonCharge = snapshot.cannon_charge;
+    wallet = snapshot.wallet;
+    upgrades = snapshot.upgrades;
+    suddenDeath = snapshot.sudden_death;
+    obstacles = snapshot.obstacles;
+    synergies = snapshot.synergies;
+
+    const seen: Map<number, RenderedUnit> = new Map();
+    // Only the lane between our tower and the one we're attacking is drawn
+    snapshot.units.filter(onShownLane).forEach((unit) => {
+      const existing = units.get(unit.id);
+      seen.set(unit.id, {
+        unit: unit,
+        x: existing ? existing.x : laneX(unit),
+        t: existing ? existing.t : 0,
+      });
+    });
+    units = seen;
   } else if ("StartGame" in response.message) {
-    const userName = response.message.StartGame[0];
-    const opponentName = response.message.StartGame[1];
+    const [userName, opponentName, rules, map] = response.message.StartGame;
+    battleMap = map;
+    lanes = map.lanes;
+    selectedLane = Math.floor(lanes / 2);
+    maxTowerHealth = rules.tower_health;
+    userMaxTowerHealth = maxTowerHealth;
+    userTowerHealth = maxTowerHealth;
+    enemyTowerHealth = maxTowerHealth;
+    userMoney = rules.starting_money;
     switchToGameView(userName, opponentName);
+  } else if ("TeamNames" in response.message) {
+    teamNames = response.message.TeamNames;
+  } else if ("Eliminated" in response.message) {
+    waveBanner = `${response.message.Eliminated}'s tower has fallen!`;
+    waveBannerEndsAt = Date.now() + 3000;
   } else if ("DrawnHand" in response.message) {
     drawnHand = response.message.DrawnHand;
-
-    setInterval(() => {
-      userMoney += 1;
-    }, 30);
+  } else if ("MulliganStart" in response.message) {
+    const [seconds, redraws] = response.message.MulliganStart;
+    mulliganEndsAt = Date.now() + seconds * 1000;
+    redrawsLeft = redraws;
+  } else if ("MulliganHand" in response.message) {
+    [drawnHand, redrawsLeft] = response.message.MulliganHand;
+  } else if ("HandUpdate" in response.message) {
+    [drawnHand, nextCard] = response.message.HandUpdate;
+  } else if ("WaveStarted" in response.message) {
+    const [wave, waves, boss] = response.message.WaveStarted;
+    waveBanner = boss ? `Wave ${wave}/${waves}: a boss approaches!` : `Wave ${wave}/${waves}`;
+    waveBannerEndsAt = Date.now() + 3000;
+  } else if ("Stages" in response.message) {
+    displayColoredMessage("Stages:", "#80a4bf");
+    response.message.Stages.forEach((stage) => {
+      const goal = stage.victory === "Survive" ? "survive" : "destroy the base";
+      const status = stage.cleared ? " (cleared)" : stage.unlocked ? "" : " (locked)";
+      displayColoredMessage(`${stage.index}: ${stage.name}, ${stage.waves} waves, ${goal}${status}`, "#80a4bf");
+    });
   } else if ("UnitSpawned" in response.message) {
-    const canvas = document.getElementById("game-canvas") as HTMLCanvasElement;
-
-    const towerSize = canvas.width * 0.1;
-    const towerPadding = canvas.width * 0.05;
-    userTowerX = towerPadding + towerSize / 2;
-    userTowerY = canvas.height * 0.7;
-    opponentTowerX = canvas.width - towerPadding - towerSize / 2;
-    opponentTowerY = canvas.height * 0.7;
-
-    let [isOurs, unit] = response.message.UnitSpawned;
-
-    if (isOurs) {
-      let unit_metadata: UnitMetadata = {
-        unit: unit,
-        isOurs: true,
-        position: [userTowerX, userTowerY],
-        target: [opponentTowerX, opponentTowerY],
-        attackCooldown: 70,
-        t: 0
-      };
-
-      playerUnits.push(unit_metadata);
-    } else {
-      let unit_metadata: UnitMetadata = {
-        unit: unit,
-        isOurs: false,
-        position: [opponentTowerX, opponentTowerY],
-        target: [userTowerX, userTowerY],
-        attackCooldown: 70,
-        t: 0
-      };
-
-      enemyUnits.push(unit_metadata);
-      newUnitSpawned = true;
+    // Units are drawn from battle snapshots, nothing to do until the next one arrives
+  } else if ("Catalog" in response.message) {
+    displayColoredMessage("Units you can build decks from:", "#80a4bf");
+    response.message.Catalog.forEach((unit) => {
+      const tags = unit.tags.length > 0 ? ` [${unit.tags.join(", ")}]` : "";
+      const bonus = unit.bonus ? `, x${unit.bonus.damage} vs ${unit.bonus.against}` : "";
+      displayColoredMessage(`${unit.id}: ${unit.emoji} ${unit.name} ($${unit.cost})${tags}${bonus}`, "#80a4bf");
+    });
+  } else if ("Decks" in response.message) {
+    displayColoredMessage("Your decks:", "#80a4bf");
+    response.message.Decks.forEach(([name, units]) => {
+      const cost = units.reduce((total, unit) => total + unit.cost, 0);
+      displayColoredMessage(`${name}: ${units.map((unit) => unit.emoji).join("")} ($${cost})`, "#80a4bf");
+    });
+  } else if ("DeckSelected" in response.message) {
+    const deck = response.message.DeckSelected;
+    displayColoredMessage(deck ? `Now playing with ${deck}` : "Now playing with random units", "#80a4bf");
+  } else if ("RulesSet" in response.message) {
+    const rules = response.message.RulesSet;
+    displayColoredMessage(
+      `Battles you start: towers with ${rules.tower_health} health, ${rules.hand_size} cards, ` +
+        `$${rules.starting_money} to start and $1 every ${rules.income_ticks} ticks`,
+      "#80a4bf",
+    );
+  } else if ("SpellCast" in response.message) {
+    const [side, spell, spot] = response.message.SpellCast;
+    waveBanner = `${teamNames[side] ?? "Someone"} cast ${spell.emoji} ${spell.name}`;
+    waveBannerEndsAt = Date.now() + 2000;
+    if ("Meteor" in spell.effect) {
+      // Spots are measured from the caster's tower, and our tower is always on the left
+      const fraction = side === ourSide ? spot : 1 - spot;
+      meteorFlashX = fraction * window.innerWidth;
+      meteorFlashWidth = spell.effect.Meteor.radius * window.innerWidth;
+      meteorFlashUntil = Date.now() + 400;
     }
+  } else if ("CannonFired" in response.message) {
+    [cannonFlashSide] = response.message.CannonFired;
+    cannonFlashUntil = Date.now() + 300;
+  } else if ("Upgraded" in response.message) {
+    const [upgrade, level] = response.message.Upgraded;
+    waveBanner = `${upgrade} upgraded to level ${level}`;
+    waveBannerEndsAt = Date.now() + 2000;
+  } else if ("LeveledUp" in response.message) {
+    const [unitId, level] = response.message.LeveledUp;
+    const unit = drawnHand?.find((card) => card.card === "Unit" && card.id === unitId);
+    waveBanner = `${unit ? unit.name : "Unit"} leveled up to ${"★".repeat(level)}`;
+    waveBannerEndsAt = Date.now() + 2000;
+  } else if ("DrawOffered" in response.message) {
+    waveBanner = `${response.message.DrawOffered} offers a draw, call acceptDraw() to accept`;
+    waveBannerEndsAt = Date.now() + 5000;
+  } else if ("RematchRequested" in response.message) {
+    displayColoredMessage(
+      `${response.message.RematchRequested} wants a rematch, call rematch() to accept`,
+      "#a32791",
+    );
+  } else if ("Error" in response.message) {
+    displayColoredMessage(response.message.Error, "#d9534f");
   } else if ("WinByDisconnect" in response.message && !gameDone) {
     alert("Opponent has left, you win!");
     gameDone = true;
   } else if ("Win" in response.message) {
     alert("You have defeated your opponent! Final health of your tower was: " + userTowerHealth);
     gameDone = true;
+  } else if ("Draw" in response.message) {
+    alert("The battle ended in a draw!");
+    gameDone = true;
   } else if ("Lose" in response.message) {
     alert("Your enemy has destroyed your tower, you lose.");
     gameDone = true;
@@ -120,88 +276,115 @@ function handleServerResponse(response: ServerResponse) {
   }
 
   if (gameDone) {
-    window.location.reload();
+    returnToLobby();
   }
 }
 
-function dist(pointA: [number, number], pointB: [number, number]): number {
-  return Math.sqrt(Math.pow((pointB[0] - pointA[0]), 2) + Math.pow((pointB[1] - pointA[1]), 2));
-}
-
-function findClosestEnemy(unit: UnitMetadata, enemies: Array<UnitMetadata>): UnitMetadata | null {
-  let closestEnemy: UnitMetadata | null = null;
-  let minDistance = Infinity;
+// Swaps the battle view back out for the lobby chat, ready for another battle or a rematch
+function returnToLobby() {
+  gameDone = false;
+  if (drawLoop !== null) {
+    clearInterval(drawLoop);
+    drawLoop = null;
+  }
+  battleListeners?.abort();
+  battleListeners = null;
+  document.getElementById("game-canvas")?.remove();
 
-  for (let i = 0; i < enemies.length; i++) {
-    let enemy = enemies[i];
-    let distance = dist(unit.position, enemy.position);
-    if (distance < minDistance) {
-      minDistance = distance;
-      closestEnemy = enemy;
-    }
+  const chatContainer = document.getElementById("chat-container");
+  if (chatContainer) {
+    chatContainer.style.display = "";
   }
 
-  return closestEnemy;
+  units = new Map();
+  drawnHand = null;
+  nextCard = null;
+  mulliganEndsAt = null;
+  battleStartedAt = null;
+  cooldownEnds = new Map();
+  aimingSpell = null;
+  lanes = 1;
+  selectedLane = 0;
+  battleMap = null;
+  obstacles = [];
+  synergies = [];
+  teamNames = [];
+  towers = [];
+  spectating = false;
+  secondsLeft = null;
+  suddenDeath = false;
+  waveBanner = null;
+
+  displayColoredMessage("Call rematch() to ask for a rematch", "#80a4bf");
 }
 
-function updateAllUnits() {
-  for (let i = 0; i < playerUnits.length; i++) {
-    let playerUnit = playerUnits[i];
+function cooldown(card: Card): number {
+  if (card.card === "Spell") {
+    return card.cooldown;
+  }
+  return card.power * (1 / card.speed) * 500;
+}
 
-    let closestEnemy = findClosestEnemy(playerUnit, enemyUnits);
-    if (closestEnemy && dist(playerUnit.position, closestEnemy.position) <= (playerUnit.unit.size + closestEnemy.unit.size) * 22.5) {
-      playerUnit.attackCooldown += playerUnit.unit.speed / 4;
-      if (playerUnit.attackCooldown >= 100) {
-        closestEnemy.unit.health -= playerUnit.unit.power;
-        playerUnit.attackCooldown = 0;
+// Units and spells are numbered separately, so a card's id alone doesn't identify it
+function cardKey(card: Card): string {
+  return `${card.card}-${card.id}`;
+}
 
-        if (closestEnemy.unit.health <= 0) {
-          enemyUnits.splice(enemyUnits.indexOf(closestEnemy), 1);
-          userMoney += closestEnemy.unit.cost / 4;
-        }
-      }
-    } else {
-      let distance = dist(playerUnit.position, [opponentTowerX, opponentTowerY]);
-      if ((distance - (playerUnit.unit.size * 22.5)) < 3) {
-        playerUnit.attackCooldown += playerUnit.unit.speed / 4;
-        if (playerUnit.attackCooldown >= 100) {
-          damagePing(playerUnit.unit.power);
-          playerUnit.attackCooldown = 0;
-        }
-      } else {
-        playerUnit.position[0] += playerUnit.unit.speed / 5;
-      }
-    }
-    playerUnit.t += 1 / (playerUnit.unit.speed * 10);
+// Milliseconds until a card can be played again, and the cooldown it started with
+function remainingCooldown(card: Card): [number, number] {
+  const duration = cooldown(card);
+  if (battleStartedAt === null) {
+    return [duration, duration];
   }
 
-  for (let i = 0; i < enemyUnits.length; i++) {
-    let enemyUnit = enemyUnits[i];
+  const endsAt = cooldownEnds.get(cardKey(card)) ?? battleStartedAt + duration;
+  return [Math.max(endsAt - Date.now(), 0), duration];
+}
 
-    let closestPlayerUnit = findClosestEnemy(enemyUnit, playerUnits);
-    if (closestPlayerUnit && dist(enemyUnit.position, closestPlayerUnit.position) <= (enemyUnit.unit.size + closestPlayerUnit.unit.size) * 22.5) {
-      enemyUnit.attackCooldown += enemyUnit.unit.speed / 4;
-      if (enemyUnit.attackCooldown >= 100) {
-        closestPlayerUnit.unit.health -= enemyUnit.unit.power;
-        enemyUnit.attackCooldown = 0;
+function onShownLane(unit: UnitSnapshot): boolean {
+  return (
+    (unit.side === ourSide && unit.target === targetSide) ||
+    (unit.side === targetSide && unit.target === ourSide)
+  );
+}
 
-        if (closestPlayerUnit.unit.health <= 0) {
-          playerUnits.splice(playerUnits.indexOf(closestPlayerUnit), 1);
-        }
-      }
-    } else {
-      let distance = dist(enemyUnit.position, [userTowerX, userTowerY]);
-      if ((distance - enemyUnit.unit.size) < 3) {
-        enemyUnit.attackCooldown += enemyUnit.unit.speed / 4;
-        if (enemyUnit.attackCooldown >= 100) {
-          enemyUnit.attackCooldown = 0;
-        }
-      } else {
-        enemyUnit.position[0] -= enemyUnit.unit.speed / 5;
-      }
-    }
-    enemyUnit.t += 1 / (enemyUnit.unit.speed * 10);
+// Where a tower is drawn, 0 is the left one and 1 the right one. Maps say where their towers
+// go, otherwise they sit near the edges of the screen
+function towerX(tower: number): number {
+  const canvas = document.getElementById("game-canvas") as HTMLCanvasElement;
+  if (battleMap) {
+    return battleMap.towers[tower].x * canvas.width;
   }
+  const towerSize = canvas.width * 0.1;
+  const towerPadding = canvas.width * 0.05;
+  return tower === 0 ? towerPadding + towerSize / 2 : canvas.width - towerPadding - towerSize / 2;
+}
+
+function towerY(tower: number): number {
+  const canvas = document.getElementById("game-canvas") as HTMLCanvasElement;
+  return canvas.height * (battleMap ? battleMap.towers[tower].y : 0.7);
+}
+
+// Where something a fraction of the way down the lane from the left tower is drawn
+function fieldX(progress: number): number {
+  return towerX(0) + (towerX(1) - towerX(0)) * progress;
+}
+
+// How far from the left tower a spot on the map is, spots are measured from the lower side
+function spotProgress(spot: number): number {
+  return ourSide < targetSide ? spot : 1 - spot;
+}
+
+// Where a unit should be drawn, our units walk from the left tower to the right one
+function laneX(unit: UnitSnapshot): number {
+  return fieldX(unit.ours ? unit.progress : 1 - unit.progress);
+}
+
+// Height a lane is drawn at, lanes are spread out around the line between the towers
+function laneY(lane: number): number {
+  const canvas = document.getElementById("game-canvas") as HTMLCanvasElement;
+  const ground = (towerY(0) + towerY(1)) / 2;
+  return ground + canvas.height * (lane - (lanes - 1) / 2) * 0.07;
 }
 
 function switchToGameView(username: string, opponentName: string) {
@@ -231,7 +414,6 @@ function switchToGameView(username: string, opponentName: string) {
     let buttonHeight: number;
 
     const towerSize = canvas.width * 0.1;
-    const towerPadding = canvas.width * 0.05;
 
     function drawBattlefield() {
       if (ctx) {
@@ -243,19 +425,53 @@ function switchToGameView(username: string, opponentName: string) {
         ctx.fillStyle = "#228B22";
         ctx.fillRect(0, canvas.height * 0.7, canvas.width, canvas.height * 0.3);
 
-        const opponentTowerX = towerPadding + towerSize / 2;
-        const opponentTowerY = canvas.height * 0.7;
+        const opponentTowerX = towerX(0);
+        const opponentTowerY = towerY(0);
         ctx.font = `${towerSize}px Arial`;
         ctx.textAlign = "center";
 
-        const userTowerX = canvas.width - towerPadding - towerSize / 2;
-        const userTowerY = canvas.height * 0.7;
+        const userTowerX = towerX(1);
+        const userTowerY = towerY(1);
         ctx.fillText("🏡", opponentTowerX, opponentTowerY);
         ctx.fillText("🏡", userTowerX, userTowerY);
 
+        if (lanes > 1) {
+          for (let lane = 0; lane < lanes; lane++) {
+            ctx.fillStyle = lane === selectedLane ? "rgba(255, 255, 255, 0.35)" : "rgba(0, 0, 0, 0.15)";
+            ctx.fillRect(towerX(0) + towerSize / 2, laneY(lane) - 4, towerX(1) - towerX(0) - towerSize, 8);
+          }
+        }
+
+        // Slow terrain is muddy, fast terrain is a path
+        battleMap?.zones.forEach((zone) => {
+          const start = fieldX(spotProgress(zone.start));
+          const end = fieldX(spotProgress(zone.end));
+          const zoneLanes = zone.lane === null ? Array.from({ length: lanes }, (_, lane) => lane) : [zone.lane];
+          ctx.fillStyle = zone.speed < 1 ? "rgba(101, 67, 33, 0.5)" : "rgba(222, 184, 135, 0.6)";
+          zoneLanes.forEach((lane) => {
+            ctx.fillRect(Math.min(start, end), laneY(lane) - 10, Math.abs(end - start), 20);
+          });
+        });
+
+        battleMap?.obstacles.forEach((obstacle, index) => {
+          const health = obstacles[index] ?? obstacle.health;
+          if (health === 0) {
+            return;
+          }
+          const x = fieldX(spotProgress(obstacle.position));
+          ctx.font = `${canvas.width * 0.03}px Arial`;
+          ctx.fillText("🪨", x, laneY(obstacle.lane));
+          ctx.fillStyle = "red";
+          ctx.fillRect(x - 20, laneY(obstacle.lane) - canvas.width * 0.035, 40, 4);
+          ctx.fillStyle = "green";
+          ctx.fillRect(x - 20, laneY(obstacle.lane) - canvas.width * 0.035, (40 * health) / obstacle.health, 4);
+        });
+
         ctx.fillStyle = "#FFFFFF";
         ctx.font = `${canvas.width * 0.03}px Arial`;
-        ctx.fillText(username, userTowerX, userTowerY - towerSize);
+        // In a free-for-all the right tower is whichever opponent we're attacking
+        const shownOpponent = teamNames.length > 2 ? teamNames[targetSide] : username;
+        ctx.fillText(shownOpponent, userTowerX, userTowerY - towerSize);
 
         ctx.fillText(opponentName, opponentTowerX, opponentTowerY - towerSize);
 
@@ -273,7 +489,7 @@ function switchToGameView(username: string, opponentName: string) {
           ctx.fillRect(
             healthBarX,
             healthBarY,
-            healthBarWidth * (enemyTowerHealth / 15000),
+            healthBarWidth * Math.min(enemyTowerHealth / maxTowerHealth, 1),
             healthBarHeight
           );
         }
@@ -291,59 +507,73 @@ function switchToGameView(username: string, opponentName: string) {
           ctx.fillRect(
             healthBarX,
             healthBarY,
-            healthBarWidth * (userTowerHealth / 15000),
+            healthBarWidth * Math.min(userTowerHealth / userMaxTowerHealth, 1),
             healthBarHeight
           );
-        }
 
+          // The cannon's charge sits just under the health bar, gold once it's ready to fire
+          ctx.fillStyle = "#555555";
+          ctx.fillRect(healthBarX, healthBarY + healthBarHeight, healthBarWidth, healthBarHeight);
+          ctx.fillStyle = cannonCharge >= 1 ? "#e0a526" : "#f5deb3";
+          ctx.fillRect(
+            healthBarX,
+            healthBarY + healthBarHeight,
+            healthBarWidth * cannonCharge,
+            healthBarHeight
+          );
+        }
 
-        // Draw The Card Buttons:
-        if (drawnHand) {
-          if (!drawnHandSetup) {
-            drawnHandSetup = true;
+        if (Date.now() < meteorFlashUntil) {
+          ctx.fillStyle = "rgba(255, 80, 0, 0.4)";
+          ctx.fillRect(
+            meteorFlashX - meteorFlashWidth,
+            canvas.height * 0.5,
+            meteorFlashWidth * 2,
+            canvas.height * 0.25,
+          );
+        }
 
-            cooldowns = drawnHand.map(
-              (unit) => unit.power * (1 / unit.speed) * 500,
-            );
+        if (Date.now() < cannonFlashUntil) {
+          ctx.fillStyle = "rgba(255, 200, 0, 0.4)";
+          const flashX = cannonFlashSide === ourSide ? 0 : canvas.width * 0.6;
+          ctx.fillRect(flashX, canvas.height * 0.5, canvas.width * 0.4, canvas.height * 0.25);
+        }
 
-            cooldownStartTimes = new Array(drawnHand.length).fill(Date.now());
-          }
 
+        // Draw The Card Buttons:
+        if (drawnHand) {
           buttonWidth = canvas.width / drawnHand.length;
           buttonHeight = canvas.height * 0.2;
 
-          const now = Date.now();
           drawnHand.forEach((unit, index) => {
             const x = index * buttonWidth;
             const y = canvas.height - buttonHeight;
 
-            const cooldownDuration = cooldowns[index];
-            const elapsed = now - cooldownStartTimes[index];
-            const remainingCooldown = Math.max(cooldownDuration - elapsed, 0);
-            const cooldownPercentage = remainingCooldown / cooldownDuration;
+            const [remaining, duration] = remainingCooldown(unit);
+            const cooldownPercentage = remaining / duration;
 
             ctx.fillStyle =
-              remainingCooldown > 0
+              remaining > 0
                 ? "#777777"
                 : userMoney >= unit.cost
                   ? "#a37b48"
                   : "#91897e";
             ctx.fillRect(x, y, buttonWidth, buttonHeight);
 
-            ctx.strokeStyle = "#654321";
+            ctx.strokeStyle = rarityColors[unit.rarity];
             ctx.lineWidth = 5;
             ctx.strokeRect(x, y, buttonWidth, buttonHeight);
 
             const emojiSize = buttonHeight * 0.6;
             ctx.font =
-              userMoney < unit.cost && remainingCooldown <= 0
+              userMoney < unit.cost && remaining <= 0
                 ? `italic ${emojiSize}px Arial`
                 : `${emojiSize}px Arial`;
             ctx.textAlign = "center";
             ctx.fillStyle = "#ffffff";
             ctx.fillText(unit.emoji, x + buttonWidth / 2, y + emojiSize);
 
-            if (remainingCooldown > 0) {
+            if (remaining > 0) {
               ctx.fillStyle = "rgba(0, 0, 0, 0.5)";
               ctx.fillRect(
                 x,
@@ -361,26 +591,32 @@ function switchToGameView(username: string, opponentName: string) {
             const priceX = x + buttonWidth / 2 - textMetrics.width / 2;
             const priceY = y + buttonHeight - priceFontSize * 0.5;
             ctx.fillText(priceText, priceX + textMetrics.width / 2, priceY);
+
+            if (unit.card === "Unit" && unit.level > 0) {
+              ctx.font = `${priceFontSize}px Arial`;
+              ctx.fillText("★".repeat(unit.level), x + buttonWidth / 2, y + priceFontSize);
+            }
           });
         }
 
-        updateAllUnits();
-        if (newUnitSpawned) {
-          allUnits = playerUnits.concat(enemyUnits);
-          allUnits.sort();
-          newUnitSpawned = false;
-        }
+        units.forEach((rendered) => {
+          const unit = rendered.unit;
+          rendered.x += (laneX(unit) - rendered.x) * 0.3;
+          rendered.t += 0.1;
 
-        for (let i = 0; i < allUnits.length; i++) {
-          let unit = allUnits[i];
           ctx.save();
-          ctx.translate(unit.position[0], unit.position[1] + (2 * Math.sin(unit.t / 2)));
-          let shouldRotate = unit.isOurs ? -1 : 1;
-          ctx.rotate(shouldRotate * unit.attackCooldown / 300);
-          ctx.font = `${45 * unit.unit.size}px Arial`;
-          ctx.fillText(unit.unit.emoji, 0, 0);
+          ctx.translate(rendered.x, laneY(unit.lane) + (2 * Math.sin(rendered.t / 2)));
+          let shouldRotate = unit.ours ? -1 : 1;
+          ctx.rotate(shouldRotate * unit.attack_charge / 300);
+          ctx.font = `${45 * unit.size}px Arial`;
+          ctx.fillText(unit.emoji, 0, 0);
+          if (unit.level > 0) {
+            ctx.font = "14px Arial";
+            ctx.fillStyle = "#ffe400";
+            ctx.fillText("★".repeat(unit.level), 0, -45 * unit.size);
+          }
           ctx.restore();
-        }
+        });
 
         ctx.clearRect(canvas.width - 200, 0, 200, 50);
         ctx.font = "30px Arial";
@@ -391,47 +627,188 @@ function switchToGameView(username: string, opponentName: string) {
 
         ctx.fillStyle = "#ffffff";
         ctx.fillText(`Money: ${userMoney}`, canvas.width - 10, 40);
+
+        if (nextCard) {
+          ctx.fillText(`Next: ${nextCard.emoji}`, canvas.width - 10, 80);
+        }
+
+        ctx.font = "18px Arial";
+        ctx.fillText(`Wallet: ${wallet}`, canvas.width - 10, 110);
+        upgrades.forEach((upgrade, index) => {
+          const price = upgrade.cost === null ? "maxed" : `$${upgrade.cost}`;
+          ctx.fillText(
+            `[${upgradeKeys[upgrade.upgrade]}] ${upgrade.upgrade} ${upgrade.level} (${price})`,
+            canvas.width - 10,
+            135 + index * 22,
+          );
+        });
+        ctx.fillText("[Space] Fire cannon", canvas.width - 10, 135 + upgrades.length * 22);
+        synergies.forEach((synergy, index) => {
+          const buffs = [
+            synergy.tier.speed > 0 ? `+${Math.round(synergy.tier.speed * 100)}% speed` : null,
+            synergy.tier.power > 0 ? `+${Math.round(synergy.tier.power * 100)}% power` : null,
+          ].filter((buff) => buff !== null);
+          ctx.fillText(
+            `${synergy.emoji} ${synergy.family} x${synergy.units}: ${buffs.join(", ")}`,
+            canvas.width - 10,
+            135 + (upgrades.length + 1 + index) * 22,
+          );
+        });
+        ctx.font = "30px Arial";
+
+        if (waveBanner && Date.now() < waveBannerEndsAt) {
+          ctx.textAlign = "center";
+          ctx.fillText(waveBanner, canvas.width / 2, canvas.height * 0.15);
+        }
+
+        if (teamNames.length > 2) {
+          ctx.textAlign = "left";
+          ctx.font = "20px Arial";
+          teamNames.forEach((name, side) => {
+            const health = towers[side] ?? 0;
+            const marker = side === targetSide ? "> " : side === ourSide ? "* " : "  ";
+            const status = health > 0 ? `${health}` : "fallen";
+            ctx.fillText(`${marker}${side + 1}: ${name} (${status})`, 10, 30 + side * 25);
+          });
+          ctx.fillText("Press a number to attack that tower", 10, 30 + teamNames.length * 25);
+        }
+
+        if (secondsLeft !== null && !mulliganEndsAt) {
+          const minutes = Math.floor(secondsLeft / 60);
+          const seconds = (secondsLeft % 60).toString().padStart(2, "0");
+          ctx.textAlign = "center";
+          ctx.font = "30px Arial";
+          ctx.fillStyle = suddenDeath ? "#d9534f" : "#ffffff";
+          ctx.fillText(
+            suddenDeath ? `Sudden death ${minutes}:${seconds}` : `${minutes}:${seconds}`,
+            canvas.width / 2,
+            40,
+          );
+          ctx.fillStyle = "#ffffff";
+        }
+
+        if (spectating) {
+          ctx.textAlign = "center";
+          ctx.font = "30px Arial";
+          ctx.fillText("Your tower has fallen, spectating", canvas.width / 2, canvas.height * 0.25);
+        }
+
+        if (mulliganEndsAt) {
+          const secondsLeft = Math.max(Math.ceil((mulliganEndsAt - Date.now()) / 1000), 0);
+          ctx.textAlign = "center";
+          ctx.fillText(
+            `Click cards to redraw them (${redrawsLeft} left), press Enter when ready. Starting in ${secondsLeft}s`,
+            canvas.width / 2,
+            canvas.height * 0.2,
+          );
+        }
       }
     }
 
+    battleListeners = new AbortController();
+    const signal = battleListeners.signal;
+
     window.addEventListener("resize", () => {
       canvas.width = window.innerWidth;
       canvas.height = window.innerHeight;
-    });
+    }, { signal });
 
     canvas.addEventListener("click", (event) => {
       const rect = canvas.getBoundingClientRect();
       const clickX = event.clientX - rect.left;
       const clickY = event.clientY - rect.top;
 
-      if (drawnHand) {
+      if (drawnHand && mulliganEndsAt) {
         drawnHand.forEach((unit, index) => {
           const x = index * buttonWidth;
           const y = canvas.height - buttonHeight;
 
-          const cooldownDuration = cooldowns[index];
-          const now = Date.now();
-          const elapsed = now - cooldownStartTimes[index];
-          const remainingCooldown = Math.max(cooldownDuration - elapsed, 0);
-
           if (
             clickX > x &&
             clickX < x + buttonWidth &&
             clickY > y &&
             clickY < y + buttonHeight &&
-            remainingCooldown === 0 &&
+            redrawsLeft > 0
+          ) {
+            redrawCard(unit);
+          }
+        });
+      } else if (aimingSpell && clickY < canvas.height - buttonHeight) {
+        // Our tower is on the left, so the click's distance across is how far down our lane
+        castSpell(aimingSpell.id, clickX / canvas.width);
+        cooldownEnds.set(cardKey(aimingSpell), Date.now() + cooldown(aimingSpell));
+        userMoney -= aimingSpell.cost;
+        aimingSpell = null;
+      } else if (lanes > 1 && clickY < canvas.height - buttonHeight) {
+        // Clicking the field picks the closest lane to send units down
+        const distances = Array.from({ length: lanes }, (_, lane) => Math.abs(laneY(lane) - clickY));
+        selectedLane = distances.indexOf(Math.min(...distances));
+      } else if (drawnHand) {
+        drawnHand.forEach((unit, index) => {
+          const x = index * buttonWidth;
+          const y = canvas.height - buttonHeight;
+
+          const [remaining] = remainingCooldown(unit);
+          const clicked = clickX > x && clickX < x + buttonWidth && clickY > y && clickY < y + buttonHeight;
+
+          if (clicked && event.shiftKey && unit.card === "Unit") {
+            levelUp(unit.id);
+            return;
+          }
+
+          if (
+            clicked &&
+            remaining === 0 &&
             userMoney >= unit.cost
           ) {
-            sendUnit(unit.name);
+            if (unit.card === "Spell" && "Meteor" in unit.effect) {
+              aimingSpell = unit;
+              waveBanner = `Click the lane to aim ${unit.name}`;
+              waveBannerEndsAt = Date.now() + 2000;
+              return;
+            }
+
+            if (unit.card === "Spell") {
+              castSpell(unit.id, 0.5);
+            } else {
+              sendUnit(unit.id, selectedLane);
+            }
 
+            // The server has the final say, the next snapshot corrects our money
             userMoney -= unit.cost;
-            cooldownStartTimes[index] = Date.now();
+            cooldownEnds.set(cardKey(unit), Date.now() + cooldown(unit));
           }
         });
       }
     });
 
-    setInterval(drawBattlefield, 10);
+    window.addEventListener("keydown", (event) => {
+      if (event.key === "Enter" && mulliganEndsAt) {
+        sendMessage({ type: "MulliganDone" });
+      }
+
+      if (event.key === " ") {
+        fireCannon();
+      }
+      if (event.key === "ArrowUp") {
+        selectedLane = Math.max(selectedLane - 1, 0);
+      } else if (event.key === "ArrowDown") {
+        selectedLane = Math.min(selectedLane + 1, lanes - 1);
+      }
+      const upgrade = (Object.keys(upgradeKeys) as Array<Upgrade>).find(
+        (upgrade) => upgradeKeys[upgrade].toLowerCase() === event.key.toLowerCase(),
+      );
+      if (upgrade && !mulliganEndsAt) {
+        buyUpgrade(upgrade);
+      }
+
+      const side = parseInt(event.key) - 1;
+      if (teamNames.length > 2 && side >= 0 && side < teamNames.length && side !== ourSide) {
+        target(side);
+      }
+    }, { signal });
+
+    drawLoop = setInterval(drawBattlefield, 10);
   }
 }
 
@@ -483,6 +860,66 @@ export function chat(message: string) {
   sendMessage(messageType);
 }
 
+export function teamChat(message: string) {
+  sendMessage({ type: "TeamText", data: message });
+}
+
+export function inviteToParty(name: string) {
+  sendMessage({ type: "PartyInvite", data: name });
+}
+
+export function leaveParty() {
+  sendMessage({ type: "LeaveParty" });
+}
+
+export function startTeamBattle(cycle: boolean = false) {
+  let beginTeamGame: MessageType = {
+    type: "BeginTeamGame",
+    data: cycle ? "Cycle" : "Fixed",
+  };
+
+  sendMessage(beginTeamGame);
+}
+
+export function startFreeForAll() {
+  sendMessage({ type: "BeginFreeForAll" });
+}
+
+// Sends our units at another side's tower in a free-for-all
+export function target(side: number) {
+  sendMessage({ type: "Target", data: side.toString() });
+}
+
+export function fireCannon() {
+  sendMessage({ type: "FireCannon" });
+}
+
+export function buyUpgrade(upgrade: Upgrade) {
+  sendMessage({ type: "BuyUpgrade", data: upgrade });
+}
+
+// Pays to level up a unit card in your hand, shift clicking a card does the same
+export function levelUp(unit: number) {
+  sendMessage({ type: "LevelUp", data: unit.toString() });
+}
+
+export function surrender() {
+  sendMessage({ type: "Surrender" });
+}
+
+export function offerDraw() {
+  sendMessage({ type: "OfferDraw" });
+}
+
+export function acceptDraw() {
+  sendMessage({ type: "AcceptDraw" });
+}
+
+// Asks the opponent from your last one on one battle to play again
+export function rematch() {
+  sendMessage({ type: "Rematch" });
+}
+
 export function join(username: string) {
   let joinRequest: MessageType = {
     type: "ConnectReq",
@@ -492,30 +929,102 @@ export function join(username: string) {
   sendMessage(joinRequest);
 }
 
-export function sendUnit(unitId: string) {
+export function sendUnit(unitId: number, lane: number | null = null) {
   let sendUnit: MessageType = {
     type: "SpawnUnit",
-    data: unitId,
+    data: lane === null ? unitId.toString() : JSON.stringify({ unit: unitId, lane }),
   };
 
   sendMessage(sendUnit);
 }
 
-export function startBattle() {
+// Aimed spells land `spot` of the way down our lane, from 0 at our tower to 1 at theirs
+export function castSpell(spellId: number, spot: number) {
+  let castSpell: MessageType = {
+    type: "CastSpell",
+    data: JSON.stringify({ spell: spellId, spot }),
+  };
+
+  sendMessage(castSpell);
+}
+
+function redrawCard(card: Card) {
+  let redraw: MessageType = {
+    type: card.card === "Spell" ? "MulliganSpell" : "Mulligan",
+    data: card.id.toString(),
+  };
+
+  sendMessage(redraw);
+}
+
+export function startBattle(cycle: boolean = false) {
   let beginGame: MessageType = {
     type: "BeginGame",
+    data: cycle ? "Cycle" : "Fixed",
   };
 
   sendMessage(beginGame);
 }
 
-function damagePing(dmg: number) {
-  let msg: MessageType = {
-    type: "DmgPing",
-    data: dmg.toString()
+export function startBotBattle(strategy: BotStrategy = "Normal") {
+  let beginBotGame: MessageType = {
+    type: "BeginBotGame",
+    data: strategy,
+  };
+
+  sendMessage(beginBotGame);
+}
+
+export function listStages() {
+  sendMessage({ type: "ListStages" });
+}
+
+export function startStage(stage: number, cycle: boolean = false) {
+  let beginStage: MessageType = {
+    type: "BeginStage",
+    data: JSON.stringify({ stage: stage, hand_mode: cycle ? "Cycle" : "Fixed" }),
+  };
+
+  sendMessage(beginStage);
+}
+
+export function getCatalog() {
+  sendMessage({ type: "GetCatalog" });
+}
+
+export function saveDeck(name: string, unitIds: Array<number>) {
+  let save: MessageType = {
+    type: "SaveDeck",
+    data: JSON.stringify({ name: name, units: unitIds }),
+  };
+
+  sendMessage(save);
+}
+
+export function deleteDeck(name: string) {
+  sendMessage({ type: "DeleteDeck", data: name });
+}
+
+export function listDecks() {
+  sendMessage({ type: "ListDecks" });
+}
+
+// Picks the rules for battles you start, any left out keep their defaults
+export function setRules(rules: Partial<MatchRules> = {}) {
+  sendMessage({ type: "SetRules", data: JSON.stringify(rules) });
+}
+
+export function selectDeck(name?: string) {
+  sendMessage({ type: "SelectDeck", data: name });
+}
+
+export function reloadUnits(adminToken: string) {
+  let reload: MessageType = {
+    type: "ReloadUnits",
+    data: adminToken,
   };
 
-  sendMessage(msg);
+  sendMessage(reload);
 }
 
 function sendMessage(msg: MessageType) {
@@ -524,5 +1033,28 @@ function sendMessage(msg: MessageType) {
 }
 
 (window as any).chat = chat;
+(window as any).teamChat = teamChat;
+(window as any).inviteToParty = inviteToParty;
+(window as any).leaveParty = leaveParty;
+(window as any).startTeamBattle = startTeamBattle;
+(window as any).startFreeForAll = startFreeForAll;
+(window as any).target = target;
 (window as any).join = join;
 (window as any).startBattle = startBattle;
+(window as any).startBotBattle = startBotBattle;
+(window as any).listStages = listStages;
+(window as any).startStage = startStage;
+(window as any).reloadUnits = reloadUnits;
+(window as any).getCatalog = getCatalog;
+(window as any).saveDeck = saveDeck;
+(window as any).deleteDeck = deleteDeck;
+(window as any).listDecks = listDecks;
+(window as any).selectDeck = selectDeck;
+(window as any).setRules = setRules;
+(window as any).fireCannon = fireCannon;
+(window as any).buyUpgrade = buyUpgrade;
+(window as any).levelUp = levelUp;
+(window as any).surrender = surrender;
+(window as any).offerDraw = offerDraw;
+(window as any).acceptDraw = acceptDraw;
+(window as any).rematch = rematch;
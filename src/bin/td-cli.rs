@@ -0,0 +1,505 @@
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use futures_util::{Sink, SinkExt, StreamExt};
+use td::game::battle::BattleSnapshot;
+use td::game::card::{Card, CardId};
+use td::game::entity::Drawable;
+use td::game::map::Map;
+use td::server::service::{CastRequest, ClientMessage, PlayRequest, ResponseType, ServerResponse};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const USAGE: &str = "Usage: td-cli [options]
+
+Plays on a server from the terminal instead of the browser.
+
+Options:
+    --server ws://127.0.0.1:7878   Server to connect to
+    --name NAME                    Name to play under, your user name by default";
+
+const COMMANDS: &str = "Anything not starting with / is sent to the lobby chat.
+
+Lobby:
+    /play [cycle]        Queue for a one on one battle, against a bot if nobody's around
+    /bot [STRATEGY]      Play a bot: Easy, Normal, Hard, Random, Greedy or Counter
+    /team [cycle]        Queue for a team battle with your party
+    /ffa                 Queue for a free-for-all
+    /invite NAME         Invite a player to your party, or accept their invite
+    /leave               Leave your party
+    /t TEXT              Chat with your team or party
+
+Battle:
+    /p SLOT [LANE]       Play the card in a hand slot, spells land halfway down the lane
+    /redraw SLOT         Redraw a card before the fighting starts
+    /ready               Done redrawing
+    /level SLOT          Level up the unit card in a hand slot
+    /cannon              Fire your tower's cannon
+    /upgrade NAME        Buy MaxHealth, Income or Wallet
+    /target SIDE         Pick which side to send units at in a free-for-all
+    /surrender, /draw, /accept, /rematch
+
+    /status              Draw the battlefield now
+    /help                Show this again
+    /quit";
+
+/// How often the battlefield is redrawn while fighting
+const RENDER_INTERVAL: Duration = Duration::from_secs(2);
+/// Cells a lane is drawn with, each two columns wide to fit an emoji
+const LANE_CELLS: usize = 30;
+
+/// A card in the player's hand, kept after the message it came in is gone
+struct HandCard {
+    id: CardId,
+    emoji: char,
+    name: String,
+    cost: usize,
+    level: usize,
+}
+
+impl From<&Card<'_>> for HandCard {
+    fn from(card: &Card) -> Self {
+        Self {
+            id: card.id(),
+            emoji: card.emoji(),
+            name: card.name().to_string(),
+            cost: card.cost(),
+            level: card.unit().map_or(0, |unit| unit.get_level()),
+        }
+    }
+}
+
+/// What the client knows about the lobby and the battle it's in
+#[derive(Default)]
+struct Client {
+    hand: Vec<HandCard>,
+    next: Option<HandCard>,
+    map: Option<Map>,
+    snapshot: Option<BattleSnapshot>,
+    fighting: bool,
+    /// Set when there's something new to draw
+    changed: bool,
+}
+
+enum Input {
+    Send(ClientMessage),
+    Status,
+    Help,
+    Quit,
+}
+
+#[tokio::main]
+async fn main() {
+    let (server, name) = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        process::exit(2);
+    });
+
+    let (socket, _) = connect_async(server.as_str()).await.unwrap_or_else(|e| {
+        eprintln!("Couldn't connect to {server}: {e}");
+        process::exit(1);
+    });
+    let (mut writer, mut reader) = socket.split();
+    println!("Connected to {server} as {name}, /help for commands");
+
+    let join = ClientMessage::new("ConnectReq", Some(name));
+    if let Err(e) = send(&mut writer, &join).await {
+        eprintln!("Couldn't join the lobby: {e}");
+        process::exit(1);
+    }
+
+    let mut client = Client::default();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut render = tokio::time::interval(RENDER_INTERVAL);
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Ok(Some(line)) = line else {
+                    break;
+                };
+                match parse_command(line.trim(), &client) {
+                    Ok(Input::Send(message)) => {
+                        if let Err(e) = send(&mut writer, &message).await {
+                            eprintln!("Lost the connection: {e}");
+                            break;
+                        }
+                    }
+                    Ok(Input::Status) => print!("{}", client.render()),
+                    Ok(Input::Help) => println!("{COMMANDS}"),
+                    Ok(Input::Quit) => break,
+                    Err(e) => println!("{e}"),
+                }
+            }
+            message = reader.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ServerResponse>(&text) {
+                        Ok(response) => client.handle(response.into_message()),
+                        Err(e) => eprintln!("Couldn't read a message from the server: {e}"),
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    println!("The server closed the connection");
+                    break;
+                }
+                Some(Err(e)) => {
+                    eprintln!("Lost the connection: {e}");
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+            _ = render.tick() => {
+                if client.fighting && client.changed {
+                    print!("{}", client.render());
+                    client.changed = false;
+                }
+            }
+        }
+    }
+
+    let _ = writer.send(Message::Close(None)).await;
+}
+
+async fn send<S>(writer: &mut S, message: &ClientMessage) -> Result<(), S::Error>
+where
+    S: Sink<Message> + Unpin,
+{
+    let json = serde_json::to_string(message).expect("Client messages always serialize");
+    writer.send(Message::Text(json)).await
+}
+
+impl Client {
+    fn handle(&mut self, response: ResponseType) {
+        match response {
+            ResponseType::Chat(name, text) => println!("{name}: {text}"),
+            ResponseType::TeamChat(name, text) => println!("[team] {name}: {text}"),
+            ResponseType::Party(names) if names.is_empty() => println!("You're not in a party"),
+            ResponseType::Party(names) => println!("Party: {}", names.join(", ")),
+            ResponseType::UserJoin(name) => println!("* {name} joined"),
+            ResponseType::UserLeave(name) => println!("* {name} left"),
+            ResponseType::GameStart(_) => {}
+            ResponseType::StartGame(opponents, team, _, map) => {
+                println!("{team} vs {opponents} on {}", map.name);
+                self.map = Some(map);
+                self.snapshot = None;
+            }
+            ResponseType::TeamNames(_) => {}
+            ResponseType::DrawnHand(cards) => {
+                self.set_hand(&cards, None);
+                println!("{}", self.render_hand());
+            }
+            ResponseType::MulliganStart(seconds, redraws) => println!(
+                "You have {seconds}s to /redraw up to {redraws} cards, /ready when you're done"
+            ),
+            ResponseType::MulliganHand(cards, redraws) => {
+                self.set_hand(&cards, None);
+                println!("{}\n{redraws} redraws left", self.render_hand());
+            }
+            ResponseType::BattleBegin => {
+                println!("Fight!");
+                self.fighting = true;
+                self.changed = true;
+            }
+            ResponseType::HandUpdate(cards, next) => {
+                self.set_hand(&cards, next.as_deref());
+                self.changed = true;
+            }
+            ResponseType::UnitSpawned(ours, unit) => {
+                if !ours {
+                    println!(
+                        "Enemy sent {} {}",
+                        Card::Unit(*unit).emoji(),
+                        unit.get_name()
+                    );
+                }
+            }
+            ResponseType::NewTowerHealth(..) => {}
+            ResponseType::SpellCast(side, spell, _, affected) => println!(
+                "Side {side} cast {} {} ({affected})",
+                Card::Spell(*spell).emoji(),
+                spell.get_name()
+            ),
+            ResponseType::CannonFired(side, killed) => {
+                println!("Side {side} fired its cannon, killing {killed}")
+            }
+            ResponseType::Upgraded(upgrade, level) => {
+                println!("Upgraded {upgrade:?} to level {level}")
+            }
+            ResponseType::LeveledUp(_, level) => println!("Card leveled up to {level}"),
+            ResponseType::Eliminated(team) => println!("{team}'s tower fell"),
+            ResponseType::WaveStarted(wave, waves, boss) => {
+                let boss = if boss { ", boss incoming!" } else { "" };
+                println!("Wave {wave} of {waves}{boss}");
+            }
+            ResponseType::BattleSnapshot(snapshot) => {
+                self.snapshot = Some(snapshot);
+                self.changed = true;
+            }
+            ResponseType::SuddenDeath => println!("Sudden death! Towers take extra damage"),
+            ResponseType::TimeUp => println!("Time's up"),
+            ResponseType::Win(_) => self.finish("You won!"),
+            ResponseType::WinByDisconnect(_) => self.finish("You won, your opponent left"),
+            ResponseType::Lose(_) => self.finish("You lost"),
+            ResponseType::Draw(_) => self.finish("It's a draw"),
+            ResponseType::DrawOffered(team) => {
+                println!("{team} offers a draw, /accept to take it")
+            }
+            ResponseType::RematchRequested(name) => {
+                println!("{name} wants a rematch, /rematch to play again")
+            }
+            ResponseType::Catalog(units) => println!("{} units in the catalog", units.len()),
+            ResponseType::Decks(decks) => {
+                for (name, units) in decks {
+                    let emojis: String =
+                        units.iter().map(|unit| Card::Unit(*unit).emoji()).collect();
+                    println!("{name}: {emojis}");
+                }
+            }
+            ResponseType::DeckSelected(Some(deck)) => println!("Playing with deck {deck}"),
+            ResponseType::DeckSelected(None) => println!("Playing with random hands"),
+            ResponseType::RulesSet(rules) => println!("Rules set: {rules:?}"),
+            ResponseType::Stages(stages) => {
+                for stage in stages {
+                    let status = match (stage.cleared, stage.unlocked) {
+                        (true, _) => "cleared",
+                        (false, true) => "unlocked",
+                        (false, false) => "locked",
+                    };
+                    println!("{}. {} ({status})", stage.index, stage.name);
+                }
+            }
+            ResponseType::Error(e) => println!("Error: {e}"),
+        }
+    }
+
+    fn set_hand(&mut self, cards: &[Card], next: Option<&Card>) {
+        self.hand = cards.iter().map(HandCard::from).collect();
+        self.next = next.map(HandCard::from);
+    }
+
+    fn finish(&mut self, result: &str) {
+        println!("{result}");
+        self.fighting = false;
+        self.hand.clear();
+        self.next = None;
+        self.snapshot = None;
+    }
+
+    fn render_hand(&self) -> String {
+        let mut text = String::from("Hand:");
+        for (slot, card) in self.hand.iter().enumerate() {
+            let stars = "★".repeat(card.level);
+            text += &format!(
+                "  [{}] {} {}{stars} {}",
+                slot + 1,
+                card.emoji,
+                card.name,
+                card.cost
+            );
+        }
+        if let Some(next) = &self.next {
+            text += &format!("  (next: {} {})", next.emoji, next.name);
+        }
+
+        text
+    }
+
+    /// Towers, money and time on top, every lane with our tower on the left, then the hand
+    fn render(&self) -> String {
+        let Some(snapshot) = &self.snapshot else {
+            return format!("{}\n", self.render_hand());
+        };
+
+        let time = match snapshot.seconds_left {
+            Some(seconds) => format!("⏱ {}:{:02}", seconds / 60, seconds % 60),
+            None => String::new(),
+        };
+        let sudden_death = if snapshot.sudden_death {
+            " SUDDEN DEATH"
+        } else {
+            ""
+        };
+        let mut text = format!(
+            "\n🏰 {}/{}  vs  {}   💰 {}/{}   {time}{sudden_death}\n",
+            snapshot.tower_health,
+            snapshot.max_tower_health,
+            snapshot.enemy_tower_health,
+            snapshot.money,
+            snapshot.wallet
+        );
+
+        let map = self.map.unwrap_or_default();
+        for lane in 0..map.lanes {
+            text += &format!("🏰{}🏯\n", render_lane(snapshot, &map, lane));
+        }
+        text += &format!("Yours:  {}\n", render_units(snapshot, true));
+        text += &format!("Theirs: {}\n", render_units(snapshot, false));
+
+        if !snapshot.synergies.is_empty() {
+            let synergies: Vec<String> = snapshot
+                .synergies
+                .iter()
+                .map(|synergy| format!("{} {} x{}", synergy.emoji, synergy.family, synergy.units))
+                .collect();
+            text += &format!("Synergies: {}\n", synergies.join(", "));
+        }
+
+        format!("{text}{}\n", self.render_hand())
+    }
+}
+
+/// Our units or the ones coming at us with their health, furthest along first
+fn render_units(snapshot: &BattleSnapshot, ours: bool) -> String {
+    let mut units: Vec<_> = snapshot
+        .units
+        .iter()
+        .filter(|unit| unit.ours == ours && (ours || unit.target == snapshot.side))
+        .collect();
+    units.sort_by(|a, b| b.progress.total_cmp(&a.progress));
+
+    let units: Vec<String> = units
+        .iter()
+        .map(|unit| format!("{} {}/{}", unit.emoji, unit.health, unit.max_health))
+        .collect();
+    units.join("  ")
+}
+
+/// One lane as a row of cells from our tower to the one we're attacking
+fn render_lane(snapshot: &BattleSnapshot, map: &Map, lane: usize) -> String {
+    let mut cells = vec!["· ".to_string(); LANE_CELLS];
+    let mut place = |spot: f32, emoji: char| {
+        let cell = ((spot * LANE_CELLS as f32) as usize).min(LANE_CELLS - 1);
+        cells[cell] = emoji.to_string();
+    };
+
+    // Obstacle spots are from the lower numbered side's tower
+    let flipped = snapshot.side > snapshot.target;
+    for (obstacle, health) in map.obstacles.iter().zip(&snapshot.obstacles) {
+        if obstacle.lane == lane && *health > 0 {
+            let spot = if flipped {
+                1.0 - obstacle.position
+            } else {
+                obstacle.position
+            };
+            place(spot, '🪨');
+        }
+    }
+
+    for unit in snapshot.units.iter().filter(|unit| unit.lane == lane) {
+        if unit.ours {
+            place(unit.progress, unit.emoji);
+        } else if unit.target == snapshot.side {
+            place(1.0 - unit.progress, unit.emoji);
+        }
+    }
+
+    cells.concat()
+}
+
+fn parse_command(line: &str, client: &Client) -> Result<Input, String> {
+    let Some(command) = line.strip_prefix('/') else {
+        return Ok(Input::Send(ClientMessage::new(
+            "Text",
+            Some(line.to_string()),
+        )));
+    };
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let rest: Vec<&str> = words.collect();
+
+    let slot = |index: usize| -> Result<&HandCard, String> {
+        let slot: usize = rest
+            .get(index)
+            .and_then(|slot| slot.parse().ok())
+            .ok_or("Which hand slot? Slots are numbered from 1")?;
+        client
+            .hand
+            .get(slot.wrapping_sub(1))
+            .ok_or_else(|| format!("There's no card in slot {slot}"))
+    };
+    let hand_mode = || match rest.first() {
+        Some(&"cycle") => "Cycle",
+        _ => "Fixed",
+    };
+    let message =
+        |kind: &str, data: Option<String>| Ok(Input::Send(ClientMessage::new(kind, data)));
+
+    match name {
+        "play" => message("BeginGame", Some(hand_mode().to_string())),
+        "bot" => message("BeginBotGame", rest.first().map(|name| name.to_string())),
+        "team" => message("BeginTeamGame", Some(hand_mode().to_string())),
+        "ffa" => message("BeginFreeForAll", None),
+        "invite" => match rest.first() {
+            Some(name) => message("PartyInvite", Some(name.to_string())),
+            None => Err("Who do you want to invite?".to_string()),
+        },
+        "leave" => message("LeaveParty", None),
+        "t" => message("TeamText", Some(rest.join(" "))),
+        "p" => {
+            let card = slot(0)?;
+            match card.id {
+                CardId::Unit(unit) => {
+                    let lane = match rest.get(1) {
+                        Some(lane) => Some(lane.parse().map_err(|_| "Lanes are numbered from 0")?),
+                        None => None,
+                    };
+                    let play = PlayRequest { unit, lane };
+                    message("SpawnUnit", serde_json::to_string(&play).ok())
+                }
+                CardId::Spell(spell) => {
+                    let cast = CastRequest { spell, spot: 0.5 };
+                    message("CastSpell", serde_json::to_string(&cast).ok())
+                }
+            }
+        }
+        "redraw" => match slot(0)?.id {
+            CardId::Unit(unit) => message("Mulligan", Some(unit.to_string())),
+            CardId::Spell(spell) => message("MulliganSpell", Some(spell.to_string())),
+        },
+        "ready" => message("MulliganDone", None),
+        "level" => match slot(0)?.id {
+            CardId::Unit(unit) => message("LevelUp", Some(unit.to_string())),
+            CardId::Spell(_) => Err("Only unit cards can be leveled up".to_string()),
+        },
+        "cannon" => message("FireCannon", None),
+        "upgrade" => match rest.first() {
+            Some(upgrade) => message("BuyUpgrade", Some(upgrade.to_string())),
+            None => Err("Which upgrade? MaxHealth, Income or Wallet".to_string()),
+        },
+        "target" => match rest.first().and_then(|side| side.parse::<usize>().ok()) {
+            Some(side) => message("Target", Some(side.to_string())),
+            None => Err("Which side do you want to attack?".to_string()),
+        },
+        "surrender" => message("Surrender", None),
+        "draw" => message("OfferDraw", None),
+        "accept" => message("AcceptDraw", None),
+        "rematch" => message("Rematch", None),
+        "status" => Ok(Input::Status),
+        "help" => Ok(Input::Help),
+        "quit" => Ok(Input::Quit),
+        _ => Err(format!("Unknown command /{name}, /help lists them")),
+    }
+}
+
+fn parse_args() -> Result<(String, String), String> {
+    let mut server = "ws://127.0.0.1:7878".to_string();
+    let mut name = env::var("USER").unwrap_or_else(|_| "player".to_string());
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--server" => server = value,
+            "--name" => name = value,
+            _ => return Err(format!("Unknown option '{flag}'")),
+        }
+    }
+
+    Ok((server, name))
+}
@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use td::game::engine::{PlayError, TICK};
+use td::game::entity::{Drawable, UnitId};
+use td::server::service::{ClientMessage, ResponseType, ServerResponse};
+use td::server::state::ServerError;
+use tokio::time::{sleep_until, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const USAGE: &str = "Usage: td-load [options]
+
+Connects lots of clients to a server at once. Each one joins the lobby, chats, queues for
+battles and plays units while fighting, then how quickly the server answered is printed.
+
+Options:
+    --server ws://127.0.0.1:7878   Server to load
+    --clients 50                   How many clients to connect
+    --duration 60                  Seconds to keep the load up for
+    --ramp 20                      Milliseconds between each client connecting
+    --chat-interval 10000          Average milliseconds between each client's chat messages
+    --spawn-interval 2000          Average milliseconds between each client's units while
+                                   fighting
+    --seed 0                       Seed for when clients act and which units they play";
+
+/// How long clients keep listening for answers once the load stops, anything not answered by
+/// then is counted as dropped
+const DRAIN: Duration = Duration::from_secs(5);
+/// How long clients wait in the lobby after a battle before queuing for the next
+const REQUEUE_DELAY: Duration = Duration::from_secs(3);
+
+struct Config {
+    server: String,
+    clients: usize,
+    duration: Duration,
+    ramp: Duration,
+    chat_interval: Duration,
+    spawn_interval: Duration,
+    seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: "ws://127.0.0.1:7878".to_string(),
+            clients: 50,
+            duration: Duration::from_secs(60),
+            ramp: Duration::from_millis(20),
+            chat_interval: Duration::from_secs(10),
+            spawn_interval: Duration::from_secs(2),
+            seed: 0,
+        }
+    }
+}
+
+/// What clients saw, added up across every client at the end
+#[derive(Default)]
+struct Stats {
+    connected: usize,
+    connect_failures: usize,
+    /// Clients the server hung up on before the load stopped
+    disconnects: usize,
+    messages: usize,
+    unreadable: usize,
+    snapshots: usize,
+    battles_joined: usize,
+    battles_finished: usize,
+    chats_sent: usize,
+    /// Time from sending a chat to seeing it come back from the server
+    chat_latencies: Vec<Duration>,
+    spawns_sent: usize,
+    /// Time from playing a unit to the server saying it spawned, or why it didn't
+    spawn_latencies: Vec<Duration>,
+    /// Spawns still waiting on an answer when their battle ended
+    spawns_cut_off: usize,
+    /// Errors the server answered spawns with, by message
+    spawn_errors: BTreeMap<String, usize>,
+    /// Every other error the server sent, by message
+    errors: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.connected += other.connected;
+        self.connect_failures += other.connect_failures;
+        self.disconnects += other.disconnects;
+        self.messages += other.messages;
+        self.unreadable += other.unreadable;
+        self.snapshots += other.snapshots;
+        self.battles_joined += other.battles_joined;
+        self.battles_finished += other.battles_finished;
+        self.chats_sent += other.chats_sent;
+        self.chat_latencies.extend(other.chat_latencies);
+        self.spawns_sent += other.spawns_sent;
+        self.spawn_latencies.extend(other.spawn_latencies);
+        self.spawns_cut_off += other.spawns_cut_off;
+        for (error, count) in other.spawn_errors {
+            *self.spawn_errors.entry(error).or_default() += count;
+        }
+        for (error, count) in other.errors {
+            *self.errors.entry(error).or_default() += count;
+        }
+    }
+}
+
+/// One simulated player and the requests it's still waiting to hear back about
+struct Player {
+    name: String,
+    rng: StdRng,
+    /// Unit cards in hand, the load doesn't bother with spells
+    hand: Vec<HandUnit>,
+    money: usize,
+    /// When the battle began, since every card starts it cooling down
+    fighting: Option<Instant>,
+    /// When cards played this battle are ready again
+    ready_at: HashMap<UnitId, Instant>,
+    requeue_at: Option<Instant>,
+    chats: usize,
+    pending_chats: HashMap<String, Instant>,
+    pending_spawns: VecDeque<Instant>,
+    stats: Stats,
+}
+
+#[derive(Clone, Copy)]
+struct HandUnit {
+    id: UnitId,
+    cost: usize,
+    cooldown: Duration,
+}
+
+#[tokio::main]
+async fn main() {
+    let config = Arc::new(parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        process::exit(2);
+    }));
+
+    let started = Instant::now();
+    let deadline = started + config.ramp * config.clients as u32 + config.duration;
+    let mut clients = vec![];
+    for index in 0..config.clients {
+        let config = config.clone();
+        clients.push(tokio::spawn(async move {
+            sleep_until(started + config.ramp * index as u32).await;
+            run_client(index, &config, deadline).await
+        }));
+    }
+
+    let mut stats = Stats::default();
+    for client in clients {
+        match client.await {
+            Ok(client) => stats.merge(client),
+            Err(e) => eprintln!("A client crashed: {e}"),
+        }
+    }
+
+    print!("{}", report(&mut stats, started.elapsed()));
+}
+
+async fn run_client(index: usize, config: &Config, deadline: Instant) -> Stats {
+    let mut player = Player {
+        name: format!("load-{index}"),
+        rng: StdRng::seed_from_u64(config.seed.wrapping_add(index as u64)),
+        hand: vec![],
+        money: 0,
+        fighting: None,
+        ready_at: HashMap::new(),
+        requeue_at: None,
+        chats: 0,
+        pending_chats: HashMap::new(),
+        pending_spawns: VecDeque::new(),
+        stats: Stats::default(),
+    };
+
+    let Ok((socket, _)) = connect_async(config.server.as_str()).await else {
+        player.stats.connect_failures += 1;
+        return player.stats;
+    };
+    player.stats.connected += 1;
+    let (mut writer, mut reader) = socket.split();
+
+    let mut outbox = vec![
+        ClientMessage::new("ConnectReq", Some(player.name.clone())),
+        ClientMessage::new("BeginGame", Some("Fixed".to_string())),
+    ];
+    let mut next_chat = Instant::now() + player.jitter(config.chat_interval);
+    let mut next_spawn = Instant::now() + player.jitter(config.spawn_interval);
+
+    loop {
+        for message in outbox.drain(..) {
+            let json = serde_json::to_string(&message).expect("Client messages always serialize");
+            if writer.send(Message::Text(json)).await.is_err() {
+                player.stats.disconnects += 1;
+                return player.stats;
+            }
+        }
+
+        let requeue_at = player.requeue_at.unwrap_or(deadline);
+        tokio::select! {
+            _ = sleep_until(deadline) => break,
+            _ = sleep_until(next_chat) => {
+                outbox.push(player.chat());
+                next_chat = Instant::now() + player.jitter(config.chat_interval);
+            }
+            _ = sleep_until(next_spawn) => {
+                outbox.extend(player.spawn());
+                next_spawn = Instant::now() + player.jitter(config.spawn_interval);
+            }
+            _ = sleep_until(requeue_at), if player.requeue_at.is_some() => {
+                player.requeue_at = None;
+                outbox.push(ClientMessage::new("BeginGame", Some("Fixed".to_string())));
+            }
+            message = reader.next() => match message {
+                Some(Ok(Message::Text(text))) => outbox.extend(player.handle(&text)),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    player.stats.disconnects += 1;
+                    return player.stats;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    // Stop sending anything new and give the server a moment to answer what's already sent
+    let drained = Instant::now() + DRAIN;
+    while !player.pending_chats.is_empty() || !player.pending_spawns.is_empty() {
+        tokio::select! {
+            _ = sleep_until(drained) => break,
+            message = reader.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    player.handle(&text);
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let _ = writer.send(Message::Close(None)).await;
+    player.stats
+}
+
+impl Player {
+    /// Somewhere between half and one and a half times the average, so clients don't all act
+    /// in lockstep
+    fn jitter(&mut self, average: Duration) -> Duration {
+        average.mul_f32(self.rng.gen_range(0.5..1.5))
+    }
+
+    fn chat(&mut self) -> ClientMessage {
+        self.chats += 1;
+        let text = format!("{} says hi #{}", self.name, self.chats);
+        self.pending_chats.insert(text.clone(), Instant::now());
+        self.stats.chats_sent += 1;
+
+        ClientMessage::new("Text", Some(text))
+    }
+
+    /// Plays a random unit that's cooled down and the player can afford, if it's fighting
+    fn spawn(&mut self) -> Option<ClientMessage> {
+        let began = self.fighting?;
+        let now = Instant::now();
+        let playable: Vec<HandUnit> = self
+            .hand
+            .iter()
+            .filter(|unit| unit.cost <= self.money)
+            .filter(|unit| {
+                let ready_at = self.ready_at.get(&unit.id).copied();
+                now >= ready_at.unwrap_or(began + unit.cooldown)
+            })
+            .copied()
+            .collect();
+        let unit = *playable.choose(&mut self.rng)?;
+
+        // Snapshots only come so often, so keep track of what's been spent since the last
+        self.money -= unit.cost;
+        self.ready_at.insert(unit.id, now + unit.cooldown);
+        self.pending_spawns.push_back(now);
+        self.stats.spawns_sent += 1;
+
+        Some(ClientMessage::new("SpawnUnit", Some(unit.id.to_string())))
+    }
+
+    /// Records a message from the server, returning anything the player sends back
+    fn handle(&mut self, text: &str) -> Vec<ClientMessage> {
+        self.stats.messages += 1;
+        let response = match serde_json::from_str::<ServerResponse>(text) {
+            Ok(response) => response.into_message(),
+            Err(_) => {
+                self.stats.unreadable += 1;
+                return vec![];
+            }
+        };
+
+        match response {
+            ResponseType::Chat(_, text) => {
+                if let Some(sent) = self.pending_chats.remove(&text) {
+                    self.stats.chat_latencies.push(sent.elapsed());
+                }
+            }
+            ResponseType::StartGame(..) => self.stats.battles_joined += 1,
+            ResponseType::DrawnHand(cards)
+            | ResponseType::MulliganHand(cards, _)
+            | ResponseType::HandUpdate(cards, _) => {
+                self.hand = cards
+                    .iter()
+                    .filter_map(|card| {
+                        let unit = card.unit()?;
+                        Some(HandUnit {
+                            id: unit.get_id(),
+                            cost: unit.cost(),
+                            cooldown: TICK * card.cooldown_ticks() as u32,
+                        })
+                    })
+                    .collect();
+            }
+            // Nobody's picky about their hand under load
+            ResponseType::MulliganStart(..) => {
+                return vec![ClientMessage::new("MulliganDone", None)];
+            }
+            ResponseType::BattleBegin => {
+                self.fighting = Some(Instant::now());
+                self.ready_at.clear();
+            }
+            ResponseType::BattleSnapshot(snapshot) => {
+                self.stats.snapshots += 1;
+                self.money = snapshot.money;
+            }
+            ResponseType::UnitSpawned(true, _) => self.answer_spawn(),
+            ResponseType::Win(_)
+            | ResponseType::WinByDisconnect(_)
+            | ResponseType::Lose(_)
+            | ResponseType::Draw(_) => {
+                self.stats.battles_finished += 1;
+                self.fighting = None;
+                // Whatever's still on its way is answered with the battle being over
+                self.stats.spawns_cut_off += self.pending_spawns.len();
+                self.pending_spawns.clear();
+                self.hand.clear();
+                self.requeue_at = Some(Instant::now() + REQUEUE_DELAY);
+            }
+            ResponseType::Error(error) if is_spawn_error(&error) => {
+                self.answer_spawn();
+                *self.stats.spawn_errors.entry(error).or_default() += 1;
+            }
+            ResponseType::Error(error) => *self.stats.errors.entry(error).or_default() += 1,
+            _ => {}
+        }
+
+        vec![]
+    }
+
+    fn answer_spawn(&mut self) {
+        if let Some(sent) = self.pending_spawns.pop_front() {
+            self.stats.spawn_latencies.push(sent.elapsed());
+        }
+    }
+}
+
+/// Whether the server only sends this error when a unit can't be played, like one that's still
+/// cooling down
+fn is_spawn_error(error: &str) -> bool {
+    let spawn_errors = [
+        ServerError::BattleNotStartedError,
+        ServerError::CardNotInHandError,
+        PlayError::NotEnoughMoney.into(),
+        PlayError::OnCooldown.into(),
+        PlayError::Eliminated.into(),
+        PlayError::InvalidLane.into(),
+    ];
+
+    spawn_errors
+        .iter()
+        .any(|spawn_error| spawn_error.to_string() == error)
+}
+
+fn report(stats: &mut Stats, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f32();
+    let mut text = format!(
+        "Clients:   {} connected, {} failed to connect, {} disconnected early\n\
+         Battles:   {} joined, {} finished\n\
+         Messages:  {} received ({:.0}/s), {} snapshots, {} unreadable\n",
+        stats.connected,
+        stats.connect_failures,
+        stats.disconnects,
+        stats.battles_joined,
+        stats.battles_finished,
+        stats.messages,
+        stats.messages as f32 / seconds,
+        stats.snapshots,
+        stats.unreadable
+    );
+
+    text += &format!(
+        "Chat:      {} sent, {} dropped, {}\n",
+        stats.chats_sent,
+        stats.chats_sent - stats.chat_latencies.len(),
+        percentiles(&mut stats.chat_latencies)
+    );
+    text += &format!(
+        "Spawn:     {} sent, {} dropped, {} cut off by the battle ending, {}\n",
+        stats.spawns_sent,
+        stats.spawns_sent - stats.spawn_latencies.len() - stats.spawns_cut_off,
+        stats.spawns_cut_off,
+        percentiles(&mut stats.spawn_latencies)
+    );
+
+    for (name, errors) in [
+        ("Spawn errors", &stats.spawn_errors),
+        ("Other errors", &stats.errors),
+    ] {
+        if errors.is_empty() {
+            text += &format!("{name}: none\n");
+            continue;
+        }
+
+        text += &format!("{name}:\n");
+        for (error, count) in errors {
+            text += &format!("{count:>10} {error}\n");
+        }
+    }
+
+    text
+}
+
+fn percentiles(latencies: &mut [Duration]) -> String {
+    if latencies.is_empty() {
+        return "no answers".to_string();
+    }
+
+    latencies.sort();
+    let at = |percentile: f32| {
+        let index = (latencies.len() as f32 * percentile).ceil() as usize;
+        latencies[index.clamp(1, latencies.len()) - 1].as_secs_f32() * 1000.0
+    };
+
+    format!(
+        "p50 {:.1}ms, p90 {:.1}ms, p99 {:.1}ms, max {:.1}ms",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        at(1.0)
+    )
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let number = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{flag} needs a whole number, not '{value}'"))
+        };
+
+        match flag.as_str() {
+            "--server" => config.server = value,
+            "--clients" => config.clients = number(&value)? as usize,
+            "--duration" => config.duration = Duration::from_secs(number(&value)?),
+            "--ramp" => config.ramp = Duration::from_millis(number(&value)?),
+            "--chat-interval" => config.chat_interval = Duration::from_millis(number(&value)?),
+            "--spawn-interval" => config.spawn_interval = Duration::from_millis(number(&value)?),
+            "--seed" => config.seed = number(&value)?,
+            _ => return Err(format!("Unknown option '{flag}'")),
+        }
+    }
+
+    Ok(config)
+}
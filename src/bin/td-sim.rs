@@ -0,0 +1,117 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+
+use td::game::card_gen::pack::UnitPack;
+use td::game::sim::{simulate, to_csv, SimConfig};
+
+const USAGE: &str = "Usage: td-sim [options]
+
+Fights every unit against every other unit, and has bots battle with random hands against
+each other, without a server or any waiting, then prints how each one did.
+
+Options:
+    --budgets 500,1000,2000   Money each side spends on its army or starts a battle with
+    --hands 200               Random hand pairings to battle per budget
+    --hand-size 5             Units in each random hand
+    --strategy Normal         Strategy the hands are played with
+    --seed 0                  Seed for drawing hands and playing them
+    --time-limit 180          Seconds before a fight is called a draw or a battle goes
+                              into sudden death
+    --units PATH              Unit pack directory or file, instead of the built in units
+    --format csv|json         Output format, csv by default
+    --out PATH                File to write to instead of stdout";
+
+enum Format {
+    Csv,
+    Json,
+}
+
+fn main() {
+    let (config, units, format, out) = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        process::exit(2);
+    });
+
+    let pack = match units {
+        Some(path) => UnitPack::load(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        None => UnitPack::builtin(),
+    };
+
+    let started = Instant::now();
+    let rows = simulate(&pack, &config).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    eprintln!(
+        "Simulated {} units at {} budgets in {:.1}s",
+        pack.len(),
+        config.budgets.len(),
+        started.elapsed().as_secs_f32()
+    );
+
+    let output = match format {
+        Format::Csv => to_csv(&rows),
+        Format::Json => serde_json::to_string_pretty(&rows).expect("Rows always serialize"),
+    };
+    match out {
+        Some(path) => fs::write(&path, output).unwrap_or_else(|e| {
+            eprintln!("Failed to write {}: {e}", path.display());
+            process::exit(1);
+        }),
+        None => print!("{output}"),
+    }
+}
+
+fn parse_args() -> Result<(SimConfig, Option<PathBuf>, Format, Option<PathBuf>), String> {
+    let mut config = SimConfig::default();
+    let mut units = None;
+    let mut format = Format::Csv;
+    let mut out = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let number = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{flag} needs a whole number, not '{value}'"))
+        };
+
+        match flag.as_str() {
+            "--budgets" => {
+                config.budgets = value
+                    .split(',')
+                    .map(|budget| number(budget).map(|budget| budget as usize))
+                    .collect::<Result<_, _>>()?
+            }
+            "--hands" => config.hand_pairings = number(&value)? as usize,
+            "--hand-size" => config.hand_size = number(&value)? as usize,
+            "--strategy" => config.strategy = value,
+            "--seed" => config.seed = number(&value)?,
+            "--time-limit" => config.time_limit = Duration::from_secs(number(&value)?),
+            "--units" => units = Some(PathBuf::from(value)),
+            "--format" => {
+                format = match value.as_str() {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(format!("Unknown format '{value}'")),
+                }
+            }
+            "--out" => out = Some(PathBuf::from(value)),
+            _ => return Err(format!("Unknown option '{flag}'")),
+        }
+    }
+
+    Ok((config, units, format, out))
+}
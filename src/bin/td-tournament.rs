@@ -0,0 +1,196 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::{rngs::StdRng, SeedableRng};
+use td::game::card_gen::pack::UnitPack;
+use td::game::hand::HandMode;
+use td::game::strategy::StrategyRegistry;
+use td::game::tournament::{
+    random_decks, run_tournament, Entrant, TournamentConfig, TournamentReport,
+};
+
+const USAGE: &str = "Usage: td-tournament [options]
+
+Pits computer players against each other in full battles, the same ones played on the server,
+then prints each player's rating and how often each deck won.
+
+Options:
+    --games 1000                Battles to play
+    --seed 0                    Seed for decks, hands and every random choice
+    --strategies Easy,Counter   Strategies taking part, every built in one by default. The same
+                                one can enter more than once
+    --decks 20                  Random decks to hand out
+    --deck-size 8               Units in each deck
+    --hand-mode fixed|cycle     Whether played cards are replaced, cycle by default
+    --units PATH                Unit pack directory or file, instead of the built in units
+    --format text|json          Output format, text by default";
+
+enum Format {
+    Text,
+    Json,
+}
+
+struct Args {
+    config: TournamentConfig,
+    entrants: Vec<Entrant>,
+    decks: usize,
+    deck_size: usize,
+    units: Option<PathBuf>,
+    format: Format,
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        process::exit(2);
+    });
+
+    let pack = match args.units {
+        Some(path) => UnitPack::load(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        None => UnitPack::builtin(),
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.config.seed);
+    let decks = random_decks(&pack, args.decks, args.deck_size, &mut rng);
+
+    let strategies = StrategyRegistry::default();
+    let started = Instant::now();
+    let report = run_tournament(
+        Arc::new(pack),
+        &strategies,
+        &args.entrants,
+        &decks,
+        &args.config,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    eprintln!(
+        "Played {} games with {} decks in {:.1}s",
+        args.config.games,
+        decks.len(),
+        started.elapsed().as_secs_f32()
+    );
+
+    match args.format {
+        Format::Text => print!("{}", to_text(&report)),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Reports always serialize")
+        ),
+    }
+}
+
+fn to_text(report: &TournamentReport) -> String {
+    let mut text = format!(
+        "{:<12} {:>7} {:>6} {:>6} {:>6} {:>6}\n",
+        "Strategy", "Elo", "Games", "Wins", "Losses", "Draws"
+    );
+    for standing in &report.strategies {
+        text += &format!(
+            "{:<12} {:>7.0} {:>6} {:>6} {:>6} {:>6}\n",
+            standing.name,
+            standing.elo.unwrap_or_default(),
+            standing.games,
+            standing.wins,
+            standing.losses,
+            standing.draws
+        );
+    }
+
+    text += &format!(
+        "\n{:<20} {:>8} {:>6} {:>6} {:>6} {:>6}\n",
+        "Deck", "Win rate", "Games", "Wins", "Losses", "Draws"
+    );
+    for standing in &report.decks {
+        // Emojis are two columns wide but count as one character, so pad them by hand
+        let padding = 20usize.saturating_sub(standing.name.chars().count() * 2);
+        text += &format!(
+            "{}{} {:>7.1}% {:>6} {:>6} {:>6} {:>6}\n",
+            standing.name,
+            " ".repeat(padding),
+            standing.win_rate() * 100.0,
+            standing.games,
+            standing.wins,
+            standing.losses,
+            standing.draws
+        );
+    }
+
+    text
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        config: TournamentConfig::default(),
+        entrants: StrategyRegistry::default()
+            .names()
+            .map(Entrant::new)
+            .collect(),
+        decks: 20,
+        deck_size: 8,
+        units: None,
+        format: Format::Text,
+    };
+
+    let mut flags = env::args().skip(1);
+    while let Some(flag) = flags.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{USAGE}");
+            process::exit(0);
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("{flag} needs a value"))?;
+        let number = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{flag} needs a whole number, not '{value}'"))
+        };
+
+        match flag.as_str() {
+            "--games" => args.config.games = number(&value)? as usize,
+            "--seed" => args.config.seed = number(&value)?,
+            "--strategies" => args.entrants = value.split(',').map(Entrant::new).collect(),
+            "--decks" => args.decks = number(&value)? as usize,
+            "--deck-size" => args.deck_size = number(&value)? as usize,
+            "--hand-mode" => {
+                args.config.hand_mode = match value.as_str() {
+                    "fixed" => HandMode::Fixed,
+                    "cycle" => HandMode::Cycle,
+                    _ => return Err(format!("Unknown hand mode '{value}'")),
+                }
+            }
+            "--units" => args.units = Some(PathBuf::from(value)),
+            "--format" => {
+                args.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    _ => return Err(format!("Unknown format '{value}'")),
+                }
+            }
+            _ => return Err(format!("Unknown option '{flag}'")),
+        }
+    }
+
+    // The same strategy can enter more than once, number them so the standings tell them apart
+    for i in 0..args.entrants.len() {
+        let copies = args.entrants[..i]
+            .iter()
+            .filter(|entrant| entrant.strategy == args.entrants[i].strategy)
+            .count();
+        if copies > 0 {
+            args.entrants[i].name += &format!(" {}", copies + 1);
+        }
+    }
+
+    Ok(args)
+}
@@ -1,34 +1,653 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+use super::{
+    card::Card,
+    card_gen::pack::UnitPack,
+    engine::{Economy, Field, FieldEvent, PlayError, Side, TICK},
+    entity::{DrawRules, Unit, UnitId},
+    family::Synergy,
+    hand::HandMode,
+    map::{Map, MAPS},
+    rules::MatchRules,
+    spell::{Spell, SpellEffect},
+    stage::{StageEvent, StageRun, Victory},
+    tower::{Tower, Upgrade, CANNON_DAMAGE, CANNON_RANGE, MAX_UPGRADE_LEVEL},
+};
+
+/// How long players get to redraw cards before a battle begins
+pub const MULLIGAN_DURATION: Duration = Duration::from_secs(10);
+/// Players are sent a snapshot of the battle every this many ticks
+pub const SNAPSHOT_TICKS: u64 = 5;
+/// How much more income players earn during sudden death
+pub const SUDDEN_DEATH_INCOME: usize = 2;
+/// How much more damage towers take during sudden death
+pub const SUDDEN_DEATH_DAMAGE: usize = 2;
+
+/// Players fighting together on one side of the lane, sharing a tower
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Team {
+    pub players: Vec<Uuid>,
+    pub tower: Tower,
+}
+
+impl Team {
+    pub fn new(players: Vec<Uuid>, tower_health: usize) -> Self {
+        Self {
+            players,
+            tower: Tower::new(tower_health),
+        }
+    }
+}
+
+impl Team {
+    /// A team is out of the battle once its tower has fallen
+    pub fn is_eliminated(&self) -> bool {
+        self.tower.health == 0
+    }
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct Battle {
-    pub team_a: (Uuid, Tower),
-    pub team_b: (Uuid, Tower),
+    /// Every team in the battle, indexed by their `Side`. Team battles have two, free-for-alls
+    /// have one per player
+    pub teams: Vec<Team>,
+    /// Units this battle was started with, so reloading the server's units never changes
+    /// the stats of a battle that's already running
+    pub units: Arc<UnitPack>,
+    pub hand_mode: HandMode,
+    pub draw_rules: DrawRules,
+    pub rules: MatchRules,
+    /// The map picked by the rules, which decides how many lanes there are
+    pub map: Map,
+    pub phase: BattlePhase,
+    /// Players who are done redrawing cards
+    pub ready: Vec<Uuid>,
+    /// Ticks since the fighting began
+    pub ticks: u64,
+    pub field: Field<'static>,
+    /// Money and cooldowns for every player, teammates earn and spend separately
+    pub economies: HashMap<Uuid, Economy>,
+    /// Set for PvE battles, where team b is a stage sending out waves rather than players
+    pub stage: Option<StageRun>,
+    /// Which opponent each player is sending their units at, when there's more than one
+    pub targets: HashMap<Uuid, Side>,
+    /// Sides that have offered to end the battle in a draw
+    pub draw_offers: Vec<Side>,
 }
 
 impl Battle {
-    pub fn start_battle(user_a: Uuid, user_b: Uuid) -> Self {
+    pub fn start_battle(
+        team_a: Vec<Uuid>,
+        team_b: Vec<Uuid>,
+        units: Arc<UnitPack>,
+        hand_mode: HandMode,
+        rules: MatchRules,
+    ) -> Self {
+        Self::start_teams(vec![team_a, team_b], units, hand_mode, rules)
+    }
+
+    /// Starts a battle where every player has their own tower
+    pub fn start_free_for_all(
+        players: Vec<Uuid>,
+        units: Arc<UnitPack>,
+        hand_mode: HandMode,
+        rules: MatchRules,
+    ) -> Self {
+        let teams = players.into_iter().map(|player| vec![player]).collect();
+
+        Self::start_teams(teams, units, hand_mode, rules)
+    }
+
+    fn start_teams(
+        teams: Vec<Vec<Uuid>>,
+        units: Arc<UnitPack>,
+        hand_mode: HandMode,
+        rules: MatchRules,
+    ) -> Self {
+        let economies = teams
+            .iter()
+            .flatten()
+            .map(|player| (*player, Economy::new(rules.starting_money)))
+            .collect();
+        let map = MAPS.get(rules.map).copied().unwrap_or_default();
+
         Self {
-            team_a: (user_a, Tower::default()),
-            team_b: (user_b, Tower::default()),
+            teams: teams
+                .into_iter()
+                .map(|players| Team::new(players, rules.tower_health))
+                .collect(),
+            units,
+            hand_mode,
+            draw_rules: hand_mode.draw_rules(),
+            rules,
+            map,
+            phase: BattlePhase::default(),
+            ready: vec![],
+            ticks: 0,
+            field: Field::new(map),
+            economies,
+            stage: None,
+            targets: HashMap::new(),
+            draw_offers: vec![],
         }
     }
 
-    pub fn get_enemy(&self, id: Uuid) -> Uuid {
-        if self.team_a.0 == id {
-            self.team_b.0
-        } else {
-            self.team_a.0
+    /// Starts a PvE battle for a player against a stage's tower. The stage gets an id of its
+    /// own that no user has, and is always ready to fight
+    pub fn start_stage(
+        player: Uuid,
+        stage: StageRun,
+        units: Arc<UnitPack>,
+        hand_mode: HandMode,
+        rules: MatchRules,
+    ) -> Self {
+        let stage_id = Uuid::new_v4();
+        let mut battle = Self::start_battle(vec![player], vec![stage_id], units, hand_mode, rules);
+
+        battle.teams[Side::B.index()].tower = Tower::new(stage.stage().tower_health);
+        // Stages are won or lost on their waves, never on the clock
+        battle.rules.time_limit = None;
+        battle.ready.push(stage_id);
+        battle.stage = Some(stage);
+
+        battle
+    }
+
+    /// Which team a player's tower and units belong to
+    pub fn side_of(&self, id: Uuid) -> Side {
+        let team = self
+            .teams
+            .iter()
+            .position(|team| team.players.contains(&id))
+            .unwrap_or(Side::B.index());
+
+        Side(team)
+    }
+
+    pub fn team(&self, side: Side) -> &Team {
+        &self.teams[side.index()]
+    }
+
+    pub fn sides(&self) -> impl Iterator<Item = Side> {
+        (0..self.teams.len()).map(Side)
+    }
+
+    /// Sides whose towers are still standing
+    pub fn sides_left(&self) -> Vec<Side> {
+        self.sides()
+            .filter(|side| !self.team(*side).is_eliminated())
+            .collect()
+    }
+
+    /// Everyone on the player's team, including the player
+    pub fn teammates(&self, id: Uuid) -> &[Uuid] {
+        &self.team(self.side_of(id)).players
+    }
+
+    /// Everyone on every other team
+    pub fn enemies(&self, id: Uuid) -> Vec<Uuid> {
+        let side = self.side_of(id);
+
+        self.sides()
+            .filter(|other| *other != side)
+            .flat_map(|other| self.team(other).players.iter().copied())
+            .collect()
+    }
+
+    /// Chooses which opponent a player's units are sent at
+    pub fn set_target(&mut self, id: Uuid, target: Side) -> Result<(), PlayError> {
+        if target == self.side_of(id)
+            || target.index() >= self.teams.len()
+            || self.team(target).is_eliminated()
+        {
+            return Err(PlayError::InvalidTarget);
+        }
+
+        self.targets.insert(id, target);
+        Ok(())
+    }
+
+    /// The tower a player's units are sent at, their chosen target if it's still standing,
+    /// otherwise the next standing tower after their own
+    pub fn target_of(&self, id: Uuid) -> Side {
+        let side = self.side_of(id);
+        if let Some(target) = self.targets.get(&id) {
+            if !self.team(*target).is_eliminated() {
+                return *target;
+            }
         }
+
+        (1..self.teams.len())
+            .map(|offset| Side((side.index() + offset) % self.teams.len()))
+            .find(|other| !self.team(*other).is_eliminated())
+            .unwrap_or(side.other())
     }
 
-    pub fn damage_tick(&mut self, attack_on: Uuid, dmg: usize) -> Option<usize> {
-        let tower = if self.team_a.0 == attack_on {
-            &mut self.team_a.1
-        } else {
-            &mut self.team_b.1
+    pub fn economy(&self, id: Uuid) -> &Economy {
+        &self.economies[&id]
+    }
+
+    pub fn can_play(&self, id: Uuid, card: &Card) -> Result<(), PlayError> {
+        if self.team(self.side_of(id)).is_eliminated() {
+            return Err(PlayError::Eliminated);
+        }
+
+        self.economy(id).can_play(card, self.ticks)
+    }
+
+    /// Pays for a unit and sends it out from the player's tower down one of the lanes towards
+    /// their target. If a copy at the same level just left the tower on that lane, the unit
+    /// merges into it instead and levels it up
+    pub fn play(&mut self, id: Uuid, unit: Unit<'static>, lane: usize) -> Result<(), PlayError> {
+        if lane >= self.map.lanes {
+            return Err(PlayError::InvalidLane);
+        }
+
+        let side = self.side_of(id);
+        let target = self.target_of(id);
+        self.spend(id, &Card::Unit(unit))?;
+        if self.field.merge(unit, side, target, lane).is_none() {
+            self.field.spawn_toward(unit, side, target, lane);
+        }
+
+        Ok(())
+    }
+
+    /// Pays to level up a unit card in the player's hand, returning the leveled card to put
+    /// back in its place
+    pub fn level_up(&mut self, id: Uuid, unit: Unit<'static>) -> Result<Unit<'static>, PlayError> {
+        if self.team(self.side_of(id)).is_eliminated() {
+            return Err(PlayError::Eliminated);
+        }
+        let leveled = unit.level_up().ok_or(PlayError::MaxUnitLevel)?;
+        let cost = unit.level_up_cost().ok_or(PlayError::MaxUnitLevel)?;
+
+        self.economies
+            .get_mut(&id)
+            .expect("Every player has an economy")
+            .pay(cost)?;
+
+        Ok(leveled)
+    }
+
+    /// Pays for a spell and casts it on the lane towards the player's target. `spot` is where
+    /// on the lane aimed spells land, as a fraction of the lane from the player's tower.
+    /// Returns how many units were frozen or killed, or how much the tower was healed by
+    pub fn cast(&mut self, id: Uuid, spell: Spell, spot: f32) -> Result<usize, PlayError> {
+        if !(0.0..=1.0).contains(&spot) {
+            return Err(PlayError::InvalidTarget);
+        }
+
+        let side = self.side_of(id);
+        let target = self.target_of(id);
+        self.spend(id, &Card::Spell(spell))?;
+
+        let affected = match spell.effect() {
+            SpellEffect::Freeze { millis } => {
+                let ticks = millis / TICK.as_millis() as u64;
+                self.field.freeze(side, target, ticks)
+            }
+            SpellEffect::Heal { amount } => {
+                let tower = &mut self.teams[side.index()].tower;
+                let healed = amount.min(tower.max_health - tower.health);
+                tower.health += healed;
+                healed
+            }
+            SpellEffect::Meteor { damage, radius } => {
+                let events = self.field.strike(side, target, spot, radius, damage);
+                for event in &events {
+                    if let FieldEvent::UnitKilled { unit, killer, .. } = event {
+                        self.pay_bounty(unit, *killer);
+                    }
+                }
+                events.len()
+            }
+        };
+
+        Ok(affected)
+    }
+
+    fn spend(&mut self, id: Uuid, card: &Card) -> Result<(), PlayError> {
+        self.can_play(id, card)?;
+        self.economies
+            .get_mut(&id)
+            .expect("Every player has an economy")
+            .spend(card, self.ticks)
+    }
+
+    /// Fires the cannon on the player's tower, blasting the enemy units closing in on it.
+    /// Returns how many units were killed
+    pub fn fire_cannon(&mut self, id: Uuid) -> Result<usize, PlayError> {
+        let side = self.side_of(id);
+        let tower = &mut self.teams[side.index()].tower;
+        if tower.health == 0 {
+            return Err(PlayError::Eliminated);
+        }
+        if !tower.cannon_ready() {
+            return Err(PlayError::CannonNotReady);
+        }
+        tower.cannon_charge = 0;
+
+        let events = self.field.blast(side, CANNON_RANGE, CANNON_DAMAGE);
+        for event in &events {
+            if let FieldEvent::UnitKilled { unit, killer, .. } = event {
+                self.pay_bounty(unit, *killer);
+            }
+        }
+
+        Ok(events.len())
+    }
+
+    /// What a player's next level of an upgrade costs, or `None` if it's maxed out
+    pub fn upgrade_cost(&self, id: Uuid, upgrade: Upgrade) -> Option<usize> {
+        let level = self.upgrade_level(id, upgrade);
+
+        (level < MAX_UPGRADE_LEVEL).then(|| upgrade.cost(level))
+    }
+
+    pub fn upgrade_level(&self, id: Uuid, upgrade: Upgrade) -> usize {
+        match upgrade {
+            Upgrade::MaxHealth => self.team(self.side_of(id)).tower.health_level,
+            Upgrade::Income => self.economy(id).income_level,
+            Upgrade::Wallet => self.economy(id).wallet_level,
+        }
+    }
+
+    /// Buys the next level of an upgrade with the player's money, returning the new level.
+    /// Max health upgrades are for the whole team's tower, the rest are the player's own
+    pub fn buy_upgrade(&mut self, id: Uuid, upgrade: Upgrade) -> Result<usize, PlayError> {
+        let side = self.side_of(id);
+        if self.team(side).is_eliminated() {
+            return Err(PlayError::Eliminated);
+        }
+        let cost = self
+            .upgrade_cost(id, upgrade)
+            .ok_or(PlayError::MaxUpgradeLevel)?;
+
+        let economy = self
+            .economies
+            .get_mut(&id)
+            .expect("Every player has an economy");
+        economy.pay(cost)?;
+
+        match upgrade {
+            Upgrade::MaxHealth => self.teams[side.index()].tower.upgrade_health(),
+            Upgrade::Income => economy.income_level += 1,
+            Upgrade::Wallet => economy.wallet_level += 1,
+        }
+
+        Ok(self.upgrade_level(id, upgrade))
+    }
+
+    /// Splits a killed unit's bounty between everyone on the team that got the kill
+    fn pay_bounty(&mut self, unit: &Unit, killer: Side) {
+        let killers = &self.teams[killer.index()].players;
+        let bounty = unit.cost / 4 / killers.len().max(1);
+        for killer in killers {
+            if let Some(economy) = self.economies.get_mut(killer) {
+                economy.earn(bounty);
+            }
+        }
+    }
+
+    /// Advances the battle by one tick, paying out income and bounties and applying damage to
+    /// the towers
+    pub fn tick(&mut self) -> Vec<BattleEvent> {
+        self.ticks += 1;
+        let mut events = vec![];
+
+        if Some(self.ticks) == self.rules.sudden_death_tick() {
+            events.push(BattleEvent::SuddenDeath);
+        }
+        if self
+            .rules
+            .tiebreak_tick()
+            .is_some_and(|tick| self.ticks >= tick)
+        {
+            events.push(BattleEvent::TimeUp {
+                winner: self.tiebreak(),
+            });
+            return events;
+        }
+
+        let (income, damage_multiplier) = match self.is_sudden_death() {
+            true => (SUDDEN_DEATH_INCOME, SUDDEN_DEATH_DAMAGE),
+            false => (1, 1),
+        };
+        if self.ticks.is_multiple_of(self.rules.income_ticks) {
+            for economy in self.economies.values_mut() {
+                economy.earn(economy.income() * income);
+            }
+        }
+        for team in self.teams.iter_mut().filter(|team| !team.is_eliminated()) {
+            team.tower.charge_cannon();
+        }
+
+        if let Some(stage) = &mut self.stage {
+            for event in stage.tick(self.ticks, &self.units) {
+                match event {
+                    StageEvent::WaveStarted { wave, boss } => {
+                        events.push(BattleEvent::WaveStarted {
+                            wave,
+                            waves: stage.stage().waves.len(),
+                            boss,
+                        })
+                    }
+                    // Stages spread their units out over the lanes
+                    StageEvent::Spawn(unit) => {
+                        let lane = self.ticks as usize % self.map.lanes;
+                        self.field.spawn_toward(unit, Side::B, Side::A, lane);
+                    }
+                }
+            }
+        }
+
+        for event in self.field.tick() {
+            match event {
+                FieldEvent::UnitKilled { unit, side, killer } => {
+                    self.pay_bounty(&unit, killer);
+                    events.push(BattleEvent::UnitKilled {
+                        side,
+                        killer,
+                        cost: unit.cost,
+                    });
+                }
+                FieldEvent::TowerHit { tower, damage } => {
+                    if self.team(tower).is_eliminated() {
+                        continue;
+                    }
+
+                    match self.damage_tower(tower, damage * damage_multiplier) {
+                        Some(health) => events.push(BattleEvent::TowerDamaged {
+                            side: tower,
+                            health,
+                        }),
+                        None => match self.eliminate(tower) {
+                            Some(winner) => {
+                                events.push(BattleEvent::Finished { winner });
+                                return events;
+                            }
+                            None => events.push(BattleEvent::Eliminated { side: tower }),
+                        },
+                    }
+                }
+            }
+        }
+
+        if let Some(stage) = &self.stage {
+            let survived = stage.stage().victory == Victory::Survive
+                && stage.is_done()
+                && self.field.units().iter().all(|unit| unit.side == Side::A);
+
+            if survived {
+                events.push(BattleEvent::Finished { winner: Side::A });
+            }
+        }
+
+        events
+    }
+
+    /// Whether the time limit has run out and sudden death has begun
+    pub fn is_sudden_death(&self) -> bool {
+        self.rules
+            .sudden_death_tick()
+            .is_some_and(|tick| self.ticks >= tick)
+    }
+
+    /// Ticks left until sudden death begins, or until the tiebreak once it has. `None` for
+    /// battles without a time limit
+    pub fn ticks_left(&self) -> Option<u64> {
+        let deadline = match self.is_sudden_death() {
+            true => self.rules.tiebreak_tick(),
+            false => self.rules.sudden_death_tick(),
         };
 
+        deadline.map(|tick| tick.saturating_sub(self.ticks))
+    }
+
+    /// The side still standing with the most tower health, or `None` if it's a tie
+    pub fn tiebreak(&self) -> Option<Side> {
+        let left = self.sides_left();
+        let best = left
+            .iter()
+            .map(|side| self.team(*side).tower.health)
+            .max()?;
+        let mut leaders = left
+            .into_iter()
+            .filter(|side| self.team(*side).tower.health == best);
+
+        match (leaders.next(), leaders.next()) {
+            (Some(winner), None) => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// What a player can see of the battle, with every position relative to their own tower.
+    /// Players who have been eliminated spectate from the view of a side still standing
+    pub fn snapshot(&self, id: Uuid) -> BattleSnapshot {
+        let own_side = self.side_of(id);
+        let spectating = self.team(own_side).is_eliminated();
+        let side = match spectating {
+            true => self.sides_left().first().copied().unwrap_or(own_side),
+            false => own_side,
+        };
+
+        BattleSnapshot {
+            money: self.economy(id).money,
+            tower_health: self.team(side).tower.health,
+            max_tower_health: self.team(side).tower.max_health,
+            cannon_charge: self.team(side).tower.cannon_progress(),
+            wallet: self.economy(id).wallet(),
+            upgrades: [Upgrade::MaxHealth, Upgrade::Income, Upgrade::Wallet]
+                .into_iter()
+                .map(|upgrade| UpgradeSnapshot {
+                    upgrade,
+                    level: self.upgrade_level(id, upgrade),
+                    cost: self.upgrade_cost(id, upgrade),
+                })
+                .collect(),
+            enemy_tower_health: self.team(self.target_of(id)).tower.health,
+            side: side.index(),
+            target: self.target_of(id).index(),
+            spectating,
+            towers: self.teams.iter().map(|team| team.tower.health).collect(),
+            seconds_left: self
+                .ticks_left()
+                .map(|ticks| ticks * TICK.as_millis() as u64 / 1000),
+            sudden_death: self.is_sudden_death(),
+            obstacles: {
+                let target = self.target_of(id);
+                self.field.obstacles((side.min(target), side.max(target)))
+            },
+            synergies: self.field.synergies(side),
+            units: self
+                .field
+                .units()
+                .iter()
+                .map(|unit| UnitSnapshot {
+                    id: unit.id,
+                    unit: unit.unit.get_id(),
+                    emoji: unit.unit.emoji,
+                    size: unit.unit.size,
+                    ours: unit.side == side,
+                    side: unit.side.index(),
+                    target: unit.target.index(),
+                    lane: unit.lane,
+                    level: unit.unit.get_level(),
+                    progress: unit.progress(),
+                    health: unit.health,
+                    max_health: unit.unit.health,
+                    attack_charge: unit.attack_charge,
+                })
+                .collect(),
+        }
+    }
+
+    /// Every player on both teams
+    pub fn players(&self) -> Vec<Uuid> {
+        self.teams
+            .iter()
+            .flat_map(|team| team.players.iter().copied())
+            .collect()
+    }
+
+    /// Marks a player as done with their mulligan, returning true once every player is
+    pub fn mark_ready(&mut self, id: Uuid) -> bool {
+        if !self.ready.contains(&id) {
+            self.ready.push(id);
+        }
+
+        self.players()
+            .iter()
+            .all(|player| self.ready.contains(player))
+    }
+
+    /// Ends the mulligan phase, returning false if the battle had already begun
+    pub fn begin_fighting(&mut self) -> bool {
+        let was_mulligan = self.phase == BattlePhase::Mulligan;
+        self.phase = BattlePhase::Fighting;
+
+        was_mulligan
+    }
+
+    /// Knocks a side out of the battle, returning the winner if only one side is left standing
+    pub fn eliminate(&mut self, side: Side) -> Option<Side> {
+        self.teams[side.index()].tower.health = 0;
+
+        // The fallen side's units leave the field, as does anything that was still heading
+        // for their tower
+        self.field.clear_side(side);
+
+        match self.sides_left()[..] {
+            [winner] => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Offers a draw on behalf of a side, returning true once every side still standing has
+    /// offered one
+    pub fn offer_draw(&mut self, side: Side) -> bool {
+        if !self.draw_offers.contains(&side) {
+            self.draw_offers.push(side);
+        }
+
+        self.sides_left()
+            .iter()
+            .all(|side| self.draw_offers.contains(side))
+    }
+
+    /// Whether a side other than this one has offered a draw
+    pub fn draw_offered_to(&self, side: Side) -> bool {
+        self.draw_offers.iter().any(|offer| *offer != side)
+    }
+
+    /// Damages a team's tower, returning its remaining health or `None` if it was destroyed
+    pub fn damage_tower(&mut self, side: Side, dmg: usize) -> Option<usize> {
+        let tower = &mut self.teams[side.index()].tower;
+
         if dmg >= tower.health {
             tower.health = 0;
             None
@@ -41,12 +660,368 @@ impl Battle {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Tower {
+pub enum BattleEvent {
+    TowerDamaged {
+        side: Side,
+        health: usize,
+    },
+    Finished {
+        winner: Side,
+    },
+    /// The time limit ran out, towers take extra damage and income is boosted from now on
+    SuddenDeath,
+    /// Sudden death ended without a tower falling. The side with the most tower health wins,
+    /// or it's a draw if the best are tied
+    TimeUp {
+        winner: Option<Side>,
+    },
+    /// A side's tower fell but more than one is still standing, so the battle goes on
+    Eliminated {
+        side: Side,
+    },
+    /// A unit on `side` was killed by a unit on `killer`'s
+    UnitKilled {
+        side: Side,
+        killer: Side,
+        cost: usize,
+    },
+    /// A stage sent out its next wave, `wave` counts from 1
+    WaveStarted {
+        wave: usize,
+        waves: usize,
+        boss: bool,
+    },
+}
+
+/// The state of a battle as sent to one of its players
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BattleSnapshot {
+    pub money: usize,
+    pub tower_health: usize,
+    pub max_tower_health: usize,
+    /// How charged the player's tower cannon is, from 0.0 to 1.0
+    pub cannon_charge: f32,
+    /// Most money the player can hold
+    pub wallet: usize,
+    pub upgrades: Vec<UpgradeSnapshot>,
+    /// Health of the tower the player is sending units at
+    pub enemy_tower_health: usize,
+    /// The side the snapshot is seen from, and the side the player is sending units at
+    pub side: usize,
+    pub target: usize,
+    /// True once the player's tower has fallen and they're watching the rest of the battle
+    pub spectating: bool,
+    /// Health of every side's tower, indexed by side
+    pub towers: Vec<usize>,
+    /// Seconds until sudden death, or until the tiebreak during sudden death
+    pub seconds_left: Option<u64>,
+    pub sudden_death: bool,
+    /// Health left on each of the map's obstacles between the player's tower and their target
+    pub obstacles: Vec<usize>,
+    /// Family buffs the side the snapshot is seen from has earned
+    pub synergies: Vec<Synergy>,
+    pub units: Vec<UnitSnapshot>,
+}
+
+/// How far a player has upgraded something, and what the next level costs if there is one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeSnapshot {
+    pub upgrade: Upgrade,
+    pub level: usize,
+    pub cost: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnitSnapshot {
+    pub id: u64,
+    pub unit: UnitId,
+    pub emoji: char,
+    pub size: f32,
+    pub ours: bool,
+    pub side: usize,
+    pub target: usize,
+    pub lane: usize,
+    /// Levels gained from merging or leveling up its card
+    pub level: usize,
+    /// How far the unit has walked from its own tower, from 0.0 to 1.0
+    pub progress: f32,
     pub health: usize,
+    pub max_health: usize,
+    pub attack_charge: f32,
 }
 
-impl Default for Tower {
-    fn default() -> Self {
-        Self { health: 15000 }
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum BattlePhase {
+    /// Players can redraw cards from their hand and income hasn't started yet
+    #[default]
+    Mulligan,
+    Fighting,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::{Battle, BattleEvent};
+    use crate::game::{
+        card_gen::{pack::UnitPack, SPELLS},
+        engine::{PlayError, Side},
+        hand::HandMode,
+        rules::MatchRules,
+        tower::{Upgrade, CANNON_CHARGE_TICKS, MAX_UPGRADE_LEVEL},
+    };
+
+    /// A one on one battle between two new players, played with the given rules
+    fn duel(rules: MatchRules) -> (Battle, [Uuid; 2]) {
+        let players = [Uuid::new_v4(), Uuid::new_v4()];
+        let battle = Battle::start_battle(
+            vec![players[0]],
+            vec![players[1]],
+            Arc::new(UnitPack::builtin()),
+            HandMode::Fixed,
+            rules,
+        );
+
+        (battle, players)
+    }
+
+    #[test]
+    fn teammates_share_a_tower_but_not_money() {
+        let team_a = vec![Uuid::new_v4(), Uuid::new_v4()];
+        let team_b = vec![Uuid::new_v4(), Uuid::new_v4()];
+        let mut battle = Battle::start_battle(
+            team_a.clone(),
+            team_b.clone(),
+            Arc::new(UnitPack::builtin()),
+            HandMode::Fixed,
+            MatchRules::default(),
+        );
+
+        assert_eq!(battle.teammates(team_a[1]), team_a.as_slice());
+        assert_eq!(battle.enemies(team_a[1]), team_b.as_slice());
+        assert_eq!(battle.side_of(team_b[1]), Side::B);
+
+        let unit = *battle.units.get(0).unwrap();
+        battle.economies.get_mut(&team_a[0]).unwrap().money = 10_000;
+        battle.ticks = 10_000;
+        battle.play(team_a[0], unit, 0).unwrap();
+        assert_ne!(
+            battle.economy(team_a[0]).money,
+            battle.economy(team_a[1]).money
+        );
+
+        let health = battle.team(Side::A).tower.health;
+        assert_eq!(battle.damage_tower(Side::A, 100), Some(health - 100));
+        assert_eq!(
+            battle.snapshot(team_a[1]).tower_health,
+            battle.snapshot(team_a[0]).tower_health
+        );
+    }
+
+    #[test]
+    fn every_player_has_to_be_ready() {
+        let players: Vec<_> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let mut battle = Battle::start_battle(
+            players[..2].to_vec(),
+            players[2..].to_vec(),
+            Arc::new(UnitPack::builtin()),
+            HandMode::Fixed,
+            MatchRules::default(),
+        );
+
+        for player in &players[..3] {
+            assert!(!battle.mark_ready(*player));
+        }
+        assert!(battle.mark_ready(players[3]));
+    }
+
+    #[test]
+    fn destroying_a_tower_finishes_the_battle() {
+        let (mut battle, _) = duel(MatchRules::default());
+        battle.teams[1].tower.health = 1;
+        battle.field.spawn(*battle.units.get(0).unwrap(), Side::A);
+
+        let finished = (0..100_000)
+            .flat_map(|_| battle.tick())
+            .find(|event| matches!(event, BattleEvent::Finished { .. }));
+        assert_eq!(finished, Some(BattleEvent::Finished { winner: Side::A }));
+    }
+
+    #[test]
+    fn free_for_alls_go_on_until_one_tower_is_left() {
+        let players: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut battle = Battle::start_free_for_all(
+            players.clone(),
+            Arc::new(UnitPack::builtin()),
+            HandMode::Fixed,
+            MatchRules::default(),
+        );
+
+        assert_eq!(battle.target_of(players[0]), Side(1));
+        battle.set_target(players[0], Side(2)).unwrap();
+        assert_eq!(battle.target_of(players[0]), Side(2));
+
+        assert_eq!(battle.eliminate(Side(2)), None);
+        assert!(battle.snapshot(players[2]).spectating);
+        // Once the chosen target falls, units go after whoever is left
+        assert_eq!(battle.target_of(players[0]), Side(1));
+
+        assert_eq!(battle.eliminate(Side(1)), Some(Side(0)));
+    }
+
+    #[test]
+    fn battles_are_played_with_their_rules() {
+        let rules = MatchRules {
+            tower_health: 500,
+            starting_money: 10,
+            income_ticks: 5,
+            ..Default::default()
+        };
+        let (mut battle, players) = duel(rules);
+
+        assert_eq!(battle.team(Side::B).tower.health, 500);
+        assert_eq!(battle.economy(players[0]).money, 10);
+
+        for _ in 0..10 {
+            battle.tick();
+        }
+        assert_eq!(battle.economy(players[0]).money, 12);
+    }
+
+    #[test]
+    fn running_out_of_time_goes_to_sudden_death_then_a_tiebreak() {
+        let rules = MatchRules {
+            time_limit: Some(1),
+            sudden_death: 1,
+            ..Default::default()
+        };
+        let (mut battle, _) = duel(rules);
+        battle.damage_tower(Side::B, 10);
+
+        let events: Vec<_> = (0..rules.tiebreak_tick().unwrap())
+            .flat_map(|_| battle.tick())
+            .collect();
+
+        assert!(battle.is_sudden_death());
+        assert!(events.contains(&BattleEvent::SuddenDeath));
+        assert_eq!(
+            events.last(),
+            Some(&BattleEvent::TimeUp {
+                winner: Some(Side::A)
+            })
+        );
+
+        battle.damage_tower(Side::A, 10);
+        assert_eq!(battle.tiebreak(), None);
+    }
+
+    #[test]
+    fn draws_need_every_side_standing_to_agree() {
+        let players: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut battle = Battle::start_free_for_all(
+            players,
+            Arc::new(UnitPack::builtin()),
+            HandMode::Fixed,
+            MatchRules::default(),
+        );
+
+        assert!(!battle.draw_offered_to(Side(1)));
+        assert!(!battle.offer_draw(Side(0)));
+        assert!(battle.draw_offered_to(Side(1)));
+        assert!(!battle.draw_offered_to(Side(0)));
+
+        // Sides that are out of the battle don't get a say
+        battle.eliminate(Side(2));
+        assert!(battle.offer_draw(Side(1)));
+    }
+
+    #[test]
+    fn upgrades_cost_money_and_max_out() {
+        let (mut battle, players) = duel(MatchRules::default());
+
+        assert_eq!(
+            battle.buy_upgrade(players[0], Upgrade::Income),
+            Err(PlayError::NotEnoughMoney)
+        );
+
+        battle.economies.get_mut(&players[0]).unwrap().money = 10_000;
+        for level in 1..=MAX_UPGRADE_LEVEL {
+            assert_eq!(
+                battle.buy_upgrade(players[0], Upgrade::MaxHealth),
+                Ok(level)
+            );
+        }
+        assert_eq!(
+            battle.buy_upgrade(players[0], Upgrade::MaxHealth),
+            Err(PlayError::MaxUpgradeLevel)
+        );
+        assert!(battle.team(Side::A).tower.max_health > battle.team(Side::B).tower.max_health);
+        assert_eq!(battle.upgrade_level(players[1], Upgrade::MaxHealth), 0);
+    }
+
+    #[test]
+    fn cannons_have_to_charge_before_firing() {
+        let (mut battle, players) = duel(MatchRules::default());
+
+        assert_eq!(
+            battle.fire_cannon(players[0]),
+            Err(PlayError::CannonNotReady)
+        );
+
+        for _ in 0..CANNON_CHARGE_TICKS {
+            battle.tick();
+        }
+        assert_eq!(battle.fire_cannon(players[0]), Ok(0));
+        assert_eq!(battle.team(Side::A).tower.cannon_charge, 0);
+    }
+
+    #[test]
+    fn spells_cost_money_and_heal_up_to_max_health() {
+        let (mut battle, players) = duel(MatchRules::default());
+        let heal = *SPELLS
+            .iter()
+            .find(|spell| spell.get_name() == "Mend")
+            .unwrap();
+        battle.ticks = 10_000;
+
+        assert_eq!(
+            battle.cast(players[0], heal, 0.0),
+            Err(PlayError::NotEnoughMoney)
+        );
+
+        battle.economies.get_mut(&players[0]).unwrap().money = 1000;
+        battle.teams[0].tower.health -= 10;
+        assert_eq!(battle.cast(players[0], heal, 0.0), Ok(10));
+        assert_eq!(battle.economy(players[0]).money, 1000 - heal.cost);
+        assert_eq!(
+            battle.cast(players[0], heal, 0.0),
+            Err(PlayError::OnCooldown)
+        );
+        assert_eq!(
+            battle.cast(players[1], heal, 1.5),
+            Err(PlayError::InvalidTarget)
+        );
+    }
+
+    #[test]
+    fn units_are_sent_down_the_lane_they_pick() {
+        let rules = MatchRules {
+            map: 2,
+            ..Default::default()
+        };
+        let (mut battle, players) = duel(rules);
+        let unit = *battle.units.get(0).unwrap();
+        battle.ticks = 10_000;
+        battle.economies.get_mut(&players[0]).unwrap().money = 10_000;
+
+        assert_eq!(battle.map.lanes, 3);
+        assert_eq!(
+            battle.play(players[0], unit, 3),
+            Err(PlayError::InvalidLane)
+        );
+        assert_eq!(battle.play(players[0], unit, 2), Ok(()));
+        assert_eq!(battle.field.units()[0].lane, 2);
     }
 }
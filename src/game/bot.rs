@@ -0,0 +1,318 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    engine::{Field, FieldUnit, Side},
+    entity::{Unit, UnitId},
+    spell::Spell,
+};
+
+/// Enemy units closer than this to the bot's tower, as a fraction of the lane, are a threat
+const THREAT_RANGE: f32 = 0.5;
+
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Ticks between each time the bot looks at the board
+    fn think_ticks(&self) -> u64 {
+        match self {
+            Self::Easy => 150,
+            Self::Normal => 80,
+            Self::Hard => 30,
+        }
+    }
+
+    /// How many ticks of income the bot is willing to wait for to afford a better card
+    fn patience_ticks(&self) -> u64 {
+        match self {
+            Self::Easy => 0,
+            Self::Normal => 500,
+            Self::Hard => 1000,
+        }
+    }
+}
+
+/// Everything a bot knows about the battle when deciding what to play
+pub struct Observation<'a, 'b> {
+    /// Unit cards in the hand
+    pub hand: &'b [Unit<'a>],
+    /// Whether each unit card in the hand is off cooldown
+    pub ready: &'b [bool],
+    /// Spell cards in the hand
+    pub spells: &'b [Spell<'a>],
+    /// Whether each spell card in the hand is off cooldown
+    pub spells_ready: &'b [bool],
+    pub money: usize,
+    /// Ticks between each coin of income, from the battle's rules
+    pub income_ticks: u64,
+    pub field: &'b Field<'a>,
+    pub side: Side,
+    /// Health of every side's tower, indexed by side
+    pub tower_health: &'b [usize],
+}
+
+impl<'a, 'b> Observation<'a, 'b> {
+    /// Cards the bot can play right now
+    pub fn playable(&self) -> Vec<Unit<'a>> {
+        self.hand
+            .iter()
+            .zip(self.ready)
+            .filter(|(unit, ready)| **ready && unit.cost <= self.money)
+            .map(|(unit, _)| *unit)
+            .collect()
+    }
+
+    /// Spells the bot can cast right now
+    pub fn castable(&self) -> Vec<Spell<'a>> {
+        self.spells
+            .iter()
+            .zip(self.spells_ready)
+            .filter(|(spell, ready)| **ready && spell.cost <= self.money)
+            .map(|(spell, _)| *spell)
+            .collect()
+    }
+
+    /// The enemy unit closing in on the bot's tower that's got the least way left to go
+    pub fn nearest_threat(&self) -> Option<&'b FieldUnit<'a>> {
+        self.field
+            .units()
+            .iter()
+            .filter(|unit| unit.target == self.side && unit.progress() > 1.0 - THREAT_RANGE)
+            .max_by(|a, b| a.progress().total_cmp(&b.progress()))
+    }
+
+    /// Combined strength of the enemy units closing in on the bot's tower
+    pub fn threat(&self) -> usize {
+        (0..self.field.lanes())
+            .map(|lane| self.lane_threat(lane))
+            .sum()
+    }
+
+    /// Combined strength of the enemy units closing in on the bot's tower down one lane
+    pub fn lane_threat(&self, lane: usize) -> usize {
+        self.field
+            .units()
+            .iter()
+            .filter(|unit| unit.target == self.side && unit.progress() > 1.0 - THREAT_RANGE)
+            .filter(|unit| unit.lane == lane)
+            .map(|unit| unit.health * unit.unit.power)
+            .sum()
+    }
+
+    /// The lane under the most threat, or the middle lane when nothing is coming
+    pub fn lane_to_defend(&self) -> usize {
+        let middle = self.field.lanes() / 2;
+
+        (0..self.field.lanes())
+            .filter(|lane| self.lane_threat(*lane) > 0)
+            .max_by_key(|lane| self.lane_threat(*lane))
+            .unwrap_or(middle)
+    }
+}
+
+/// How strong a unit is for what it costs to play
+fn value(unit: &Unit) -> f32 {
+    (unit.health * unit.power) as f32 / unit.cost.max(1) as f32
+}
+
+/// Strength of a unit in a fight, ignoring cost
+fn strength(unit: &Unit) -> usize {
+    unit.health * unit.power
+}
+
+/// A computer controlled player that decides which card to play from its hand
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bot {
+    difficulty: Difficulty,
+    ticks_until_think: u64,
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            difficulty,
+            ticks_until_think: difficulty.think_ticks(),
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Whether the bot looked at the board the last time it acted
+    pub fn just_thought(&self) -> bool {
+        self.ticks_until_think == self.difficulty.think_ticks()
+    }
+
+    /// Called every tick of the battle, returns the card the bot wants to play if any
+    pub fn act<R: Rng>(&mut self, observation: &Observation, rng: &mut R) -> Option<UnitId> {
+        self.ticks_until_think = self.ticks_until_think.saturating_sub(1);
+        if self.ticks_until_think > 0 {
+            return None;
+        }
+        self.ticks_until_think = self.difficulty.think_ticks();
+
+        let playable = observation.playable();
+        if playable.is_empty() {
+            return None;
+        }
+
+        let choice = match self.difficulty {
+            Difficulty::Easy => {
+                if rng.gen_bool(0.5) {
+                    playable.choose(rng).copied()
+                } else {
+                    None
+                }
+            }
+            Difficulty::Normal => {
+                if observation.threat() > 0 {
+                    playable.iter().max_by_key(|unit| strength(unit)).copied()
+                } else {
+                    self.save_for_best(observation, &playable, |unit| strength(unit) as f32)
+                }
+            }
+            Difficulty::Hard => {
+                let threat = observation.threat();
+                if threat > 0 {
+                    // Cheapest unit that can trade with what's coming, otherwise the best we have
+                    playable
+                        .iter()
+                        .filter(|unit| strength(unit) >= threat)
+                        .min_by_key(|unit| unit.cost)
+                        .or_else(|| playable.iter().max_by_key(|unit| strength(unit)))
+                        .copied()
+                } else {
+                    self.save_for_best(observation, &playable, value)
+                }
+            }
+        };
+
+        choice.map(|unit| unit.get_id())
+    }
+
+    /// Plays the best card by `score` the bot could afford within its patience, waiting for
+    /// the money if it can't afford it yet
+    fn save_for_best<'a>(
+        &self,
+        observation: &Observation<'a, '_>,
+        playable: &[Unit<'a>],
+        score: impl Fn(&Unit) -> f32,
+    ) -> Option<Unit<'a>> {
+        let budget = observation.money
+            + (self.difficulty.patience_ticks() / observation.income_ticks) as usize;
+
+        let target = observation
+            .hand
+            .iter()
+            .filter(|unit| unit.cost <= budget)
+            .max_by(|a, b| score(a).total_cmp(&score(b)))?;
+
+        playable
+            .iter()
+            .find(|unit| unit.get_id() == target.get_id())
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{Bot, Difficulty, Observation};
+    use crate::game::{
+        card_gen::UNITS,
+        engine::{Field, Side},
+        entity::Unit,
+    };
+
+    fn unit(name: &str) -> Unit<'static> {
+        *UNITS.iter().find(|unit| unit.get_name() == name).unwrap()
+    }
+
+    /// Runs the bot until it next thinks
+    fn decide(difficulty: Difficulty, observation: &Observation) -> Option<usize> {
+        let mut bot = Bot::new(difficulty);
+        let mut rng = StdRng::seed_from_u64(32);
+
+        (0..difficulty.think_ticks())
+            .filter_map(|_| bot.act(observation, &mut rng))
+            .next()
+    }
+
+    #[test]
+    fn bots_only_play_cards_they_can_afford_and_are_ready() {
+        let hand = [unit("Star"), unit("Moon"), unit("Smiley")];
+        let field = Field::default();
+
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            let observation = Observation {
+                hand: &hand,
+                ready: &[false, true, true],
+                money: 100,
+                income_ticks: 3,
+                field: &field,
+                side: Side::B,
+                spells: &[],
+                spells_ready: &[],
+                tower_health: &[1000, 1000],
+            };
+
+            assert_eq!(
+                decide(difficulty, &observation),
+                Some(unit("Smiley").get_id())
+            );
+        }
+    }
+
+    #[test]
+    fn hard_bot_defends_against_incoming_units() {
+        let hand = [unit("Star"), unit("Moon"), unit("Hippo")];
+        let mut field = Field::default();
+        field.spawn(unit("Boar"), Side::A);
+        for _ in 0..5000 {
+            field.tick();
+        }
+
+        let observation = Observation {
+            hand: &hand,
+            ready: &[true, true, true],
+            money: 5000,
+            income_ticks: 3,
+            field: &field,
+            side: Side::B,
+            spells: &[],
+            spells_ready: &[],
+            tower_health: &[1000, 1000],
+        };
+
+        let choice = decide(Difficulty::Hard, &observation);
+        assert_eq!(choice, Some(unit("Hippo").get_id()));
+    }
+
+    #[test]
+    fn normal_bot_saves_up_for_a_better_card_when_safe() {
+        let hand = [unit("Star"), unit("Boar")];
+        let field = Field::default();
+
+        let observation = Observation {
+            hand: &hand,
+            ready: &[true, true],
+            money: 300,
+            income_ticks: 3,
+            field: &field,
+            side: Side::B,
+            spells: &[],
+            spells_ready: &[],
+            tower_health: &[1000, 1000],
+        };
+
+        assert_eq!(decide(Difficulty::Normal, &observation), None);
+    }
+}
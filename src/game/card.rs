@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    engine::TICK,
+    entity::{Drawable, Rarity, Unit, UnitId},
+    spell::{Spell, SpellId},
+};
+
+/// Anything that can be drawn into a hand and played from it. Sent to clients as the card's
+/// own fields with a `card` field saying which kind it is
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(tag = "card")]
+pub enum Card<'a> {
+    #[serde(borrow)]
+    Unit(Unit<'a>),
+    #[serde(borrow)]
+    Spell(Spell<'a>),
+}
+
+/// Identifies a card in a hand, since units and spells are numbered separately
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum CardId {
+    Unit(UnitId),
+    Spell(SpellId),
+}
+
+impl<'a> Card<'a> {
+    pub fn id(&self) -> CardId {
+        match self {
+            Self::Unit(unit) => CardId::Unit(unit.get_id()),
+            Self::Spell(spell) => CardId::Spell(spell.get_id()),
+        }
+    }
+
+    pub fn unit(&self) -> Option<Unit<'a>> {
+        match self {
+            Self::Unit(unit) => Some(*unit),
+            Self::Spell(_) => None,
+        }
+    }
+
+    pub fn spell(&self) -> Option<Spell<'a>> {
+        match self {
+            Self::Unit(_) => None,
+            Self::Spell(spell) => Some(*spell),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Unit(unit) => unit.get_name(),
+            Self::Spell(spell) => spell.get_name(),
+        }
+    }
+
+    pub fn emoji(&self) -> char {
+        match self {
+            Self::Unit(unit) => unit.emoji,
+            Self::Spell(spell) => spell.emoji,
+        }
+    }
+
+    /// Ticks the card takes to cool down after being played, every card starts the battle
+    /// cooling down
+    pub fn cooldown_ticks(&self) -> u64 {
+        let millis = match self {
+            Self::Unit(unit) => unit.power as f32 * (1.0 / unit.speed) * 500.0,
+            Self::Spell(spell) => spell.cooldown as f32,
+        };
+
+        (millis / TICK.as_millis() as f32) as u64
+    }
+}
+
+impl Drawable for Card<'_> {
+    fn cost(&self) -> usize {
+        match self {
+            Self::Unit(unit) => unit.cost,
+            Self::Spell(spell) => spell.cost,
+        }
+    }
+
+    fn rarity(&self) -> Rarity {
+        match self {
+            Self::Unit(unit) => unit.rarity,
+            Self::Spell(spell) => spell.rarity,
+        }
+    }
+}
+
+impl<'a> From<Unit<'a>> for Card<'a> {
+    fn from(unit: Unit<'a>) -> Self {
+        Self::Unit(unit)
+    }
+}
+
+impl<'a> From<Spell<'a>> for Card<'a> {
+    fn from(spell: Spell<'a>) -> Self {
+        Self::Spell(spell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Card, CardId};
+    use crate::game::card_gen::{SPELLS, UNITS};
+
+    #[test]
+    fn units_and_spells_with_the_same_index_are_different_cards() {
+        let unit = Card::from(UNITS[0]);
+        let spell = Card::from(SPELLS[0]);
+
+        assert_eq!(unit.id(), CardId::Unit(0));
+        assert_eq!(spell.id(), CardId::Spell(0));
+        assert_ne!(unit.id(), spell.id());
+    }
+
+    #[test]
+    fn cards_serialize_with_their_kind() {
+        let json = serde_json::to_value(Card::from(SPELLS[0])).unwrap();
+
+        assert_eq!(json["card"], "Spell");
+        assert_eq!(json["name"], SPELLS[0].get_name());
+    }
+}
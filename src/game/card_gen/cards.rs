@@ -1,31 +1,698 @@
 /// THIS FILE IS AUTOGENERATED BY BUILD.RS
 /// TO ADD NEW UNITS, ADD A NEW FILE TO THE UNITS DIRECTORY
-pub static CARDS: &[&str] = &[
-    r#"{"name":"Super Hero","emoji":"🦸","cost":2555,"health":1000,"power":60,"size":1.0,"speed":1.25,"attack_type":"Area"}"#,
-    r#"{"name":"Alien","emoji":"👽","cost":500,"health":135,"power":22,"size":0.7,"speed":1.8,"attack_type":"Area"}"#,
-    r#"{"name":"Smiley","emoji":"🙂","cost":75,"health":75,"power":15,"size":1.0,"speed":1.0,"attack_type":"Single"}"#,
-    r#"{"name":"T-Rex","emoji":"🦖","cost":1750,"health":1200,"power":60,"size":3.0,"speed":0.5,"attack_type":"Area"}"#,
-    r#"{"name":"Star","emoji":"⭐","cost":35,"health":1,"power":10,"size":1.0,"speed":5.0,"attack_type":"Single"}"#,
-    r#"{"name":"Robot","emoji":"🤖","cost":200,"health":125,"power":12,"size":1.1,"speed":0.9,"attack_type":"Area"}"#,
-    r#"{"name":"Hippo","emoji":"🦛","cost":500,"health":750,"power":10,"size":1.6,"speed":0.6,"attack_type":"Single"}"#,
-    r#"{"name":"Ninja","emoji":"🥷","cost":200,"health":100,"power":20,"size":1.0,"speed":1.4,"attack_type":"Single"}"#,
-    r#"{"name":"Skeleton","emoji":"💀","cost":120,"health":85,"power":12,"size":1.0,"speed":1.0,"attack_type":"Single"}"#,
-    r#"{"name":"Snowman","emoji":"⛄","cost":340,"health":175,"power":30,"size":1.0,"speed":0.85,"attack_type":"Single"}"#,
-    r#"{"name":"Spooked","emoji":"😱","cost":100,"health":100,"power":30,"size":1.0,"speed":1.2,"attack_type":"Single"}"#,
-    r#"{"name":"Cowboy","emoji":"🤠","cost":150,"health":85,"power":35,"size":1.1,"speed":0.9,"attack_type":"Single"}"#,
-    r#"{"name":"Moon","emoji":"🌝","cost":3000,"health":2000,"power":15,"size":10.0,"speed":0.3,"attack_type":"Area"}"#,
-    r#"{"name":"Demon","emoji":"👹","cost":666,"health":666,"power":16,"size":2.1,"speed":0.75,"attack_type":"Area"}"#,
-    r#"{"name":"Melted","emoji":"🫠","cost":350,"health":120,"power":20,"size":1.0,"speed":0.875,"attack_type":"Area"}"#,
-    r#"{"name":"Silly","emoji":"🤗","cost":165,"health":90,"power":20,"size":1.0,"speed":1.0,"attack_type":"Single"}"#,
-    r#"{"name":"Hamster","emoji":"🐹","cost":75,"health":45,"power":10,"size":0.3,"speed":1.0,"attack_type":"Single"}"#,
-    r#"{"name":"Sneaker","emoji":"🫥","cost":75,"health":40,"power":25,"size":0.99,"speed":1.5,"attack_type":"Single"}"#,
-    r#"{"name":"Golem","emoji":"🗿","cost":2500,"health":1500,"power":55,"size":2.5,"speed":0.2,"attack_type":"Area"}"#,
-    r#"{"name":"EXPLOSIVE","emoji":"🧨","cost":1000,"health":1,"power":9999,"size":1.2,"speed":10.0,"attack_type":"Area"}"#,
-    r#"{"name":"Gatto","emoji":"😻","cost":150,"health":150,"power":5,"size":1.0,"speed":1.5,"attack_type":"Single"}"#,
-    r#"{"name":"Nerd","emoji":"🤓","cost":314,"health":200,"power":15,"size":0.88,"speed":0.67,"attack_type":"Single"}"#,
-    r#"{"name":"Boar","emoji":"🐗","cost":400,"health":120,"power":40,"size":1.2,"speed":1.2,"attack_type":"Single"}"#,
-    r#"{"name":"Boomer","emoji":"🤯","cost":550,"health":1,"power":150,"size":1.0,"speed":2.5,"attack_type":"Area"}"#,
-    r#"{"name":"Snail","emoji":"🐌","cost":60,"health":100,"power":10,"size":0.3,"speed":0.1,"attack_type":"Area"}"#,
-    r#"{"name":"ANGRY","emoji":"😡","cost":150,"health":50,"power":50,"size":1.1,"speed":1.5,"attack_type":"Single"}"#,
-    r#"{"name":"Lil Bugger","emoji":"👾","cost":300,"health":250,"power":20,"size":0.75,"speed":1.2,"attack_type":"Single"}"#,
+use crate::game::entity::{
+    AttackType, LevelCurve, Rarity, Tag, TagBonus, Tags, Targeting, Unit, UnitId,
+};
+
+pub static UNITS: &[Unit<'static>] = &[
+    Unit {
+        id: 0,
+        name: "Alien",
+        emoji: '👽',
+        cost: 500,
+        health: 135,
+        power: 22,
+        size: 0.7,
+        speed: 1.8,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Rare,
+        switches_lanes: true,
+        tags: Tags::of(&[Tag::Flying]),
+        targeting: Targeting::HighestCost,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 1,
+        name: "ANGRY",
+        emoji: '😡',
+        cost: 150,
+        health: 50,
+        power: 50,
+        size: 1.1,
+        speed: 1.5,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Faces"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 2,
+        name: "Boar",
+        emoji: '🐗',
+        cost: 400,
+        health: 120,
+        power: 40,
+        size: 1.2,
+        speed: 1.2,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Critters"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 3,
+        name: "Boomer",
+        emoji: '🤯',
+        cost: 550,
+        health: 1,
+        power: 150,
+        size: 1.0,
+        speed: 2.5,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 4,
+        name: "Cowboy",
+        emoji: '🤠',
+        cost: 150,
+        health: 85,
+        power: 35,
+        size: 1.1,
+        speed: 0.9,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: Some(TagBonus {
+            against: Tag::Undead,
+            damage: 2.0,
+        }),
+        family: Some("Heroes"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 5,
+        name: "Demon",
+        emoji: '👹',
+        cost: 666,
+        health: 666,
+        power: 16,
+        size: 2.1,
+        speed: 0.75,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Epic,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Undead, Tag::Heavy]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Spooky"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 6,
+        name: "EXPLOSIVE",
+        emoji: '🧨',
+        cost: 1000,
+        health: 1,
+        power: 9999,
+        size: 1.2,
+        speed: 10.0,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Epic,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 7,
+        name: "Gatto",
+        emoji: '😻',
+        cost: 150,
+        health: 150,
+        power: 5,
+        size: 1.0,
+        speed: 1.5,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Critters"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 8,
+        name: "Golem",
+        emoji: '🗿',
+        cost: 2500,
+        health: 1500,
+        power: 55,
+        size: 2.5,
+        speed: 0.2,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Legendary,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Heavy]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 1,
+            health: 0.5,
+            power: 0.2,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 9,
+        name: "Hamster",
+        emoji: '🐹',
+        cost: 75,
+        health: 45,
+        power: 10,
+        size: 0.3,
+        speed: 1.0,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Critters"),
+        levels: LevelCurve {
+            max: 5,
+            health: 0.4,
+            power: 0.4,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 10,
+        name: "Hippo",
+        emoji: '🦛',
+        cost: 500,
+        health: 750,
+        power: 10,
+        size: 1.6,
+        speed: 0.6,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Heavy]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Critters"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 11,
+        name: "Lil Bugger",
+        emoji: '👾',
+        cost: 300,
+        health: 250,
+        power: 20,
+        size: 0.75,
+        speed: 1.2,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 12,
+        name: "Melted",
+        emoji: '🫠',
+        cost: 350,
+        health: 120,
+        power: 20,
+        size: 1.0,
+        speed: 0.875,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 13,
+        name: "Moon",
+        emoji: '🌝',
+        cost: 3000,
+        health: 2000,
+        power: 15,
+        size: 10.0,
+        speed: 0.3,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Legendary,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Heavy, Tag::Flying]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 1,
+            health: 0.5,
+            power: 0.5,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 14,
+        name: "Nerd",
+        emoji: '🤓',
+        cost: 314,
+        health: 200,
+        power: 15,
+        size: 0.88,
+        speed: 0.67,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: Some(TagBonus {
+            against: Tag::Heavy,
+            damage: 2.5,
+        }),
+        family: Some("Faces"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 15,
+        name: "Ninja",
+        emoji: '🥷',
+        cost: 200,
+        health: 100,
+        power: 20,
+        size: 1.0,
+        speed: 1.4,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: true,
+        tags: Tags::of(&[]),
+        targeting: Targeting::LowestHealth,
+        bonus: None,
+        family: Some("Heroes"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 16,
+        name: "Robot",
+        emoji: '🤖',
+        cost: 200,
+        health: 125,
+        power: 12,
+        size: 1.1,
+        speed: 0.9,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: Some(TagBonus {
+            against: Tag::Flying,
+            damage: 2.0,
+        }),
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 17,
+        name: "Silly",
+        emoji: '🤗',
+        cost: 165,
+        health: 90,
+        power: 20,
+        size: 1.0,
+        speed: 1.0,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Faces"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 18,
+        name: "Skeleton",
+        emoji: '💀',
+        cost: 120,
+        health: 85,
+        power: 12,
+        size: 1.0,
+        speed: 1.0,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Undead]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Spooky"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 19,
+        name: "Smiley",
+        emoji: '🙂',
+        cost: 75,
+        health: 75,
+        power: 15,
+        size: 1.0,
+        speed: 1.0,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Faces"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 20,
+        name: "Snail",
+        emoji: '🐌',
+        cost: 60,
+        health: 100,
+        power: 10,
+        size: 0.3,
+        speed: 0.1,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Critters"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 21,
+        name: "Sneaker",
+        emoji: '🫥',
+        cost: 75,
+        health: 40,
+        power: 25,
+        size: 0.99,
+        speed: 1.5,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::TowerOnly,
+        bonus: None,
+        family: Some("Faces"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 22,
+        name: "Snowman",
+        emoji: '⛄',
+        cost: 340,
+        health: 175,
+        power: 30,
+        size: 1.0,
+        speed: 0.85,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Rare,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 23,
+        name: "Spooked",
+        emoji: '😱',
+        cost: 100,
+        health: 100,
+        power: 30,
+        size: 1.0,
+        speed: 1.2,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Undead]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Spooky"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 24,
+        name: "Star",
+        emoji: '⭐',
+        cost: 35,
+        health: 1,
+        power: 10,
+        size: 1.0,
+        speed: 5.0,
+        attack_type: AttackType::Single,
+        rarity: Rarity::Common,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Flying]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.5,
+            speed: 0.1,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 25,
+        name: "Super Hero",
+        emoji: '🦸',
+        cost: 2555,
+        health: 1000,
+        power: 60,
+        size: 1.0,
+        speed: 1.25,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Legendary,
+        switches_lanes: false,
+        tags: Tags::of(&[]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: Some("Heroes"),
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
+    Unit {
+        id: 26,
+        name: "T-Rex",
+        emoji: '🦖',
+        cost: 1750,
+        health: 1200,
+        power: 60,
+        size: 3.0,
+        speed: 0.5,
+        attack_type: AttackType::Area,
+        rarity: Rarity::Legendary,
+        switches_lanes: false,
+        tags: Tags::of(&[Tag::Heavy]),
+        targeting: Targeting::Closest,
+        bonus: None,
+        family: None,
+        levels: LevelCurve {
+            max: 3,
+            health: 0.25,
+            power: 0.25,
+            speed: 0.0,
+        },
+        level: 0,
+    },
 ];
+
+pub fn unit_id(name: &str) -> Option<UnitId> {
+    match name {
+        "Alien" => Some(0),
+        "ANGRY" => Some(1),
+        "Boar" => Some(2),
+        "Boomer" => Some(3),
+        "Cowboy" => Some(4),
+        "Demon" => Some(5),
+        "EXPLOSIVE" => Some(6),
+        "Gatto" => Some(7),
+        "Golem" => Some(8),
+        "Hamster" => Some(9),
+        "Hippo" => Some(10),
+        "Lil Bugger" => Some(11),
+        "Melted" => Some(12),
+        "Moon" => Some(13),
+        "Nerd" => Some(14),
+        "Ninja" => Some(15),
+        "Robot" => Some(16),
+        "Silly" => Some(17),
+        "Skeleton" => Some(18),
+        "Smiley" => Some(19),
+        "Snail" => Some(20),
+        "Sneaker" => Some(21),
+        "Snowman" => Some(22),
+        "Spooked" => Some(23),
+        "Star" => Some(24),
+        "Super Hero" => Some(25),
+        "T-Rex" => Some(26),
+        _ => None,
+    }
+}
@@ -1,14 +1,25 @@
-use std::sync::LazyLock;
+pub use cards::{unit_id, UNITS};
+pub use spells::SPELLS;
 
-use cards::CARDS;
+mod cards;
+pub mod pack;
+#[rustfmt::skip]
+mod spells;
 
-use super::entity::Unit;
+#[cfg(test)]
+mod tests {
+    use super::{unit_id, UNITS};
 
-mod cards;
+    #[test]
+    fn unit_ids_match_their_index_and_name_lookup() {
+        for (index, unit) in UNITS.iter().enumerate() {
+            assert_eq!(unit.get_id(), index);
+            assert_eq!(unit_id(unit.get_name()), Some(index));
+        }
+    }
 
-pub const UNITS: LazyLock<Vec<Unit<'static>>> = LazyLock::new(|| {
-    CARDS
-        .iter()
-        .map(|card_str| serde_json::from_str::<Unit>(card_str).unwrap())
-        .collect()
-});
+    #[test]
+    fn unknown_unit_name_has_no_id() {
+        assert_eq!(unit_id("Not A Real Unit"), None);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use crate::game::{
+    entity::{Unit, UnitId, MAX_LEVELS},
+    family::Family,
+};
+
+use super::UNITS;
+
+/// Names of every unit and family loaded at runtime. Units borrow their names, so each name
+/// is leaked the first time it's seen and shared by every load after, keeping memory from
+/// growing as packs are reloaded
+static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// A validated set of units that battles draw their hands from. The built in pack is the
+/// `UNITS` slice compiled in by build.rs, but a pack can also be loaded at runtime from a
+/// directory of `.unit` files or from a single json file holding an array of units
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitPack {
+    units: Vec<Unit<'static>>,
+    ids: HashMap<&'static str, UnitId>,
+}
+
+impl Default for UnitPack {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl UnitPack {
+    pub fn builtin() -> Self {
+        Self::from_units(UNITS.to_vec()).expect("Built in units should always be valid")
+    }
+
+    /// Loads a pack from either a units directory or a pack file
+    pub fn load(path: &Path) -> PackResult<Self> {
+        let mut units = vec![];
+
+        if path.is_dir() {
+            for path in unit_files(path)? {
+                let contents = fs::read_to_string(&path)?;
+                let unit: Unit = serde_json::from_str(&contents)
+                    .map_err(|e| PackError::Parse(path.clone(), e))?;
+                units.push(intern_names(unit));
+            }
+        } else {
+            let contents = fs::read_to_string(path)?;
+            let parsed: Vec<Unit> =
+                serde_json::from_str(&contents).map_err(|e| PackError::Parse(path.into(), e))?;
+            units = parsed.into_iter().map(intern_names).collect();
+        }
+
+        Self::from_units(units)
+    }
+
+    /// Validates a list of units and assigns them ids based on their position
+    pub fn from_units(mut units: Vec<Unit<'static>>) -> PackResult<Self> {
+        if units.is_empty() {
+            return Err(PackError::Empty);
+        }
+
+        let mut ids = HashMap::new();
+        for (id, unit) in units.iter_mut().enumerate() {
+            unit.id = id;
+            validate(unit)?;
+
+            if ids.insert(unit.name, id).is_some() {
+                return Err(PackError::DuplicateName(unit.name.to_string()));
+            }
+        }
+
+        Ok(Self { units, ids })
+    }
+
+    pub fn units(&self) -> &[Unit<'static>] {
+        &self.units
+    }
+
+    pub fn get(&self, id: UnitId) -> Option<&Unit<'static>> {
+        self.units.get(id)
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<UnitId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+}
+
+/// Most recent modification time of a pack file, or of any unit file inside a pack directory.
+/// Polled by the server to know when a pack needs reloading
+pub fn last_modified(path: &Path) -> io::Result<SystemTime> {
+    let mut latest = fs::metadata(path)?.modified()?;
+
+    if path.is_dir() {
+        for path in unit_files(path)? {
+            latest = latest.max(fs::metadata(path)?.modified()?);
+        }
+    }
+
+    Ok(latest)
+}
+
+fn unit_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    // Same ordering as build.rs so a pack made from the units directory keeps the same ids
+    paths.sort();
+    Ok(paths)
+}
+
+/// Moves a unit's names off the file it was parsed from so it can outlive it
+fn intern_names(unit: Unit) -> Unit<'static> {
+    unit.with_names(intern(unit.name), unit.family.map(intern))
+}
+
+fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(name) = names.get(name) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(name.into());
+    names.insert(name);
+    name
+}
+
+fn validate(unit: &Unit) -> PackResult<()> {
+    let invalid = |reason: &str| {
+        Err(PackError::InvalidUnit(
+            unit.name.to_string(),
+            reason.to_string(),
+        ))
+    };
+
+    if unit.name.trim().is_empty() {
+        return invalid("name is empty");
+    }
+    if unit.health == 0 {
+        return invalid("health must be greater than 0");
+    }
+    if !(unit.size.is_finite() && unit.size > 0.0) {
+        return invalid("size must be a positive number");
+    }
+    if !(unit.speed.is_finite() && unit.speed > 0.0) {
+        return invalid("speed must be a positive number");
+    }
+    let growth = [unit.levels.health, unit.levels.power, unit.levels.speed];
+    if !growth
+        .iter()
+        .all(|growth| growth.is_finite() && *growth >= 0.0)
+    {
+        return invalid("level growth can't be negative");
+    }
+    if unit.levels.max > MAX_LEVELS {
+        return invalid(&format!("can't level up more than {MAX_LEVELS} times"));
+    }
+    if unit
+        .family
+        .is_some_and(|family| Family::get(family).is_none())
+    {
+        return invalid("family doesn't exist");
+    }
+    if let Some(bonus) = unit.bonus {
+        if !(bonus.damage.is_finite() && bonus.damage > 0.0) {
+            return invalid("bonus damage must be a positive number");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PackError {
+    #[error("Failed to read unit pack: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("Unit pack has no units")]
+    Empty,
+    #[error("Unit pack has more than one unit named {0}")]
+    DuplicateName(String),
+    #[error("Unit {0} is invalid: {1}")]
+    InvalidUnit(String, String),
+}
+
+pub type PackResult<T> = std::result::Result<T, PackError>;
+
+#[cfg(test)]
+mod tests {
+    use super::{PackError, UnitPack};
+    use crate::game::{card_gen::UNITS, entity::MAX_LEVELS};
+
+    #[test]
+    fn builtin_pack_matches_generated_units() {
+        let pack = UnitPack::builtin();
+
+        assert_eq!(pack.units(), UNITS);
+        for unit in UNITS {
+            assert_eq!(pack.id_of(unit.get_name()), Some(unit.get_id()));
+        }
+    }
+
+    #[test]
+    fn units_directory_loads_with_the_same_ids() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("units");
+        let pack = UnitPack::load(&dir).expect("Load the units directory");
+
+        assert_eq!(pack, UnitPack::builtin());
+    }
+
+    #[test]
+    fn reloading_reuses_the_same_names() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("units");
+        let first = UnitPack::load(&dir).expect("Load the units directory");
+        let second = UnitPack::load(&dir).expect("Reload the units directory");
+
+        assert!(first
+            .units()
+            .iter()
+            .zip(second.units())
+            .all(|(a, b)| std::ptr::eq(a.get_name(), b.get_name())));
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let units = vec![UNITS[0], UNITS[0]];
+
+        assert!(matches!(
+            UnitPack::from_units(units),
+            Err(PackError::DuplicateName(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_stats_are_rejected() {
+        let mut unit = UNITS[0];
+        unit.speed = 0.0;
+
+        assert!(matches!(
+            UnitPack::from_units(vec![unit]),
+            Err(PackError::InvalidUnit(_, _))
+        ));
+        assert!(matches!(
+            UnitPack::from_units(vec![]),
+            Err(PackError::Empty)
+        ));
+    }
+
+    #[test]
+    fn too_many_levels_are_rejected() {
+        let mut unit = UNITS[0];
+        unit.levels.max = MAX_LEVELS + 1;
+
+        assert!(matches!(
+            UnitPack::from_units(vec![unit]),
+            Err(PackError::InvalidUnit(_, _))
+        ));
+        unit.levels.max = MAX_LEVELS;
+        assert!(UnitPack::from_units(vec![unit]).is_ok());
+    }
+}
@@ -0,0 +1,33 @@
+/// THIS FILE IS AUTOGENERATED BY BUILD.RS
+/// TO ADD NEW SPELLS, ADD A NEW FILE TO THE SPELLS DIRECTORY
+use crate::game::{entity::Rarity, spell::{Spell, SpellEffect}};
+
+pub static SPELLS: &[Spell<'static>] = &[
+    Spell {
+        id: 0,
+        name: "Frost",
+        emoji: '❄',
+        cost: 350,
+        cooldown: 15000,
+        rarity: Rarity::Rare,
+        effect: SpellEffect::Freeze { millis: 3000 },
+    },
+    Spell {
+        id: 1,
+        name: "Mend",
+        emoji: '💚',
+        cost: 400,
+        cooldown: 20000,
+        rarity: Rarity::Common,
+        effect: SpellEffect::Heal { amount: 1500 },
+    },
+    Spell {
+        id: 2,
+        name: "Meteor",
+        emoji: '☄',
+        cost: 600,
+        cooldown: 18000,
+        rarity: Rarity::Epic,
+        effect: SpellEffect::Meteor { damage: 120, radius: 0.1 },
+    },
+];
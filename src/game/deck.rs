@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::server::state::GAME_HAND_SIZE;
+
+use super::{
+    card_gen::pack::UnitPack,
+    entity::{Unit, UnitId},
+};
+
+/// A deck needs at least enough cards to draw a full hand from
+pub const DECK_MIN_SIZE: usize = GAME_HAND_SIZE;
+pub const DECK_MAX_SIZE: usize = 10;
+/// Combined cost of every unit in a deck, so a deck can't be made of only the biggest units
+pub const DECK_MAX_COST: usize = 8000;
+pub const DECK_NAME_MAX_LEN: usize = 32;
+
+/// A named list of units a player builds from the unit catalog. Units are stored by name
+/// rather than id so saved decks survive the unit pack being reloaded
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deck {
+    name: String,
+    units: Vec<String>,
+}
+
+impl Deck {
+    /// Builds a deck from unit ids in the given pack, checking the deck building constraints
+    pub fn build(name: &str, unit_ids: &[UnitId], pack: &UnitPack) -> DeckResult<Self> {
+        let units = unit_ids
+            .iter()
+            .map(|id| {
+                pack.get(*id)
+                    .map(|unit| unit.get_name().to_string())
+                    .ok_or(DeckError::UnknownUnit(id.to_string()))
+            })
+            .collect::<DeckResult<Vec<_>>>()?;
+
+        let deck = Self {
+            name: name.trim().to_string(),
+            units,
+        };
+        deck.validate(pack)?;
+
+        Ok(deck)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Resolves the deck's units against a pack, failing if any of them no longer exist
+    pub fn units(&self, pack: &UnitPack) -> DeckResult<Vec<Unit<'static>>> {
+        self.units
+            .iter()
+            .map(|name| {
+                pack.id_of(name)
+                    .and_then(|id| pack.get(id).copied())
+                    .ok_or_else(|| DeckError::UnknownUnit(name.clone()))
+            })
+            .collect()
+    }
+
+    pub fn validate(&self, pack: &UnitPack) -> DeckResult<()> {
+        if self.name.is_empty() || self.name.len() > DECK_NAME_MAX_LEN {
+            return Err(DeckError::InvalidName);
+        }
+
+        if self.units.len() < DECK_MIN_SIZE || self.units.len() > DECK_MAX_SIZE {
+            return Err(DeckError::InvalidSize(self.units.len()));
+        }
+
+        for (i, name) in self.units.iter().enumerate() {
+            if self.units[..i].contains(name) {
+                return Err(DeckError::DuplicateUnit(name.clone()));
+            }
+        }
+
+        let cost: usize = self.units(pack)?.iter().map(|unit| unit.cost).sum();
+        if cost > DECK_MAX_COST {
+            return Err(DeckError::TooExpensive(cost));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum DeckError {
+    #[error("Deck names must be between 1 and {DECK_NAME_MAX_LEN} characters")]
+    InvalidName,
+    #[error("Decks must have between {DECK_MIN_SIZE} and {DECK_MAX_SIZE} units, this one has {0}")]
+    InvalidSize(usize),
+    #[error("Decks can only have one of each unit, {0} is in this one more than once")]
+    DuplicateUnit(String),
+    #[error("Decks can cost at most {DECK_MAX_COST}, this one costs {0}")]
+    TooExpensive(usize),
+    #[error("Unit {0} does not exist")]
+    UnknownUnit(String),
+    #[error("No deck named {0}")]
+    NoSuchDeck(String),
+}
+
+pub type DeckResult<T> = std::result::Result<T, DeckError>;
+
+#[cfg(test)]
+mod tests {
+    use super::{Deck, DeckError, DECK_MAX_SIZE, DECK_MIN_SIZE};
+    use crate::game::card_gen::pack::UnitPack;
+
+    fn cheapest_ids(pack: &UnitPack, count: usize) -> Vec<usize> {
+        let mut units = pack.units().to_vec();
+        units.sort_by_key(|unit| unit.cost);
+
+        units.iter().take(count).map(|unit| unit.get_id()).collect()
+    }
+
+    #[test]
+    fn valid_deck_builds_and_resolves() {
+        let pack = UnitPack::builtin();
+        let ids = cheapest_ids(&pack, DECK_MIN_SIZE);
+        let deck = Deck::build("Rush", &ids, &pack).expect("Build a cheap deck");
+
+        let units = deck.units(&pack).unwrap();
+        assert_eq!(units.len(), DECK_MIN_SIZE);
+        assert!(units.iter().zip(ids).all(|(unit, id)| unit.get_id() == id));
+    }
+
+    #[test]
+    fn deck_size_is_limited() {
+        let pack = UnitPack::builtin();
+
+        let too_small = cheapest_ids(&pack, DECK_MIN_SIZE - 1);
+        let too_big = cheapest_ids(&pack, DECK_MAX_SIZE + 1);
+
+        assert_eq!(
+            Deck::build("Small", &too_small, &pack),
+            Err(DeckError::InvalidSize(DECK_MIN_SIZE - 1))
+        );
+        assert_eq!(
+            Deck::build("Big", &too_big, &pack),
+            Err(DeckError::InvalidSize(DECK_MAX_SIZE + 1))
+        );
+    }
+
+    #[test]
+    fn deck_cost_and_duplicates_are_limited() {
+        let pack = UnitPack::builtin();
+
+        let mut expensive = pack.units().to_vec();
+        expensive.sort_by_key(|unit| std::cmp::Reverse(unit.cost));
+        let expensive: Vec<_> = expensive[..DECK_MIN_SIZE]
+            .iter()
+            .map(|unit| unit.get_id())
+            .collect();
+
+        let mut duplicated = cheapest_ids(&pack, DECK_MIN_SIZE);
+        duplicated[1] = duplicated[0];
+
+        assert!(matches!(
+            Deck::build("Big Boys", &expensive, &pack),
+            Err(DeckError::TooExpensive(_))
+        ));
+        assert!(matches!(
+            Deck::build("Twins", &duplicated, &pack),
+            Err(DeckError::DuplicateUnit(_))
+        ));
+    }
+}
@@ -0,0 +1,913 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    card::{Card, CardId},
+    entity::{Drawable, Tag, Targeting, Unit},
+    family::{Synergy, Tier, FAMILIES},
+    map::Map,
+    tower::{BASE_WALLET, WALLET_PER_LEVEL},
+};
+
+/// How often the server advances a battle, matching the frontend's draw loop
+pub const TICK: Duration = Duration::from_millis(10);
+/// How far from its tower a unit can be for a copy played after it to merge into it, as a
+/// fraction of the lane
+pub const MERGE_RANGE: f32 = 0.2;
+
+/// Distance between two towers. Every pair of towers has a lane between them, and positions
+/// on a lane are measured from the tower of the lower numbered side. Maps with longer lanes
+/// don't change this, their units just walk and fill up space slower
+pub const LANE_LENGTH: f32 = 1500.0;
+
+/// Radius of a unit with a size of 1.0, units fight when they're touching
+const UNIT_RADIUS: f32 = 22.5;
+/// How close to a tower a unit has to get before it starts attacking it
+const TOWER_REACH: f32 = 3.0;
+/// Units attack once their attack charge fills up to this
+const ATTACK_CHARGE: f32 = 100.0;
+
+/// Which team a unit or tower belongs to, numbered from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Side(pub usize);
+
+impl Side {
+    /// The first team in a battle, on the left of a two team lane
+    pub const A: Self = Self(0);
+    /// The second team in a battle, on the right of a two team lane
+    pub const B: Self = Self(1);
+
+    /// The opposing side in a two team battle
+    pub fn other(&self) -> Self {
+        if *self == Self::A {
+            Self::B
+        } else {
+            Self::A
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    /// Position of this side's tower on the lane it shares with `opponent`
+    pub fn tower_position(&self, opponent: Side) -> f32 {
+        if *self < opponent {
+            0.0
+        } else {
+            LANE_LENGTH
+        }
+    }
+
+    /// Direction this side's units walk in on the lane it shares with `opponent`
+    fn direction(&self, opponent: Side) -> f32 {
+        if *self < opponent {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+/// A unit that has been played onto the field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldUnit<'a> {
+    /// Unique for the battle, so clients can tell units with the same stats apart
+    pub id: u64,
+    pub unit: Unit<'a>,
+    pub side: Side,
+    /// The side whose tower the unit is walking towards
+    pub target: Side,
+    /// Which of the map's side by side lanes between the two towers the unit is walking down
+    pub lane: usize,
+    pub position: f32,
+    pub health: usize,
+    pub attack_charge: f32,
+    /// Ticks left before the unit can move or attack again after being frozen
+    pub frozen: u64,
+}
+
+impl<'a> FieldUnit<'a> {
+    /// How far the unit has walked from its own tower, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        (self.position - self.side.tower_position(self.target)).abs() / LANE_LENGTH
+    }
+
+    /// The two sides whose towers the unit is walking between, lowest first
+    pub fn towers(&self) -> (Side, Side) {
+        (self.side.min(self.target), self.side.max(self.target))
+    }
+
+    /// How far the unit reaches from its position, `scale` shrinks units on longer maps
+    fn radius(&self, scale: f32) -> f32 {
+        self.unit.size * UNIT_RADIUS * scale
+    }
+
+    /// Whether `other` is a living enemy walking between the same towers, in any lane
+    fn is_enemy(&self, other: &FieldUnit) -> bool {
+        other.side != self.side && other.towers() == self.towers() && other.health > 0
+    }
+
+    /// Builds up the unit's next attack, returning true when it lands
+    fn charge_attack(&mut self) -> bool {
+        self.attack_charge += self.unit.speed / 4.0;
+        if self.attack_charge >= ATTACK_CHARGE {
+            self.attack_charge = 0.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldEvent<'a> {
+    /// A unit attacked the tower on the given side
+    TowerHit { tower: Side, damage: usize },
+    /// A unit belonging to `side` was killed by a unit belonging to `killer`
+    UnitKilled {
+        unit: Unit<'a>,
+        side: Side,
+        killer: Side,
+    },
+}
+
+/// Every unit fighting in a battle's lanes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field<'a> {
+    units: Vec<FieldUnit<'a>>,
+    next_id: u64,
+    /// Lanes, terrain and obstacles between every pair of towers
+    map: Map,
+    /// Damage each of the map's obstacles has taken, by the towers of the lane it's on and its
+    /// index in the map
+    obstacle_damage: HashMap<((Side, Side), usize), usize>,
+}
+
+impl Default for Field<'_> {
+    fn default() -> Self {
+        Self::new(Map::default())
+    }
+}
+
+impl<'a> Field<'a> {
+    pub fn new(map: Map) -> Self {
+        Self {
+            units: vec![],
+            next_id: 0,
+            map,
+            obstacle_damage: HashMap::new(),
+        }
+    }
+
+    pub fn lanes(&self) -> usize {
+        self.map.lanes
+    }
+
+    /// Health left on each of the map's obstacles in the lanes between two towers
+    pub fn obstacles(&self, towers: (Side, Side)) -> Vec<usize> {
+        (0..self.map.obstacles.len())
+            .map(|index| self.obstacle_health(towers, index))
+            .collect()
+    }
+
+    fn obstacle_health(&self, towers: (Side, Side), index: usize) -> usize {
+        let damage = self.obstacle_damage.get(&(towers, index)).copied();
+        self.map.obstacles[index]
+            .health
+            .saturating_sub(damage.unwrap_or(0))
+    }
+
+    /// Shrinks distances on maps with lanes longer than `LANE_LENGTH`
+    fn scale(&self) -> f32 {
+        LANE_LENGTH / self.map.length
+    }
+
+    /// Places a unit at its side's tower in the first lane towards the other side of a two
+    /// team battle, returning its field id
+    pub fn spawn(&mut self, unit: Unit<'a>, side: Side) -> u64 {
+        self.spawn_toward(unit, side, side.other(), 0)
+    }
+
+    /// Places a unit at its side's tower in one of the lanes towards `target`'s tower,
+    /// returning its field id. Lanes past the last one are clamped to it. The unit's stats
+    /// are grown to its level as it's placed
+    pub fn spawn_toward(&mut self, unit: Unit<'a>, side: Side, target: Side, lane: usize) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let unit = unit.leveled();
+        self.units.push(FieldUnit {
+            id,
+            unit,
+            side,
+            target,
+            lane: lane.min(self.map.lanes - 1),
+            position: side.tower_position(target),
+            health: unit.health,
+            attack_charge: 0.0,
+            frozen: 0,
+        });
+
+        id
+    }
+
+    /// Merges a unit into a living copy at the same level still within `MERGE_RANGE` of its
+    /// tower in the same lane, levelling the copy up and healing it to its new max health.
+    /// Returns the copy's field id, or `None` if there's nothing to merge with and the unit
+    /// should be spawned instead
+    pub fn merge(&mut self, unit: Unit<'a>, side: Side, target: Side, lane: usize) -> Option<u64> {
+        let leveled = unit.level_up()?.leveled();
+        let copy = self.units.iter_mut().find(|other| {
+            other.side == side
+                && other.target == target
+                && other.lane == lane
+                && other.health > 0
+                && other.unit.get_id() == unit.get_id()
+                && other.unit.get_name() == unit.get_name()
+                && other.unit.get_level() == unit.get_level()
+                && other.progress() <= MERGE_RANGE
+        })?;
+
+        copy.unit = leveled;
+        copy.health = leveled.health;
+        Some(copy.id)
+    }
+
+    /// Takes every unit belonging to or heading for a side off the field
+    pub fn clear_side(&mut self, side: Side) {
+        self.units
+            .retain(|unit| unit.side != side && unit.target != side);
+    }
+
+    pub fn units(&self) -> &[FieldUnit<'a>] {
+        &self.units
+    }
+
+    /// Damages every unit within `range` of a side's tower that's heading for it, on every
+    /// lane that tower is on
+    pub fn blast(&mut self, tower: Side, range: f32, damage: usize) -> Vec<FieldEvent<'a>> {
+        let mut events = vec![];
+
+        for unit in self.units.iter_mut() {
+            let distance = (1.0 - unit.progress()) * LANE_LENGTH;
+            if unit.target != tower || distance > range {
+                continue;
+            }
+
+            unit.health = unit.health.saturating_sub(damage);
+            if unit.health == 0 {
+                events.push(FieldEvent::UnitKilled {
+                    unit: unit.unit,
+                    side: unit.side,
+                    killer: tower,
+                });
+            }
+        }
+
+        self.units.retain(|unit| unit.health > 0);
+        events
+    }
+
+    /// Freezes every unit in the lanes between two sides that doesn't belong to `side`,
+    /// returning how many were frozen
+    pub fn freeze(&mut self, side: Side, target: Side, ticks: u64) -> usize {
+        let lane = (side.min(target), side.max(target));
+        let mut frozen = 0;
+
+        for unit in self.units.iter_mut() {
+            if unit.side != side && unit.towers() == lane {
+                unit.frozen = unit.frozen.max(ticks);
+                frozen += 1;
+            }
+        }
+
+        frozen
+    }
+
+    /// Damages every unit that doesn't belong to `side` within `radius` of a spot in the lanes
+    /// towards `target`. The spot and radius are fractions of the lane, measured from `side`'s
+    /// tower
+    pub fn strike(
+        &mut self,
+        side: Side,
+        target: Side,
+        spot: f32,
+        radius: f32,
+        damage: usize,
+    ) -> Vec<FieldEvent<'a>> {
+        let lane = (side.min(target), side.max(target));
+        let center = side.tower_position(target) + side.direction(target) * spot * LANE_LENGTH;
+        let mut events = vec![];
+
+        for unit in self.units.iter_mut() {
+            let distance = (unit.position - center).abs();
+            if unit.side == side || unit.towers() != lane || distance > radius * LANE_LENGTH {
+                continue;
+            }
+
+            unit.health = unit.health.saturating_sub(damage);
+            if unit.health == 0 {
+                events.push(FieldEvent::UnitKilled {
+                    unit: unit.unit,
+                    side: unit.side,
+                    killer: side,
+                });
+            }
+        }
+
+        self.units.retain(|unit| unit.health > 0);
+        events
+    }
+
+    /// Moves every unit forward one tick. Frozen units only thaw. Units attack the enemy unit
+    /// in their lane they're touching that their targeting picks, otherwise an obstacle in
+    /// their way unless they fly over it, otherwise their target's tower if they've reached
+    /// it, otherwise keep walking at the speed of the terrain they're on. Family synergies
+    /// speed up and power up every unit on the side that earned them. Units that switch lanes
+    /// first move over to a neighbouring lane if there's an enemy there and none in their own
+    pub fn tick(&mut self) -> Vec<FieldEvent<'a>> {
+        let mut events = vec![];
+        let scale = self.scale();
+
+        // Synergies only change as units are played or die, so buffs are worked out once a tick
+        let mut buffs = HashMap::new();
+        for unit in &self.units {
+            buffs
+                .entry(unit.side)
+                .or_insert_with(|| Tier::total(&self.synergies(unit.side)));
+        }
+
+        for i in 0..self.units.len() {
+            if self.units[i].health == 0 {
+                continue;
+            }
+            if self.units[i].frozen > 0 {
+                self.units[i].frozen -= 1;
+                continue;
+            }
+
+            if self.units[i].unit.switches_lanes {
+                self.switch_lanes(i);
+            }
+
+            let unit = self.units[i];
+            let buff: Tier = buffs[&unit.side];
+            let boost = |damage: usize| (damage as f32 * (1.0 + buff.power)).round() as usize;
+            if let Some(j) = self.pick_target(i, scale) {
+                if self.units[i].charge_attack() {
+                    let enemy = &mut self.units[j];
+                    enemy.health = enemy
+                        .health
+                        .saturating_sub(boost(unit.unit.damage_against(&enemy.unit)));
+
+                    if enemy.health == 0 {
+                        events.push(FieldEvent::UnitKilled {
+                            unit: enemy.unit,
+                            side: enemy.side,
+                            killer: unit.side,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            let direction = unit.side.direction(unit.target);
+            let obstacle = self
+                .map
+                .obstacles
+                .iter()
+                .enumerate()
+                .position(|(index, obstacle)| {
+                    let ahead = (obstacle.position * LANE_LENGTH - unit.position) * direction;
+                    obstacle.lane == unit.lane
+                        && ahead >= 0.0
+                        && ahead - unit.radius(scale) < TOWER_REACH
+                        && self.obstacle_health(unit.towers(), index) > 0
+                });
+            if let Some(index) = obstacle.filter(|_| !unit.unit.has_tag(Tag::Flying)) {
+                if self.units[i].charge_attack() {
+                    *self
+                        .obstacle_damage
+                        .entry((unit.towers(), index))
+                        .or_default() += boost(unit.unit.power);
+                }
+                continue;
+            }
+
+            let enemy_tower = unit.target.tower_position(unit.side);
+            if (enemy_tower - unit.position).abs() - unit.radius(scale) < TOWER_REACH {
+                if self.units[i].charge_attack() {
+                    events.push(FieldEvent::TowerHit {
+                        tower: unit.target,
+                        damage: boost(unit.unit.power),
+                    });
+                }
+            } else {
+                let terrain = self.map.speed_at(unit.lane, unit.position / LANE_LENGTH);
+                let speed = unit.unit.speed * (1.0 + buff.speed);
+                self.units[i].position += direction * speed / 5.0 * scale * terrain;
+            }
+        }
+
+        self.units.retain(|unit| unit.health > 0);
+        events
+    }
+
+    /// The enemy in the unit's lane it's touching that it should attack, by its targeting.
+    /// Ties go to the closest enemy
+    fn pick_target(&self, i: usize, scale: f32) -> Option<usize> {
+        let unit = self.units[i];
+        let distance = |other: &FieldUnit| (other.position - unit.position).abs();
+        let in_reach = self.units.iter().enumerate().filter(|(_, other)| {
+            unit.is_enemy(other)
+                && other.lane == unit.lane
+                && distance(other) <= unit.radius(scale) + other.radius(scale)
+        });
+
+        let closest = |a: &FieldUnit, b: &FieldUnit| distance(a).total_cmp(&distance(b));
+        let picked = match unit.unit.targeting {
+            Targeting::Closest => in_reach.min_by(|(_, a), (_, b)| closest(a, b)),
+            Targeting::LowestHealth => {
+                in_reach.min_by(|(_, a), (_, b)| a.health.cmp(&b.health).then(closest(a, b)))
+            }
+            Targeting::HighestCost => {
+                in_reach.min_by(|(_, a), (_, b)| b.unit.cost.cmp(&a.unit.cost).then(closest(a, b)))
+            }
+            Targeting::TowerOnly => None,
+        };
+
+        picked.map(|(j, _)| j)
+    }
+
+    /// Families whose buffs a side has earned with the units it has on the field
+    pub fn synergies(&self, side: Side) -> Vec<Synergy> {
+        FAMILIES
+            .iter()
+            .filter_map(|family| {
+                let units = self
+                    .units
+                    .iter()
+                    .filter(|unit| unit.side == side && unit.health > 0)
+                    .filter(|unit| unit.unit.get_family() == Some(family.name))
+                    .count();
+
+                family.tier(units).map(|tier| Synergy {
+                    family: family.name,
+                    emoji: family.emoji,
+                    units,
+                    tier,
+                })
+            })
+            .collect()
+    }
+
+    /// Moves a unit into the neighbouring lane with the closest enemy in it, as long as there
+    /// are no enemies in its own lane
+    fn switch_lanes(&mut self, i: usize) {
+        let unit = self.units[i];
+        let enemies = || self.units.iter().filter(|other| unit.is_enemy(other));
+
+        if enemies().any(|enemy| enemy.lane == unit.lane) {
+            return;
+        }
+
+        let closest = enemies()
+            .filter(|enemy| enemy.lane.abs_diff(unit.lane) == 1)
+            .min_by(|a, b| {
+                let distance = |enemy: &&FieldUnit| (enemy.position - unit.position).abs();
+                distance(a).total_cmp(&distance(b))
+            });
+        if let Some(enemy) = closest {
+            self.units[i].lane = enemy.lane;
+        }
+    }
+}
+
+/// A player's money and card cooldowns during a battle
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Economy {
+    pub money: usize,
+    /// How many times the player has upgraded their income and wallet
+    pub income_level: usize,
+    pub wallet_level: usize,
+    /// Tick each card can next be played on, cards not in here haven't been played yet
+    ready_at: HashMap<CardId, u64>,
+}
+
+impl Economy {
+    pub fn new(money: usize) -> Self {
+        Self {
+            money,
+            income_level: 0,
+            wallet_level: 0,
+            ready_at: HashMap::new(),
+        }
+    }
+
+    /// Coins the player earns each time income is paid
+    pub fn income(&self) -> usize {
+        1 + self.income_level
+    }
+
+    /// Most money the player can hold, anything earned past this is lost
+    pub fn wallet(&self) -> usize {
+        BASE_WALLET + WALLET_PER_LEVEL * self.wallet_level
+    }
+
+    /// Adds money to the player's wallet, up to what it can hold
+    pub fn earn(&mut self, amount: usize) {
+        self.money = (self.money + amount).min(self.wallet().max(self.money));
+    }
+
+    /// Pays for something other than a card, like an upgrade
+    pub fn pay(&mut self, cost: usize) -> Result<(), PlayError> {
+        if self.money < cost {
+            return Err(PlayError::NotEnoughMoney);
+        }
+
+        self.money -= cost;
+        Ok(())
+    }
+
+    pub fn is_ready(&self, card: &Card, tick: u64) -> bool {
+        let ready_at = self
+            .ready_at
+            .get(&card.id())
+            .copied()
+            .unwrap_or(card.cooldown_ticks());
+
+        tick >= ready_at
+    }
+
+    pub fn can_play(&self, card: &Card, tick: u64) -> Result<(), PlayError> {
+        if self.money < card.cost() {
+            Err(PlayError::NotEnoughMoney)
+        } else if !self.is_ready(card, tick) {
+            Err(PlayError::OnCooldown)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Pays for a card and starts its cooldown
+    pub fn spend(&mut self, card: &Card, tick: u64) -> Result<(), PlayError> {
+        self.can_play(card, tick)?;
+
+        self.money -= card.cost();
+        self.ready_at
+            .insert(card.id(), tick + card.cooldown_ticks());
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum PlayError {
+    #[error("Not enough money to play that card")]
+    NotEnoughMoney,
+    #[error("That card is still cooling down")]
+    OnCooldown,
+    #[error("Your tower has fallen, you can only watch now")]
+    Eliminated,
+    #[error("You can't send units at that tower")]
+    InvalidTarget,
+    #[error("There's no lane with that number on this map")]
+    InvalidLane,
+    #[error("Your cannon is still charging")]
+    CannonNotReady,
+    #[error("That upgrade is already at its highest level")]
+    MaxUpgradeLevel,
+    #[error("That unit is already at its highest level")]
+    MaxUnitLevel,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Economy, Field, FieldEvent, PlayError, Side, LANE_LENGTH, MERGE_RANGE};
+    use crate::game::card::Card;
+    use crate::game::card_gen::UNITS;
+    use crate::game::entity::{Drawable, Unit};
+    use crate::game::map::{Map, Obstacle};
+
+    fn lanes(lanes: usize) -> Map {
+        Map {
+            lanes,
+            ..Map::default()
+        }
+    }
+
+    fn unit(name: &str) -> Unit<'static> {
+        *UNITS.iter().find(|unit| unit.get_name() == name).unwrap()
+    }
+
+    #[test]
+    fn lone_unit_walks_to_the_enemy_tower_and_attacks_it() {
+        let mut field = Field::default();
+        field.spawn(unit("Star"), Side::A);
+
+        let mut hits = vec![];
+        for _ in 0..10_000 {
+            hits.extend(field.tick());
+        }
+
+        let unit = field.units()[0];
+        assert!(unit.position > LANE_LENGTH * 0.9);
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|event| *event
+            == FieldEvent::TowerHit {
+                tower: Side::B,
+                damage: unit.unit.power
+            }));
+    }
+
+    #[test]
+    fn units_fight_when_they_meet() {
+        let mut field = Field::default();
+        field.spawn(unit("Moon"), Side::A);
+        field.spawn(unit("Smiley"), Side::B);
+
+        let mut events = vec![];
+        for _ in 0..30_000 {
+            events.extend(field.tick());
+        }
+
+        assert!(events.contains(&FieldEvent::UnitKilled {
+            unit: unit("Smiley"),
+            side: Side::B,
+            killer: Side::A
+        }));
+        assert_eq!(field.units().len(), 1);
+        assert_eq!(field.units()[0].side, Side::A);
+    }
+
+    #[test]
+    fn units_only_fight_on_their_own_lane() {
+        let mut field = Field::default();
+        field.spawn_toward(unit("Moon"), Side(0), Side(1), 0);
+        field.spawn_toward(unit("Moon"), Side(2), Side(0), 0);
+        field.spawn_toward(unit("Smiley"), Side(1), Side(2), 0);
+
+        let mut events = vec![];
+        for _ in 0..10_000 {
+            events.extend(field.tick());
+        }
+
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, FieldEvent::UnitKilled { .. })));
+        assert!(events.contains(&FieldEvent::TowerHit {
+            tower: Side(2),
+            damage: unit("Smiley").power
+        }));
+        assert_eq!(field.units().len(), 3);
+    }
+
+    #[test]
+    fn economy_enforces_money_and_cooldowns() {
+        let mut economy = Economy::new(50);
+        let star = Card::from(unit("Star"));
+        let cooldown = star.cooldown_ticks();
+
+        assert_eq!(economy.can_play(&star, 0), Err(PlayError::OnCooldown));
+        assert_eq!(economy.spend(&star, cooldown), Ok(()));
+        assert_eq!(economy.money, 50 - star.cost());
+
+        economy.money = 1000;
+        assert_eq!(
+            economy.can_play(&star, cooldown + 1),
+            Err(PlayError::OnCooldown)
+        );
+        assert_eq!(economy.can_play(&star, cooldown * 2), Ok(()));
+
+        economy.money = 0;
+        assert_eq!(
+            economy.can_play(&star, cooldown * 2),
+            Err(PlayError::NotEnoughMoney)
+        );
+    }
+
+    #[test]
+    fn blasts_only_hit_units_closing_in_on_the_tower() {
+        let mut field = Field::default();
+        field.spawn(unit("Boar"), Side::A);
+        while field.units()[0].progress() < 0.75 {
+            field.tick();
+        }
+        field.spawn(unit("Boar"), Side::A);
+        field.spawn(unit("Boar"), Side::B);
+
+        let events = field.blast(Side::B, LANE_LENGTH * 0.5, usize::MAX);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(field.units().len(), 2);
+        assert!(field.units().iter().all(|unit| unit.progress() == 0.0));
+    }
+
+    #[test]
+    fn frozen_units_stand_still_until_they_thaw() {
+        let mut field = Field::default();
+        field.spawn(unit("Boar"), Side::A);
+        field.spawn(unit("Boar"), Side::B);
+
+        assert_eq!(field.freeze(Side::A, Side::B, 10), 1);
+        for _ in 0..10 {
+            field.tick();
+        }
+        assert_eq!(field.units()[1].progress(), 0.0);
+        assert!(field.units()[0].progress() > 0.0);
+
+        field.tick();
+        assert!(field.units()[1].progress() > 0.0);
+    }
+
+    #[test]
+    fn strikes_only_hit_enemies_near_the_spot() {
+        let mut field = Field::default();
+        field.spawn(unit("Boar"), Side::A);
+        field.spawn(unit("Boar"), Side::B);
+        field.spawn_toward(unit("Boar"), Side(2), Side::A, 0);
+
+        let near_b = field.strike(Side::A, Side::B, 1.0, 0.1, usize::MAX);
+        let near_a = field.strike(Side::A, Side::B, 0.0, 0.1, usize::MAX);
+
+        assert_eq!(near_b.len(), 1);
+        assert!(near_a.is_empty());
+        assert_eq!(field.units().len(), 2);
+    }
+
+    #[test]
+    fn units_in_different_lanes_walk_past_each_other() {
+        let mut field = Field::new(lanes(3));
+        field.spawn_toward(unit("Boar"), Side::A, Side::B, 0);
+        field.spawn_toward(unit("Boar"), Side::B, Side::A, 1);
+        field.spawn_toward(unit("Boar"), Side::A, Side::B, 7);
+
+        for _ in 0..5_000 {
+            field.tick();
+        }
+
+        assert!(field.units()[0].progress() > 0.5);
+        assert!(field.units()[1].progress() > 0.5);
+        assert!(field
+            .units()
+            .iter()
+            .all(|unit| unit.health == unit.unit.health));
+        // Lanes that don't exist are clamped to the last one
+        assert_eq!(field.units()[2].lane, 2);
+    }
+
+    #[test]
+    fn lane_switchers_move_over_to_find_a_fight() {
+        let mut field = Field::new(lanes(3));
+        field.spawn_toward(unit("Ninja"), Side::A, Side::B, 0);
+        field.spawn_toward(unit("Smiley"), Side::B, Side::A, 1);
+        field.spawn_toward(unit("Smiley"), Side::B, Side::A, 2);
+        field.tick();
+
+        assert_eq!(field.units()[0].lane, 1);
+    }
+
+    #[test]
+    fn obstacles_block_their_lane_until_broken() {
+        let mut field = Field::new(Map {
+            lanes: 2,
+            obstacles: &[Obstacle {
+                lane: 0,
+                position: 0.2,
+                health: 50,
+            }],
+            ..Map::default()
+        });
+        field.spawn_toward(unit("Boar"), Side::A, Side::B, 0);
+        field.spawn_toward(unit("Boar"), Side::A, Side::B, 1);
+
+        for _ in 0..1_500 {
+            field.tick();
+        }
+        assert!(field.units()[0].progress() < 0.2);
+        assert!(field.units()[1].progress() > 0.2);
+
+        for _ in 0..1_500 {
+            field.tick();
+        }
+        assert_eq!(field.obstacles(field.units()[0].towers()), vec![0]);
+        assert!(field.units()[0].progress() > 0.2);
+    }
+
+    #[test]
+    fn units_take_longer_to_cross_longer_maps() {
+        let mut short = Field::default();
+        let mut long = Field::new(Map {
+            length: LANE_LENGTH * 2.0,
+            ..Map::default()
+        });
+        short.spawn(unit("Boar"), Side::A);
+        long.spawn(unit("Boar"), Side::A);
+
+        for _ in 0..1_000 {
+            short.tick();
+            long.tick();
+        }
+
+        let (short, long) = (short.units()[0].progress(), long.units()[0].progress());
+        assert!((short - long * 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn copies_near_their_tower_merge_and_level_up() {
+        let mut field = Field::default();
+        let smiley = unit("Smiley");
+        let id = field.spawn(smiley, Side::A);
+
+        assert_eq!(field.merge(smiley, Side::A, Side::B, 0), Some(id));
+        assert_eq!(field.units().len(), 1);
+        assert_eq!(field.units()[0].unit.get_level(), 1);
+        assert_eq!(
+            field.units()[0].health,
+            smiley.level_up().unwrap().leveled().health
+        );
+
+        // A level 0 card doesn't merge into the level 1 copy
+        assert_eq!(field.merge(smiley, Side::A, Side::B, 0), None);
+
+        let far = field.spawn(smiley, Side::A);
+        while field
+            .units()
+            .iter()
+            .all(|unit| unit.progress() <= MERGE_RANGE)
+        {
+            field.tick();
+        }
+        assert_eq!(field.merge(smiley, Side::A, Side::B, 0), None);
+        assert!(field.units().iter().any(|unit| unit.id == far));
+    }
+
+    #[test]
+    fn targeting_picks_which_enemy_in_reach_to_hit() {
+        let mut field = Field::default();
+        field.spawn(unit("Ninja"), Side::A);
+        field.spawn(unit("Boar"), Side::B);
+        field.spawn(unit("Smiley"), Side::B);
+        field.units[0].position = 500.0;
+        field.units[1].position = 510.0;
+        field.units[2].position = 520.0;
+
+        for _ in 0..300 {
+            field.tick();
+        }
+
+        // The boar is closer, but the ninja goes for whatever has the least health
+        assert_eq!(field.units[1].health, unit("Boar").health);
+        assert!(field.units[2].health < unit("Smiley").health);
+    }
+
+    #[test]
+    fn tower_only_units_and_flyers_skip_what_blocks_others() {
+        let mut field = Field::new(Map {
+            obstacles: &[Obstacle {
+                lane: 0,
+                position: 0.5,
+                health: 10_000,
+            }],
+            ..Map::default()
+        });
+        field.spawn(unit("Sneaker"), Side::A);
+        field.spawn(unit("Hippo"), Side::B);
+        field.spawn(unit("Star"), Side::A);
+        field.units[0].position = 100.0;
+        field.units[1].position = 120.0;
+        field.units[2].position = 700.0;
+
+        for _ in 0..100 {
+            field.tick();
+        }
+
+        assert_eq!(field.units[1].health, unit("Hippo").health);
+        assert!(field.units[0].position > 120.0);
+        assert!(field.units[2].position > LANE_LENGTH * 0.5);
+    }
+
+    #[test]
+    fn family_synergies_speed_up_the_whole_side() {
+        let mut alone = Field::default();
+        let mut together = Field::default();
+        alone.spawn(unit("Skeleton"), Side::A);
+        together.spawn(unit("Skeleton"), Side::A);
+        together.spawn(unit("Spooked"), Side::A);
+        together.spawn(unit("Smiley"), Side::B);
+
+        let synergies = together.synergies(Side::A);
+        assert_eq!(synergies.len(), 1);
+        assert_eq!((synergies[0].family, synergies[0].units), ("Spooky", 2));
+        assert!(together.synergies(Side::B).is_empty());
+
+        for _ in 0..100 {
+            alone.tick();
+            together.tick();
+        }
+
+        assert!(together.units()[0].progress() > alone.units()[0].progress());
+    }
+}
@@ -1,27 +1,253 @@
-use rand::seq::SliceRandom;
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
 use super::card_gen::UNITS;
 
+/// Stable index of a unit in the `UNITS` catalog, used to refer to units over the wire
+pub type UnitId = usize;
+
 #[derive(Default, PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
 pub struct Unit<'a> {
-    name: &'a str,
-    emoji: char,
+    #[serde(default)]
+    pub(crate) id: UnitId,
+    pub(crate) name: &'a str,
+    pub(crate) emoji: char,
+
+    pub(crate) cost: usize,
+    pub(crate) health: usize,
+    pub(crate) power: usize,
+
+    pub(crate) size: f32,
+    pub(crate) speed: f32,
 
-    cost: usize,
-    health: usize,
-    power: usize,
+    pub(crate) attack_type: AttackType,
+    #[serde(default)]
+    pub(crate) rarity: Rarity,
+    /// Moves over to a neighbouring lane when there's nothing to fight in its own
+    #[serde(default)]
+    pub(crate) switches_lanes: bool,
 
-    size: f32,
-    speed: f32,
+    #[serde(default)]
+    pub(crate) tags: Tags,
+    /// Which enemy in reach the unit attacks
+    #[serde(default)]
+    pub(crate) targeting: Targeting,
+    /// Extra damage the unit deals to units with a tag
+    #[serde(default)]
+    pub(crate) bonus: Option<TagBonus>,
+    /// Name of the family the unit counts towards for synergy buffs
+    #[serde(default, borrow)]
+    pub(crate) family: Option<&'a str>,
 
-    attack_type: AttackType,
+    /// How much stronger the unit gets with every level
+    #[serde(default)]
+    pub(crate) levels: LevelCurve,
+    /// Levels gained on top of the unit file's stats, 0 for a fresh card
+    #[serde(default)]
+    pub(crate) level: usize,
 }
 
 impl<'a> Unit<'a> {
+    pub fn get_id(&self) -> UnitId {
+        self.id
+    }
+
     pub fn get_name(&self) -> &str {
         self.name
     }
+
+    pub fn get_level(&self) -> usize {
+        self.level
+    }
+
+    pub fn get_family(&self) -> Option<&str> {
+        self.family
+    }
+
+    pub fn has_tag(&self, tag: Tag) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// The same unit borrowing its name and family from somewhere else, like units parsed
+    /// from a file that's about to be dropped
+    pub(crate) fn with_names<'b>(self, name: &'b str, family: Option<&'b str>) -> Unit<'b> {
+        Unit {
+            id: self.id,
+            name,
+            emoji: self.emoji,
+            cost: self.cost,
+            health: self.health,
+            power: self.power,
+            size: self.size,
+            speed: self.speed,
+            attack_type: self.attack_type,
+            rarity: self.rarity,
+            switches_lanes: self.switches_lanes,
+            tags: self.tags,
+            targeting: self.targeting,
+            bonus: self.bonus,
+            family,
+            levels: self.levels,
+            level: self.level,
+        }
+    }
+
+    /// Damage one of the unit's attacks does to `target`, including its bonus against tags
+    pub fn damage_against(&self, target: &Unit) -> usize {
+        match self.bonus {
+            Some(bonus) if target.has_tag(bonus.against) => {
+                (self.power as f32 * bonus.damage).round() as usize
+            }
+            _ => self.power,
+        }
+    }
+
+    /// The same unit one level up, or `None` if it's already at its highest level. Stats stay
+    /// at the unit file's until it's `leveled`
+    pub fn level_up(&self) -> Option<Self> {
+        (self.level < self.levels.max).then(|| Self {
+            level: self.level + 1,
+            ..*self
+        })
+    }
+
+    /// What leveling up a card in hand costs, each level costing the card's price again
+    pub fn level_up_cost(&self) -> Option<usize> {
+        self.level_up().map(|_| self.cost * (self.level + 1))
+    }
+
+    /// The unit with its stats grown to its level. Only done once as it's put on the field,
+    /// so leveling up again starts from the unit file's stats
+    pub fn leveled(&self) -> Self {
+        let growth = |per_level: f32| 1.0 + per_level * self.level as f32;
+
+        Self {
+            health: (self.health as f32 * growth(self.levels.health)).round() as usize,
+            power: (self.power as f32 * growth(self.levels.power)).round() as usize,
+            speed: self.speed * growth(self.levels.speed),
+            ..*self
+        }
+    }
+}
+
+/// Kinds of unit that other units can deal bonus damage to
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum Tag {
+    /// Flies over obstacles instead of having to break through them
+    Flying,
+    Heavy,
+    Undead,
+}
+
+impl Tag {
+    pub const ALL: [Self; 3] = [Self::Flying, Self::Heavy, Self::Undead];
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The set of tags a unit has, written as a list like `["Flying", "Undead"]` in unit files
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(from = "Vec<Tag>", into = "Vec<Tag>")]
+pub struct Tags(u8);
+
+impl Tags {
+    pub const fn of(tags: &[Tag]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < tags.len() {
+            bits |= tags[i].bit();
+            i += 1;
+        }
+        Self(bits)
+    }
+
+    pub fn contains(&self, tag: Tag) -> bool {
+        self.0 & tag.bit() != 0
+    }
+}
+
+impl From<Vec<Tag>> for Tags {
+    fn from(tags: Vec<Tag>) -> Self {
+        Self::of(&tags)
+    }
+}
+
+impl From<Tags> for Vec<Tag> {
+    fn from(tags: Tags) -> Self {
+        Tag::ALL
+            .into_iter()
+            .filter(|tag| tags.contains(*tag))
+            .collect()
+    }
+}
+
+/// Which of the enemies in reach a unit attacks
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum Targeting {
+    #[default]
+    Closest,
+    LowestHealth,
+    /// Walks straight past enemy units to attack their tower
+    TowerOnly,
+    HighestCost,
+}
+
+/// Multiplies a unit's damage against units with a tag
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct TagBonus {
+    pub against: Tag,
+    pub damage: f32,
+}
+
+/// Most times a unit file can let its unit level up, build.rs holds the built in units to the
+/// same limit
+pub const MAX_LEVELS: usize = 10;
+
+/// How a unit's stats grow as it levels up, set with `levels` in its unit file. Each level adds
+/// the unit file's stat times the growth, so a health growth of 0.25 gives 125% health at
+/// level 1 and 150% at level 2
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct LevelCurve {
+    /// How many times the unit can level up
+    pub max: usize,
+    pub health: f32,
+    pub power: f32,
+    pub speed: f32,
+}
+
+impl LevelCurve {
+    /// Used by unit files that don't declare their own curve
+    pub const STANDARD: Self = Self {
+        max: 3,
+        health: 0.25,
+        power: 0.25,
+        speed: 0.0,
+    };
+}
+
+impl Default for LevelCurve {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl Drawable for Unit<'_> {
+    fn cost(&self) -> usize {
+        self.cost
+    }
+
+    fn rarity(&self) -> Rarity {
+        self.rarity
+    }
+}
+
+/// Anything that can be shuffled into a draw order by `DrawRules`
+pub trait Drawable: Copy {
+    fn cost(&self) -> usize;
+    fn rarity(&self) -> Rarity;
 }
 
 #[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
@@ -31,54 +257,299 @@ pub enum AttackType {
     Single,
 }
 
-pub fn draw_hand<'a, const NUM: usize>() -> Option<[Unit<'a>; NUM]> {
-    let cards = UNITS.clone();
-    if NUM > cards.len() {
-        return None;
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    /// Relative chance of a card with this rarity being drawn when drawing is weighted
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::Common => 12,
+            Self::Rare => 6,
+            Self::Epic => 3,
+            Self::Legendary => 1,
+        }
     }
+}
 
-    let mut units = [Unit::default(); NUM];
-    let mut cards_available = cards;
+/// How cards are drawn from a deck or unit pack into a hand, set per game mode
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct DrawRules {
+    /// Draw cards with a chance based on their rarity instead of uniformly
+    pub weighted: bool,
+    /// An opening hand gets at least this many cards costing `cheap_cost` or less, as long as
+    /// the cards being drawn from have that many
+    pub min_cheap_cards: usize,
+    pub cheap_cost: usize,
+}
 
-    let mut rng = rand::thread_rng();
+impl DrawRules {
+    /// Every card is equally likely and nothing is guaranteed
+    pub const UNIFORM: Self = Self {
+        weighted: false,
+        min_cheap_cards: 0,
+        cheap_cost: 0,
+    };
 
-    cards_available.shuffle(&mut rng);
+    /// Fixed hands are stuck with what they're dealt, so make sure they can play something
+    pub const FIXED_HAND: Self = Self {
+        weighted: true,
+        min_cheap_cards: 2,
+        cheap_cost: 300,
+    };
 
-    for unit in units.iter_mut().take(NUM) {
-        if let Some(drawn) = cards_available.pop() {
-            *unit = drawn;
-        } else {
-            return None;
+    /// Cycling hands see their whole deck eventually so only one cheap card is guaranteed
+    pub const CYCLE_HAND: Self = Self {
+        weighted: true,
+        min_cheap_cards: 1,
+        cheap_cost: 300,
+    };
+
+    /// Puts every card in the order it'll be drawn, with the first `hand_size` cards being the
+    /// opening hand
+    pub fn draw_order<C: Drawable, R: Rng>(
+        &self,
+        cards: &[C],
+        hand_size: usize,
+        rng: &mut R,
+    ) -> Vec<C> {
+        let mut remaining = cards.to_vec();
+        let mut order = Vec::with_capacity(cards.len());
+
+        while order.len() < self.min_cheap_cards.min(hand_size) {
+            match self.pick(&mut remaining, rng, |card| card.cost() <= self.cheap_cost) {
+                Some(card) => order.push(card),
+                None => break,
+            }
+        }
+
+        while let Some(card) = self.pick(&mut remaining, rng, |_| true) {
+            order.push(card);
         }
+
+        // Guaranteed cards shouldn't always end up in the first slots of the hand
+        let hand_size = hand_size.min(order.len());
+        order[..hand_size].shuffle(rng);
+
+        order
     }
 
-    Some(units)
+    fn pick<C: Drawable, R: Rng>(
+        &self,
+        remaining: &mut Vec<C>,
+        rng: &mut R,
+        allowed: impl Fn(&C) -> bool,
+    ) -> Option<C> {
+        let weights = remaining.iter().map(|card| match allowed(card) {
+            false => 0,
+            true if self.weighted => card.rarity().weight(),
+            true => 1,
+        });
+
+        // Fails when nothing is left that's allowed to be drawn
+        let index = WeightedIndex::new(weights).ok()?.sample(rng);
+        Some(remaining.swap_remove(index))
+    }
+}
+
+/// Draws a hand of unique units, or `None` if there aren't that many units to draw from
+pub fn draw_hand(size: usize) -> Option<Vec<Unit<'static>>> {
+    if size > UNITS.len() {
+        return None;
+    }
+
+    let mut cards_available = UNITS.to_vec();
+    cards_available.shuffle(&mut rand::thread_rng());
+    cards_available.truncate(size);
+
+    Some(cards_available)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{draw_hand, Unit};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{draw_hand, DrawRules, LevelCurve, Rarity, Tag, TagBonus, Tags, Unit};
+    use crate::game::card_gen::UNITS;
+
     const LARGE_NUMBER: usize = 9999;
+    const TRIALS: usize = 20_000;
 
     #[test]
     fn draw_hand_greater_than_cards_len_is_none() {
-        let attempted_draw = draw_hand::<LARGE_NUMBER>();
+        let attempted_draw = draw_hand(LARGE_NUMBER);
 
         assert!(attempted_draw.is_none())
     }
 
     #[test]
     fn draw_hand_less_than_cards_len_is_some_and_valid() {
-        let valid_draw = draw_hand::<2>();
+        let valid_draw = draw_hand(2);
 
         assert!(valid_draw.is_some_and(|hand| hand.len() == 2))
     }
 
     #[test]
     fn draw_hand_actually_draws_uniquely_and_not_default_units() {
-        let valid_draw: [Unit; 5] = draw_hand().expect("Draw a hand of 5 units");
+        let valid_draw = draw_hand(5).expect("Draw a hand of 5 units");
         for unit in valid_draw {
             assert_ne!(unit, Unit::default())
         }
     }
+
+    fn unit(id: usize, cost: usize, rarity: Rarity) -> Unit<'static> {
+        Unit {
+            id,
+            cost,
+            rarity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn draw_order_keeps_every_card_once() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let order = DrawRules::FIXED_HAND.draw_order(UNITS, 5, &mut rng);
+
+        let mut ids: Vec<_> = order.iter().map(|unit| unit.get_id()).collect();
+        ids.sort();
+        assert_eq!(ids, (0..UNITS.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn weighted_draws_follow_rarity_weights() {
+        let cards = [
+            unit(0, 100, Rarity::Common),
+            unit(1, 100, Rarity::Rare),
+            unit(2, 100, Rarity::Epic),
+            unit(3, 100, Rarity::Legendary),
+        ];
+        let total: u32 = cards.iter().map(|card| card.rarity.weight()).sum();
+
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut first_drawn = [0usize; 4];
+        for _ in 0..TRIALS {
+            let order = DrawRules::FIXED_HAND.draw_order(&cards, 1, &mut rng);
+            first_drawn[order[0].get_id()] += 1;
+        }
+
+        for (card, drawn) in cards.iter().zip(first_drawn) {
+            let expected = card.rarity.weight() as f64 / total as f64;
+            let observed = drawn as f64 / TRIALS as f64;
+
+            assert!(
+                (expected - observed).abs() < 0.015,
+                "{:?} drawn {:.3} of the time, expected {:.3}",
+                card.rarity,
+                observed,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_draws_ignore_rarity() {
+        let cards = [
+            unit(0, 100, Rarity::Common),
+            unit(1, 100, Rarity::Legendary),
+        ];
+
+        let mut rng = StdRng::seed_from_u64(32);
+        let legendary_first = (0..TRIALS)
+            .filter(|_| DrawRules::UNIFORM.draw_order(&cards, 1, &mut rng)[0].get_id() == 1)
+            .count();
+
+        let observed = legendary_first as f64 / TRIALS as f64;
+        assert!((observed - 0.5).abs() < 0.015);
+    }
+
+    #[test]
+    fn opening_hands_get_guaranteed_cheap_cards() {
+        let mut rng = StdRng::seed_from_u64(33);
+        let rules = DrawRules::FIXED_HAND;
+
+        for _ in 0..TRIALS / 10 {
+            let order = rules.draw_order(UNITS, 5, &mut rng);
+            let cheap = order[..5]
+                .iter()
+                .filter(|unit| unit.cost <= rules.cheap_cost)
+                .count();
+
+            assert!(cheap >= rules.min_cheap_cards);
+        }
+    }
+
+    #[test]
+    fn guarantee_is_best_effort_when_there_arent_enough_cheap_cards() {
+        let cards = [
+            unit(0, 100, Rarity::Common),
+            unit(1, 5000, Rarity::Legendary),
+            unit(2, 5000, Rarity::Legendary),
+        ];
+
+        let mut rng = StdRng::seed_from_u64(34);
+        let order = DrawRules::FIXED_HAND.draw_order(&cards, 2, &mut rng);
+
+        assert_eq!(order.len(), 3);
+        assert!(order[..2].iter().any(|unit| unit.get_id() == 0));
+    }
+
+    #[test]
+    fn levels_grow_stats_along_the_curve_up_to_the_max() {
+        let base = Unit {
+            cost: 100,
+            health: 100,
+            power: 20,
+            speed: 1.0,
+            levels: LevelCurve {
+                max: 2,
+                health: 0.5,
+                power: 0.25,
+                speed: 0.0,
+            },
+            ..Default::default()
+        };
+
+        let first = base.level_up().unwrap();
+        let second = first.level_up().unwrap();
+        assert_eq!(second.level_up(), None);
+        assert_eq!(base.level_up_cost(), Some(100));
+        assert_eq!(first.level_up_cost(), Some(200));
+        assert_eq!(second.level_up_cost(), None);
+
+        let leveled = second.leveled();
+        assert_eq!((leveled.health, leveled.power), (200, 30));
+        assert_eq!(leveled.speed, 1.0);
+        assert_eq!(base.leveled(), base);
+    }
+
+    #[test]
+    fn bonus_damage_only_hits_units_with_the_tag() {
+        let hunter = Unit {
+            power: 10,
+            bonus: Some(TagBonus {
+                against: Tag::Undead,
+                damage: 2.5,
+            }),
+            ..Default::default()
+        };
+        let skeleton = Unit {
+            tags: Tags::of(&[Tag::Undead, Tag::Heavy]),
+            ..Default::default()
+        };
+
+        assert_eq!(hunter.damage_against(&skeleton), 25);
+        assert_eq!(hunter.damage_against(&Unit::default()), 10);
+        assert!(!skeleton.has_tag(Tag::Flying));
+        assert_eq!(
+            serde_json::to_value(skeleton.tags).unwrap(),
+            serde_json::json!(["Heavy", "Undead"])
+        );
+    }
 }
@@ -0,0 +1,36 @@
+/// THIS FILE IS AUTOGENERATED BY BUILD.RS
+/// TO ADD NEW FAMILIES, ADD A NEW FILE TO THE FAMILIES DIRECTORY
+use crate::game::family::{Family, Tier};
+
+pub static FAMILIES: &[Family] = &[
+    Family {
+        name: "Critters",
+        emoji: '🐾',
+        tiers: &[
+            Tier { units: 3, speed: 0.0, power: 0.1 },
+            Tier { units: 5, speed: 0.0, power: 0.2 },
+        ],
+    },
+    Family {
+        name: "Faces",
+        emoji: '😀',
+        tiers: &[
+            Tier { units: 3, speed: 0.05, power: 0.05 },
+        ],
+    },
+    Family {
+        name: "Heroes",
+        emoji: '🏅',
+        tiers: &[
+            Tier { units: 2, speed: 0.0, power: 0.15 },
+        ],
+    },
+    Family {
+        name: "Spooky",
+        emoji: '👻',
+        tiers: &[
+            Tier { units: 2, speed: 0.05, power: 0.0 },
+            Tier { units: 3, speed: 0.1, power: 0.0 },
+        ],
+    },
+];
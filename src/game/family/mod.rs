@@ -0,0 +1,102 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+pub use families::FAMILIES;
+
+// Generated by build.rs, which doesn't bother matching rustfmt's layout for short lists
+#[rustfmt::skip]
+mod families;
+
+/// A group of units that buff their whole team when enough of them are on the field together
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Family {
+    pub name: &'static str,
+    pub emoji: char,
+    /// Buffs from fewest units needed to most, only the highest one reached applies
+    pub tiers: &'static [Tier],
+}
+
+/// A buff a family gives once a side has `units` of it on the field. Speed and power are
+/// fractions added on top of every unit's own, so 0.1 is 10% faster
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tier {
+    pub units: usize,
+    pub speed: f32,
+    pub power: f32,
+}
+
+/// A family whose buff a side has earned
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Synergy {
+    pub family: &'static str,
+    pub emoji: char,
+    /// How many units of the family the side has on the field
+    pub units: usize,
+    pub tier: Tier,
+}
+
+/// A synergy as sent over the wire, its family is looked up by name when read back
+#[derive(Deserialize)]
+struct SentSynergy {
+    family: String,
+    units: usize,
+    tier: Tier,
+}
+
+impl<'de> Deserialize<'de> for Synergy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let synergy = SentSynergy::deserialize(deserializer)?;
+        let family = Family::get(&synergy.family).ok_or_else(|| {
+            de::Error::custom(format!("There's no family called {}", synergy.family))
+        })?;
+
+        Ok(Self {
+            family: family.name,
+            emoji: family.emoji,
+            units: synergy.units,
+            tier: synergy.tier,
+        })
+    }
+}
+
+impl Family {
+    pub fn get(name: &str) -> Option<&'static Family> {
+        FAMILIES.iter().find(|family| family.name == name)
+    }
+
+    /// The buff for having `units` of the family on the field, if that's enough for one
+    pub fn tier(&self, units: usize) -> Option<Tier> {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| units >= tier.units)
+            .copied()
+    }
+}
+
+impl Tier {
+    /// Every buff in `synergies` added together
+    pub fn total(synergies: &[Synergy]) -> Self {
+        synergies
+            .iter()
+            .fold(Self::default(), |total, synergy| Self {
+                units: total.units + synergy.units,
+                speed: total.speed + synergy.tier.speed,
+                power: total.power + synergy.tier.power,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Family, FAMILIES};
+
+    #[test]
+    fn the_highest_tier_reached_applies() {
+        let spooky = Family::get("Spooky").unwrap();
+
+        assert_eq!(spooky.tier(1), None);
+        assert_eq!(spooky.tier(2), Some(spooky.tiers[0]));
+        assert_eq!(spooky.tier(5), Some(spooky.tiers[1]));
+        assert!(FAMILIES.iter().all(|family| !family.tiers.is_empty()));
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    card::{Card, CardId},
+    entity::DrawRules,
+};
+
+/// How many cards a cycling player owns when they haven't picked a saved deck
+pub const CYCLE_DECK_SIZE: usize = 8;
+/// How many cards a player can send back and redraw before a battle begins
+pub const MULLIGAN_REDRAWS: usize = 2;
+
+/// Match setting for what happens to a card once it's played
+#[derive(Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum HandMode {
+    /// The hand drawn at the start of the battle is kept the whole match
+    #[default]
+    Fixed,
+    /// Played cards go to the back of the player's deck and are replaced by the next card
+    Cycle,
+}
+
+/// The cards a player can play during a battle, and the rest of their deck waiting to be
+/// drawn by a mulligan or, in cycle mode, as cards are played
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hand<'a> {
+    cards: Vec<Card<'a>>,
+    queue: VecDeque<Card<'a>>,
+    mode: HandMode,
+    redraws_left: usize,
+}
+
+impl HandMode {
+    /// How hands are drawn in battles using this mode
+    pub fn draw_rules(&self) -> DrawRules {
+        match self {
+            Self::Fixed => DrawRules::FIXED_HAND,
+            Self::Cycle => DrawRules::CYCLE_HAND,
+        }
+    }
+}
+
+impl<'a> Hand<'a> {
+    /// Orders a deck using the draw rules and deals the first `size` cards into the hand, the
+    /// rest are queued up to be drawn later. Returns `None` if the deck can't fill a hand
+    pub fn deal(
+        deck: Vec<Card<'a>>,
+        size: usize,
+        mode: HandMode,
+        rules: &DrawRules,
+    ) -> Option<Self> {
+        Self::deal_with(deck, size, mode, rules, &mut rand::thread_rng())
+    }
+
+    /// Deals a hand like `deal`, shuffling with the given rng so the hand can be repeated
+    pub fn deal_with<R: Rng>(
+        deck: Vec<Card<'a>>,
+        size: usize,
+        mode: HandMode,
+        rules: &DrawRules,
+        rng: &mut R,
+    ) -> Option<Self> {
+        if deck.len() < size {
+            return None;
+        }
+
+        let mut deck = rules.draw_order(&deck, size, rng);
+        let queue = deck.split_off(size);
+
+        Some(Self {
+            cards: deck,
+            queue: queue.into(),
+            mode,
+            redraws_left: MULLIGAN_REDRAWS,
+        })
+    }
+
+    pub fn cards(&self) -> &[Card<'a>] {
+        &self.cards
+    }
+
+    pub fn mode(&self) -> HandMode {
+        self.mode
+    }
+
+    /// The card that will replace the next card played
+    pub fn next_card(&self) -> Option<Card<'a>> {
+        match self.mode {
+            HandMode::Fixed => None,
+            HandMode::Cycle => self.queue.front().copied(),
+        }
+    }
+
+    pub fn redraws_left(&self) -> usize {
+        self.redraws_left
+    }
+
+    /// The card with the given id, if it's in the hand
+    pub fn card(&self, card: CardId) -> Option<Card<'a>> {
+        self.cards.iter().find(|held| held.id() == card).copied()
+    }
+
+    /// Puts a different card in the slot of the card with the given id, like a leveled up copy
+    /// of it. Returns the card that was replaced
+    pub fn replace(&mut self, card: CardId, with: Card<'a>) -> Option<Card<'a>> {
+        let slot = self.cards.iter().position(|held| held.id() == card)?;
+
+        Some(std::mem::replace(&mut self.cards[slot], with))
+    }
+
+    /// Sends the card with the given id to the back of the deck and draws a new one in its
+    /// place, returning the new card. Fails if the card isn't in the hand, there's nothing left
+    /// to draw or the player is out of redraws
+    pub fn redraw(&mut self, card: CardId) -> Option<Card<'a>> {
+        if self.redraws_left == 0 {
+            return None;
+        }
+
+        let slot = self.cards.iter().position(|held| held.id() == card)?;
+        let next = self.queue.pop_front()?;
+
+        self.queue.push_back(self.cards[slot]);
+        self.cards[slot] = next;
+        self.redraws_left -= 1;
+
+        Some(next)
+    }
+
+    /// Plays the card with the given id, returning it if it was in the hand. When cycling, the
+    /// played card goes to the back of the deck and its slot is filled by the next card
+    pub fn play(&mut self, card: CardId) -> Option<Card<'a>> {
+        let slot = self.cards.iter().position(|held| held.id() == card)?;
+        let played = self.cards[slot];
+
+        if self.mode == HandMode::Cycle {
+            if let Some(next) = self.queue.pop_front() {
+                self.cards[slot] = next;
+                self.queue.push_back(played);
+            }
+        }
+
+        Some(played)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hand, HandMode, MULLIGAN_REDRAWS};
+    use crate::game::{
+        card::{Card, CardId},
+        card_gen::{SPELLS, UNITS},
+        entity::DrawRules,
+    };
+
+    fn deck(size: usize) -> Vec<Card<'static>> {
+        UNITS[..size].iter().copied().map(Card::from).collect()
+    }
+
+    #[test]
+    fn fixed_hand_keeps_its_cards() {
+        let mut hand = Hand::deal(deck(5), 3, HandMode::Fixed, &DrawRules::UNIFORM).unwrap();
+        let cards = hand.cards().to_vec();
+
+        assert_eq!(hand.play(cards[1].id()), Some(cards[1]));
+        assert_eq!(hand.cards(), cards);
+        assert_eq!(hand.next_card(), None);
+    }
+
+    #[test]
+    fn cycling_hand_replaces_played_card_with_next() {
+        let mut hand = Hand::deal(deck(5), 3, HandMode::Cycle, &DrawRules::UNIFORM).unwrap();
+        let cards = hand.cards().to_vec();
+        let next = hand.next_card().expect("Two cards left in the deck");
+
+        assert_eq!(hand.play(cards[0].id()), Some(cards[0]));
+        assert_eq!(hand.cards()[0], next);
+        assert_eq!(hand.cards()[1..], cards[1..]);
+
+        // Played cards come back around once the rest of the deck has been cycled through
+        let second_next = hand.next_card().unwrap();
+        hand.play(cards[1].id());
+        assert_eq!(hand.cards()[1], second_next);
+        assert_eq!(hand.next_card(), Some(cards[0]));
+    }
+
+    #[test]
+    fn redraws_swap_in_new_cards_until_the_limit() {
+        let mut hand = Hand::deal(deck(8), 3, HandMode::Fixed, &DrawRules::UNIFORM).unwrap();
+
+        for _ in 0..MULLIGAN_REDRAWS {
+            let old = hand.cards()[0];
+            let new = hand.redraw(old.id()).expect("Redraw a card");
+
+            assert_ne!(old, new);
+            assert_eq!(hand.cards()[0], new);
+        }
+
+        assert_eq!(hand.redraws_left(), 0);
+        assert_eq!(hand.redraw(hand.cards()[0].id()), None);
+    }
+
+    #[test]
+    fn cards_not_in_hand_cant_be_played() {
+        let mut hand = Hand::deal(deck(3), 3, HandMode::Cycle, &DrawRules::UNIFORM).unwrap();
+
+        assert_eq!(hand.play(CardId::Unit(4)), None);
+        assert!(Hand::deal(deck(2), 3, HandMode::Fixed, &DrawRules::UNIFORM).is_none());
+    }
+
+    #[test]
+    fn spells_are_played_by_their_own_id() {
+        let cards = vec![Card::from(UNITS[0]), Card::from(SPELLS[0])];
+        let mut hand = Hand::deal(cards, 2, HandMode::Fixed, &DrawRules::UNIFORM).unwrap();
+
+        assert_eq!(hand.play(CardId::Spell(0)), Some(Card::from(SPELLS[0])));
+        assert_eq!(hand.play(CardId::Unit(0)), Some(Card::from(UNITS[0])));
+        assert_eq!(hand.play(CardId::Spell(1)), None);
+    }
+}
@@ -0,0 +1,49 @@
+/// THIS FILE IS AUTOGENERATED BY BUILD.RS
+/// TO ADD NEW MAPS, ADD A NEW FILE TO THE MAPS DIRECTORY
+use crate::game::map::{Map, Obstacle, Point, Zone};
+
+pub static MAPS: &[Map] = &[
+    Map {
+        name: "Meadow",
+        lanes: 1,
+        length: 1500.0,
+        towers: [
+            Point { x: 0.1, y: 0.7 },
+            Point { x: 0.9, y: 0.7 },
+        ],
+        zones: &[
+        ],
+        obstacles: &[
+        ],
+    },
+    Map {
+        name: "Twin Bridges",
+        lanes: 2,
+        length: 1800.0,
+        towers: [
+            Point { x: 0.1, y: 0.7 },
+            Point { x: 0.9, y: 0.7 },
+        ],
+        zones: &[
+            Zone { lane: None, start: 0.42, end: 0.58, speed: 0.6 },
+        ],
+        obstacles: &[
+        ],
+    },
+    Map {
+        name: "Crossroads",
+        lanes: 3,
+        length: 1500.0,
+        towers: [
+            Point { x: 0.08, y: 0.68 },
+            Point { x: 0.92, y: 0.68 },
+        ],
+        zones: &[
+            Zone { lane: Some(1), start: 0.2, end: 0.8, speed: 1.3 },
+        ],
+        obstacles: &[
+            Obstacle { lane: 0, position: 0.5, health: 400 },
+            Obstacle { lane: 2, position: 0.5, health: 400 },
+        ],
+    },
+];
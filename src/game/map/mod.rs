@@ -0,0 +1,140 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+pub use maps::MAPS;
+
+// Generated by build.rs, which doesn't bother matching rustfmt's layout for short lists
+#[rustfmt::skip]
+mod maps;
+
+/// Layout of the battlefield between every pair of towers. Spots along a lane are fractions
+/// of it from 0.0 at the lower numbered side's tower to 1.0 at the other, so the same map
+/// works at any length and on any screen
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Map {
+    pub name: &'static str,
+    /// How many side by side lanes units can walk down, numbered from 0
+    pub lanes: usize,
+    /// How long every lane is, units take longer to cross longer lanes
+    pub length: f32,
+    /// Where the towers at either end of a lane are drawn, from (0, 0) at the top left of the
+    /// screen to (1, 1) at the bottom right. Clients draw their own tower at the first
+    pub towers: [Point; 2],
+    pub zones: &'static [Zone],
+    pub obstacles: &'static [Obstacle],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A stretch of terrain that changes how fast units walk through it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Zone {
+    /// The lane the zone covers, or every lane if `None`
+    pub lane: Option<usize>,
+    pub start: f32,
+    pub end: f32,
+    /// Multiplies the speed of units walking through, below 1.0 slows them down
+    pub speed: f32,
+}
+
+/// Something in the way that units have to break through before they can walk past it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Obstacle {
+    pub lane: usize,
+    pub position: f32,
+    pub health: usize,
+}
+
+/// Every map is built in, so a map sent over the wire is read back by finding the one with its
+/// name
+#[derive(Deserialize)]
+struct MapName {
+    name: String,
+}
+
+impl<'de> Deserialize<'de> for Map {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = MapName::deserialize(deserializer)?;
+
+        MAPS.iter()
+            .find(|built_in| built_in.name == map.name)
+            .copied()
+            .ok_or_else(|| de::Error::custom(format!("There's no map called {}", map.name)))
+    }
+}
+
+impl Map {
+    /// The lane units are sent down when a player doesn't pick one
+    pub fn middle_lane(&self) -> usize {
+        self.lanes / 2
+    }
+
+    /// How much faster than normal units walk at a spot in a lane, overlapping zones stack
+    pub fn speed_at(&self, lane: usize, spot: f32) -> f32 {
+        self.zones
+            .iter()
+            .filter(|zone| zone.lane.is_none_or(|zone_lane| zone_lane == lane))
+            .filter(|zone| (zone.start..=zone.end).contains(&spot))
+            .map(|zone| zone.speed)
+            .product()
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        MAPS[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Map, Zone, MAPS};
+
+    #[test]
+    fn every_map_has_lanes_and_the_default_is_plain() {
+        assert!(MAPS.iter().all(|map| map.lanes > 0 && map.length > 0.0));
+        assert_eq!(MAPS[0].lanes, 1);
+        assert!(MAPS[0].zones.is_empty() && MAPS[0].obstacles.is_empty());
+    }
+
+    #[test]
+    fn zones_only_change_speed_in_their_lane() {
+        let map = Map {
+            lanes: 2,
+            zones: &[
+                Zone {
+                    lane: Some(0),
+                    start: 0.2,
+                    end: 0.4,
+                    speed: 0.5,
+                },
+                Zone {
+                    lane: None,
+                    start: 0.3,
+                    end: 0.6,
+                    speed: 2.0,
+                },
+            ],
+            ..Map::default()
+        };
+
+        assert_eq!(map.speed_at(0, 0.1), 1.0);
+        assert_eq!(map.speed_at(0, 0.25), 0.5);
+        assert_eq!(map.speed_at(0, 0.35), 1.0);
+        assert_eq!(map.speed_at(1, 0.25), 1.0);
+        assert_eq!(map.speed_at(1, 0.5), 2.0);
+    }
+
+    #[test]
+    fn maps_are_read_back_by_name() {
+        for map in MAPS {
+            let json = serde_json::to_string(map).unwrap();
+            assert_eq!(serde_json::from_str::<Map>(&json).unwrap(), *map);
+        }
+
+        assert!(serde_json::from_str::<Map>(r#"{"name": "Nowhere"}"#).is_err());
+    }
+}
@@ -1,3 +1,17 @@
 pub mod battle;
+pub mod bot;
+pub mod card;
 pub mod card_gen;
+pub mod deck;
+pub mod engine;
 pub mod entity;
+pub mod family;
+pub mod hand;
+pub mod map;
+pub mod rules;
+pub mod sim;
+pub mod spell;
+pub mod stage;
+pub mod strategy;
+pub mod tournament;
+pub mod tower;
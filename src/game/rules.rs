@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::server::state::GAME_HAND_SIZE;
+
+use super::{engine::TICK, map::MAPS};
+
+/// Most cards a hand can hold, so every card still fits on the screen
+pub const MAX_HAND_SIZE: usize = 8;
+/// Most health a tower can be given
+pub const MAX_TOWER_HEALTH: usize = 100_000;
+/// Seconds of normal play in a timed battle unless the rules say otherwise
+pub const DEFAULT_TIME_LIMIT: u64 = 180;
+/// Longest time limit a battle can be given, a day
+pub const MAX_TIME_LIMIT: u64 = 24 * 60 * 60;
+/// Longest sudden death a battle can be given, an hour
+pub const MAX_SUDDEN_DEATH: u64 = 60 * 60;
+
+/// Settings a battle is played with, picked by whoever starts it or by the game mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchRules {
+    /// Health every tower starts with, except a stage's which is set by the stage
+    pub tower_health: usize,
+    /// How many cards each player holds
+    pub hand_size: usize,
+    /// Money each player starts the battle with
+    pub starting_money: usize,
+    /// Players earn a coin every this many ticks
+    pub income_ticks: u64,
+    /// Seconds of normal play before sudden death begins, or `None` to play until a tower falls
+    pub time_limit: Option<u64>,
+    /// Seconds sudden death lasts before the battle goes to a tiebreak on tower health
+    pub sudden_death: u64,
+    /// Index of the map in `MAPS` the battle is fought on
+    pub map: usize,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            tower_health: 15000,
+            hand_size: GAME_HAND_SIZE,
+            starting_money: 50,
+            income_ticks: 3,
+            time_limit: None,
+            sudden_death: 60,
+            map: 0,
+        }
+    }
+}
+
+impl MatchRules {
+    /// The default rules with a time limit, for modes played against the clock
+    pub fn timed() -> Self {
+        Self {
+            time_limit: Some(DEFAULT_TIME_LIMIT),
+            ..Self::default()
+        }
+    }
+
+    /// Checks the rules make for a battle that can actually be played
+    pub fn validate(&self) -> RulesResult<()> {
+        if self.tower_health == 0 || self.tower_health > MAX_TOWER_HEALTH {
+            return Err(RulesError::InvalidTowerHealth(self.tower_health));
+        }
+        if self.hand_size == 0 || self.hand_size > MAX_HAND_SIZE {
+            return Err(RulesError::InvalidHandSize(self.hand_size));
+        }
+        if self.income_ticks == 0 {
+            return Err(RulesError::NoIncome);
+        }
+        match self.time_limit {
+            Some(0) => return Err(RulesError::NoTime),
+            Some(secs) if secs > MAX_TIME_LIMIT => return Err(RulesError::TooLong(secs)),
+            _ => {}
+        }
+        if self.sudden_death > MAX_SUDDEN_DEATH {
+            return Err(RulesError::SuddenDeathTooLong(self.sudden_death));
+        }
+        if self.map >= MAPS.len() {
+            return Err(RulesError::UnknownMap(self.map));
+        }
+
+        Ok(())
+    }
+
+    /// Tick sudden death begins on, if the battle has a time limit
+    pub fn sudden_death_tick(&self) -> Option<u64> {
+        self.time_limit.map(secs_to_ticks)
+    }
+
+    /// Tick the battle goes to a tiebreak on, if the battle has a time limit
+    pub fn tiebreak_tick(&self) -> Option<u64> {
+        self.sudden_death_tick()
+            .map(|tick| tick.saturating_add(secs_to_ticks(self.sudden_death)))
+    }
+}
+
+fn secs_to_ticks(secs: u64) -> u64 {
+    secs.saturating_mul(1000) / TICK.as_millis() as u64
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum RulesError {
+    #[error("Towers must have between 1 and {MAX_TOWER_HEALTH} health, not {0}")]
+    InvalidTowerHealth(usize),
+    #[error("Hands must hold between 1 and {MAX_HAND_SIZE} cards, not {0}")]
+    InvalidHandSize(usize),
+    #[error("Players have to earn income at least once every so many ticks")]
+    NoIncome,
+    #[error("Time limits have to be at least a second long")]
+    NoTime,
+    #[error("Time limits can be at most {MAX_TIME_LIMIT} seconds, not {0}")]
+    TooLong(u64),
+    #[error("Sudden death can last at most {MAX_SUDDEN_DEATH} seconds, not {0}")]
+    SuddenDeathTooLong(u64),
+    #[error("There's no map number {0}")]
+    UnknownMap(usize),
+}
+
+pub type RulesResult<T> = std::result::Result<T, RulesError>;
+
+#[cfg(test)]
+mod tests {
+    use super::{MatchRules, RulesError};
+
+    #[test]
+    fn default_rules_are_valid() {
+        assert_eq!(MatchRules::default().validate(), Ok(()));
+        assert_eq!(MatchRules::timed().validate(), Ok(()));
+        assert_eq!(MatchRules::default().tiebreak_tick(), None);
+    }
+
+    #[test]
+    fn unplayable_rules_are_rejected() {
+        let no_cards = MatchRules {
+            hand_size: 0,
+            ..Default::default()
+        };
+        let no_income = MatchRules {
+            income_ticks: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(no_cards.validate(), Err(RulesError::InvalidHandSize(0)));
+        assert_eq!(no_income.validate(), Err(RulesError::NoIncome));
+    }
+
+    #[test]
+    fn time_limits_are_bounded() {
+        let forever = MatchRules {
+            time_limit: Some(u64::MAX),
+            ..Default::default()
+        };
+        let endless_sudden_death = MatchRules {
+            sudden_death: u64::MAX,
+            ..MatchRules::timed()
+        };
+
+        assert_eq!(forever.validate(), Err(RulesError::TooLong(u64::MAX)));
+        assert_eq!(
+            endless_sudden_death.validate(),
+            Err(RulesError::SuddenDeathTooLong(u64::MAX))
+        );
+        // Even rules that were never validated can't wrap around to an earlier tick
+        assert!(endless_sudden_death.tiebreak_tick() > MatchRules::timed().tiebreak_tick());
+    }
+
+    #[test]
+    fn missing_fields_use_the_defaults() {
+        let rules: MatchRules = serde_json::from_str(r#"{"tower_health": 5000}"#).unwrap();
+
+        assert_eq!(rules.tower_health, 5000);
+        assert_eq!(rules.hand_size, MatchRules::default().hand_size);
+    }
+}
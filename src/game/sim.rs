@@ -0,0 +1,428 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Arc, time::Duration};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::Serialize;
+
+use super::{
+    card::Card,
+    card_gen::pack::UnitPack,
+    engine::{Field, FieldEvent, Side, TICK},
+    entity::Unit,
+    hand::HandMode,
+    rules::{MatchRules, RulesError, DEFAULT_TIME_LIMIT},
+    strategy::{StrategyRegistry, DEFAULT_STRATEGY},
+    tournament::play_game,
+};
+use crate::server::state::GAME_HAND_SIZE;
+
+/// Ticks between each side sending out its next unit, so armies walk out in a line instead of
+/// all standing on top of each other
+pub const SPAWN_INTERVAL: u64 = 50;
+
+/// What `simulate` runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    /// Money each side spends on its army, or starts a hand battle with. Every pairing is
+    /// fought once per budget
+    pub budgets: Vec<usize>,
+    /// How many random hands are pitted against each other per budget
+    pub hand_pairings: usize,
+    pub hand_size: usize,
+    /// The strategy both sides play their hands with
+    pub strategy: String,
+    /// Seeds the hands that are drawn and the battles they play, so runs can be repeated
+    pub seed: u64,
+    /// Army fights that go on longer than this are draws, hand battles go into sudden death
+    /// like timed battles do
+    pub time_limit: Duration,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            budgets: vec![500, 1000, 2000],
+            hand_pairings: 200,
+            hand_size: GAME_HAND_SIZE,
+            strategy: DEFAULT_STRATEGY.to_string(),
+            seed: 0,
+            time_limit: Duration::from_secs(DEFAULT_TIME_LIMIT),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum SimKind {
+    /// Armies made of copies of a single unit
+    Unit,
+    /// Hands of different units played by a strategy in a full battle
+    Hand,
+}
+
+/// How a unit or hand did across every fight at one budget
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SimRow {
+    pub kind: SimKind,
+    /// The unit's name, or the emojis of every unit in the hand
+    pub name: String,
+    pub budget: usize,
+    pub fights: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    /// Draws count as half a win
+    pub win_rate: f32,
+    /// Average seconds taken to wipe out the other army, or to win the battle, in fights that
+    /// were won
+    pub time_to_kill: Option<f32>,
+    /// Cost of the enemy units killed for every coin spent on cards
+    pub cost_efficiency: f32,
+}
+
+/// How a single fight between two armies or hands went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FightResult {
+    /// The side that wiped out the other, `None` for a draw
+    pub winner: Option<Side>,
+    pub ticks: u64,
+    /// Cost of the units each side killed, indexed by side
+    pub killed: [usize; 2],
+    /// Cost of each side's army, or of the cards it played, indexed by side
+    pub spent: [usize; 2],
+}
+
+#[derive(Default)]
+struct Tally {
+    fights: usize,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    kill_ticks: u64,
+    killed: usize,
+    spent: usize,
+}
+
+/// Runs every unit against every other unit and random hands against each other at every
+/// budget, as fast as the engine goes. Single units are bought as armies and fought out on a
+/// bare field, hands are played by the configured strategy in a battle with the budget as
+/// starting money, so costs, cooldowns, income and towers all count
+pub fn simulate(pack: &UnitPack, config: &SimConfig) -> SimResult<Vec<SimRow>> {
+    let strategies = StrategyRegistry::default();
+    if strategies.create(&config.strategy).is_none() {
+        return Err(SimError::NoSuchStrategy(config.strategy.clone()));
+    }
+
+    let time_limit = config.time_limit.as_millis() as u64 / TICK.as_millis() as u64;
+    let mut tallies: BTreeMap<(SimKind, String, usize), Tally> = BTreeMap::new();
+    let mut record = |kind, names: [String; 2], budget, result: FightResult| {
+        for side in [Side::A, Side::B] {
+            let tally = tallies
+                .entry((kind, names[side.index()].clone(), budget))
+                .or_default();
+            tally.fights += 1;
+            tally.killed += result.killed[side.index()];
+            tally.spent += result.spent[side.index()];
+            match result.winner {
+                Some(winner) if winner == side => {
+                    tally.wins += 1;
+                    tally.kill_ticks += result.ticks;
+                }
+                Some(_) => tally.losses += 1,
+                None => tally.draws += 1,
+            }
+        }
+    };
+
+    let units = pack.units();
+    for &budget in &config.budgets {
+        for (i, a) in units.iter().enumerate() {
+            for b in &units[i + 1..] {
+                let armies = [army(&[*a], budget), army(&[*b], budget)];
+                if armies.iter().any(Vec::is_empty) {
+                    continue;
+                }
+
+                let names = [a.get_name().to_string(), b.get_name().to_string()];
+                record(SimKind::Unit, names, budget, fight(&armies, time_limit));
+            }
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let hand_size = config.hand_size.min(units.len());
+    let pack = Arc::new(pack.clone());
+    let rules = |budget| MatchRules {
+        hand_size,
+        starting_money: budget,
+        time_limit: Some(config.time_limit.as_secs()),
+        ..MatchRules::default()
+    };
+    // Starting money isn't checked, so any budget will do
+    rules(0).validate()?;
+
+    for &budget in &config.budgets {
+        for _ in 0..config.hand_pairings {
+            let hands: [Vec<Unit>; 2] = [(); 2].map(|_| {
+                let mut hand: Vec<Unit> = units
+                    .choose_multiple(&mut rng, hand_size)
+                    .copied()
+                    .collect();
+                // The same hand drawn in a different order is still the same hand
+                hand.sort_by_key(|unit| unit.get_id());
+                hand
+            });
+            let players = [(); 2].map(|_| {
+                strategies
+                    .create(&config.strategy)
+                    .expect("The strategy was checked to exist")
+            });
+            let decks = hands
+                .clone()
+                .map(|hand| hand.into_iter().map(Card::from).collect());
+            let game = play_game(
+                pack.clone(),
+                players,
+                decks,
+                rules(budget),
+                HandMode::Fixed,
+                &mut rng,
+            );
+
+            let names = hands.map(|hand| hand.iter().map(|unit| unit.emoji).collect());
+            let result = FightResult {
+                winner: game.winner,
+                ticks: game.ticks,
+                killed: game.killed,
+                spent: game.spent,
+            };
+            record(SimKind::Hand, names, budget, result);
+        }
+    }
+
+    let rows = tallies
+        .into_iter()
+        .map(|((kind, name, budget), tally)| SimRow {
+            kind,
+            name,
+            budget,
+            fights: tally.fights,
+            wins: tally.wins,
+            losses: tally.losses,
+            draws: tally.draws,
+            win_rate: (tally.wins as f32 + tally.draws as f32 / 2.0) / tally.fights as f32,
+            time_to_kill: (tally.wins > 0).then(|| {
+                let ticks = tally.kill_ticks as f32 / tally.wins as f32;
+                ticks * TICK.as_millis() as f32 / 1000.0
+            }),
+            cost_efficiency: tally.killed as f32 / tally.spent.max(1) as f32,
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Spends a budget on cards from a hand, buying each card in turn for as long as there's
+/// money for any of them. Free units would make for an endless army so they're left out
+pub fn army<'a>(hand: &[Unit<'a>], budget: usize) -> Vec<Unit<'a>> {
+    let mut money = budget;
+    let mut army = vec![];
+    let affordable = |unit: &Unit, money: usize| unit.cost > 0 && unit.cost <= money;
+
+    while hand.iter().any(|unit| affordable(unit, money)) {
+        for unit in hand {
+            if affordable(unit, money) {
+                money -= unit.cost;
+                army.push(*unit);
+            }
+        }
+    }
+
+    army
+}
+
+/// Sends two armies at each other one unit at a time until one of them is wiped out or the
+/// fight runs out of time. Army 0 fights for side A
+pub fn fight(armies: &[Vec<Unit>; 2], time_limit: u64) -> FightResult {
+    let mut field = Field::default();
+    let mut killed = [0, 0];
+    let spent = armies
+        .clone()
+        .map(|army| army.iter().map(|unit| unit.cost).sum());
+
+    for ticks in 0..time_limit {
+        if ticks % SPAWN_INTERVAL == 0 {
+            let next = (ticks / SPAWN_INTERVAL) as usize;
+            for side in [Side::A, Side::B] {
+                if let Some(unit) = armies[side.index()].get(next) {
+                    field.spawn(*unit, side);
+                }
+            }
+        }
+
+        for event in field.tick() {
+            if let FieldEvent::UnitKilled { unit, killer, .. } = event {
+                killed[killer.index()] += unit.cost;
+            }
+        }
+
+        let spawned = (ticks / SPAWN_INTERVAL) as usize + 1;
+        let wiped = [Side::A, Side::B].map(|side| {
+            spawned >= armies[side.index()].len()
+                && field.units().iter().all(|unit| unit.side != side)
+        });
+        let winner = match wiped {
+            [false, false] => continue,
+            [true, false] => Some(Side::B),
+            [false, true] => Some(Side::A),
+            [true, true] => None,
+        };
+
+        return FightResult {
+            winner,
+            ticks: ticks + 1,
+            killed,
+            spent,
+        };
+    }
+
+    FightResult {
+        winner: None,
+        ticks: time_limit,
+        killed,
+        spent,
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SimError {
+    #[error("There is no strategy called {0}")]
+    NoSuchStrategy(String),
+    #[error("Invalid rules: {0}")]
+    InvalidRules(#[from] RulesError),
+}
+
+pub type SimResult<T> = std::result::Result<T, SimError>;
+
+/// Writes rows as CSV with a header line
+pub fn to_csv(rows: &[SimRow]) -> String {
+    let mut csv = String::from(
+        "kind,name,budget,fights,wins,losses,draws,win_rate,time_to_kill,cost_efficiency\n",
+    );
+
+    for row in rows {
+        let time_to_kill = row
+            .time_to_kill
+            .map_or(String::new(), |seconds| format!("{seconds:.2}"));
+        writeln!(
+            csv,
+            "{:?},{},{},{},{},{},{},{:.3},{},{:.3}",
+            row.kind,
+            csv_field(&row.name),
+            row.budget,
+            row.fights,
+            row.wins,
+            row.losses,
+            row.draws,
+            row.win_rate,
+            time_to_kill,
+            row.cost_efficiency
+        )
+        .expect("Writing to a string can't fail");
+    }
+
+    csv
+}
+
+/// Quotes a field if it would otherwise break the row up
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{army, simulate, to_csv, SimConfig, SimError, SimKind};
+    use crate::game::{
+        card_gen::{pack::UnitPack, UNITS},
+        rules::RulesError,
+    };
+
+    fn unit(name: &str) -> crate::game::entity::Unit<'static> {
+        *UNITS.iter().find(|unit| unit.get_name() == name).unwrap()
+    }
+
+    #[test]
+    fn armies_buy_cards_in_turn_until_the_money_runs_out() {
+        let hand = [unit("Smiley"), unit("Boar")];
+
+        let army = army(&hand, 700);
+        let names: Vec<&str> = army.iter().map(|unit| unit.get_name()).collect();
+
+        assert_eq!(names, ["Smiley", "Boar", "Smiley", "Smiley", "Smiley"]);
+        assert!(super::army(&[unit("Golem")], 100).is_empty());
+    }
+
+    #[test]
+    fn every_fight_is_counted_from_both_sides() {
+        let pack =
+            UnitPack::from_units(vec![unit("Smiley"), unit("Boar"), unit("EXPLOSIVE")]).unwrap();
+        let config = SimConfig {
+            budgets: vec![1000],
+            hand_pairings: 4,
+            hand_size: 2,
+            time_limit: Duration::from_secs(30),
+            ..SimConfig::default()
+        };
+
+        let rows = simulate(&pack, &config).unwrap();
+        let units: Vec<_> = rows
+            .iter()
+            .filter(|row| row.kind == SimKind::Unit)
+            .collect();
+        let hand_fights: usize = rows
+            .iter()
+            .filter(|row| row.kind == SimKind::Hand)
+            .map(|row| row.fights)
+            .sum();
+
+        assert_eq!(units.len(), 3);
+        assert!(units.iter().all(|row| row.fights == 2));
+        assert!(rows
+            .iter()
+            .all(|row| row.wins + row.losses + row.draws == row.fights));
+        assert_eq!(hand_fights, 8);
+        assert_eq!(to_csv(&rows).lines().count(), rows.len() + 1);
+    }
+
+    #[test]
+    fn hands_need_a_strategy_to_play_them() {
+        let pack = UnitPack::from_units(vec![unit("Smiley"), unit("Boar")]).unwrap();
+        let config = SimConfig {
+            strategy: "Nobody".to_string(),
+            ..SimConfig::default()
+        };
+
+        assert_eq!(
+            simulate(&pack, &config),
+            Err(SimError::NoSuchStrategy("Nobody".to_string()))
+        );
+    }
+
+    #[test]
+    fn hand_battles_need_valid_rules() {
+        let pack = UnitPack::from_units(vec![unit("Smiley"), unit("Boar")]).unwrap();
+        let config = SimConfig {
+            time_limit: Duration::from_millis(500),
+            ..SimConfig::default()
+        };
+
+        assert_eq!(
+            simulate(&pack, &config),
+            Err(SimError::InvalidRules(RulesError::NoTime))
+        );
+    }
+}
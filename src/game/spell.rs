@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::entity::Rarity;
+
+/// Stable index of a spell in the `SPELLS` catalog, used to refer to spells over the wire
+pub type SpellId = usize;
+
+/// A card that does something to the battle when cast instead of walking down the lane
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct Spell<'a> {
+    pub(crate) id: SpellId,
+    pub(crate) name: &'a str,
+    pub(crate) emoji: char,
+
+    pub(crate) cost: usize,
+    /// Milliseconds before the spell can be cast again
+    pub(crate) cooldown: u64,
+    pub(crate) rarity: Rarity,
+
+    pub(crate) effect: SpellEffect,
+}
+
+impl<'a> Spell<'a> {
+    pub fn get_id(&self) -> SpellId {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+
+    pub fn effect(&self) -> SpellEffect {
+        self.effect
+    }
+}
+
+/// What a spell does once it's cast
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum SpellEffect {
+    /// Stops every enemy unit on the caster's lane from moving or attacking for a while
+    Freeze { millis: u64 },
+    /// Heals the caster's tower, up to its max health
+    Heal { amount: usize },
+    /// Damages every enemy unit within `radius` of where it lands on the caster's lane. The
+    /// radius is a fraction of the lane, like the landing spot
+    Meteor { damage: usize, radius: f32 },
+}
@@ -0,0 +1,301 @@
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use super::{card_gen::pack::UnitPack, engine::TICK, entity::Unit};
+
+pub use stages::STAGES;
+
+// Generated by build.rs, which doesn't bother matching rustfmt's layout for short lists
+#[rustfmt::skip]
+mod stages;
+
+/// A boss has this many times the health of the unit it's based on
+const BOSS_HEALTH_SCALE: usize = 5;
+/// A boss has this many times the power of the unit it's based on
+const BOSS_POWER_SCALE: usize = 2;
+const BOSS_SIZE_SCALE: f32 = 1.5;
+
+/// How a player beats a stage
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Victory {
+    /// Last until every wave has been sent and defeated
+    Survive,
+    /// Destroy the enemy base, waves stop coming once they've all been sent
+    DestroyBase,
+}
+
+/// A scripted PvE battle where the enemy tower sends out waves of units instead of a player
+#[derive(Debug, PartialEq)]
+pub struct Stage {
+    pub name: &'static str,
+    pub victory: Victory,
+    pub tower_health: usize,
+    pub waves: &'static [Wave],
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Wave {
+    /// How long after the last wave finished spawning before this one starts
+    pub delay: Duration,
+    /// The first unit spawned in a boss wave is a boss, the rest are its escorts
+    pub boss: bool,
+    pub spawns: &'static [Spawn],
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Spawn {
+    /// Name of the unit, looked up in the battle's unit pack when it spawns
+    pub unit: &'static str,
+    pub count: usize,
+    /// Time between each of these units spawning
+    pub interval: Duration,
+}
+
+/// A stage as shown to a player picking which one to play
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StageSummary {
+    pub index: usize,
+    pub name: &'static str,
+    pub victory: Victory,
+    pub waves: usize,
+    pub unlocked: bool,
+    pub cleared: bool,
+}
+
+/// A stage summary as sent over the wire, its name is looked up from the stage's index when
+/// read back
+#[derive(Deserialize)]
+struct SentStageSummary {
+    index: usize,
+    victory: Victory,
+    waves: usize,
+    unlocked: bool,
+    cleared: bool,
+}
+
+impl<'de> Deserialize<'de> for StageSummary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let summary = SentStageSummary::deserialize(deserializer)?;
+        let stage = STAGES
+            .get(summary.index)
+            .ok_or_else(|| de::Error::custom(format!("There is no stage {}", summary.index)))?;
+
+        Ok(Self {
+            index: summary.index,
+            name: stage.name,
+            victory: summary.victory,
+            waves: summary.waves,
+            unlocked: summary.unlocked,
+            cleared: summary.cleared,
+        })
+    }
+}
+
+fn ticks(duration: Duration) -> u64 {
+    (duration.as_millis() / TICK.as_millis()) as u64
+}
+
+impl Wave {
+    /// Every unit in the wave, in the order they're sent, with how many ticks after the wave
+    /// starts each arrives and whether it's the boss
+    fn schedule(&self) -> Vec<(u64, &'static str, bool)> {
+        let mut at = 0;
+        let mut schedule = vec![];
+
+        for spawn in self.spawns {
+            for _ in 0..spawn.count {
+                let boss = self.boss && schedule.is_empty();
+                schedule.push((at, spawn.unit, boss));
+                at += ticks(spawn.interval);
+            }
+        }
+
+        schedule
+    }
+}
+
+/// Makes a much tougher version of a unit to lead a boss wave
+pub fn boss(unit: Unit<'static>) -> Unit<'static> {
+    Unit {
+        health: unit.health * BOSS_HEALTH_SCALE,
+        power: unit.power * BOSS_POWER_SCALE,
+        size: unit.size * BOSS_SIZE_SCALE,
+        ..unit
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StageEvent {
+    /// `wave` counts from 1
+    WaveStarted {
+        wave: usize,
+        boss: bool,
+    },
+    Spawn(Unit<'static>),
+}
+
+/// Where a battle is up to in its stage's script
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageRun {
+    index: usize,
+    stage: &'static Stage,
+    /// How many waves have started
+    wave: usize,
+    next_wave_at: u64,
+    /// Units from the current wave still to spawn, with the tick they spawn on
+    pending: VecDeque<(u64, &'static str, bool)>,
+}
+
+impl StageRun {
+    pub fn new(index: usize) -> Option<Self> {
+        let stage = STAGES.get(index)?;
+
+        Some(Self {
+            index,
+            stage,
+            wave: 0,
+            next_wave_at: ticks(stage.waves[0].delay),
+            pending: VecDeque::new(),
+        })
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn stage(&self) -> &'static Stage {
+        self.stage
+    }
+
+    /// True once every unit in every wave has been sent out
+    pub fn is_done(&self) -> bool {
+        self.wave == self.stage.waves.len() && self.pending.is_empty()
+    }
+
+    /// Starts waves and spawns units that are due on this tick. Units missing from the pack
+    /// are skipped so a reloaded pack can't break a stage
+    pub fn tick(&mut self, tick: u64, pack: &UnitPack) -> Vec<StageEvent> {
+        let mut events = vec![];
+
+        if self.pending.is_empty()
+            && self.wave < self.stage.waves.len()
+            && tick >= self.next_wave_at
+        {
+            let wave = &self.stage.waves[self.wave];
+            self.wave += 1;
+            self.pending = wave
+                .schedule()
+                .into_iter()
+                .map(|(at, unit, boss)| (tick + at, unit, boss))
+                .collect();
+
+            events.push(StageEvent::WaveStarted {
+                wave: self.wave,
+                boss: wave.boss,
+            });
+        }
+
+        while let Some(&(at, name, is_boss)) = self.pending.front() {
+            if at > tick {
+                break;
+            }
+            self.pending.pop_front();
+
+            if let Some(unit) = pack.id_of(name).and_then(|id| pack.get(id)) {
+                events.push(StageEvent::Spawn(if is_boss { boss(*unit) } else { *unit }));
+            }
+
+            if self.pending.is_empty() {
+                if let Some(next) = self.stage.waves.get(self.wave) {
+                    self.next_wave_at = tick + ticks(next.delay);
+                }
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{boss, StageEvent, StageRun, STAGES};
+    use crate::game::card_gen::pack::UnitPack;
+
+    #[test]
+    fn stages_only_spawn_units_in_the_builtin_pack() {
+        let pack = UnitPack::builtin();
+
+        for stage in STAGES {
+            for wave in stage.waves {
+                for spawn in wave.spawns {
+                    assert!(pack.id_of(spawn.unit).is_some(), "{}", spawn.unit);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stage_runs_every_wave_in_order() {
+        let pack = UnitPack::builtin();
+        let mut run = StageRun::new(0).unwrap();
+        let stage = run.stage();
+
+        let mut waves = vec![];
+        let mut spawned = 0;
+        let mut tick = 0;
+        while !run.is_done() {
+            tick += 1;
+            for event in run.tick(tick, &pack) {
+                match event {
+                    StageEvent::WaveStarted { wave, boss } => waves.push((wave, boss)),
+                    StageEvent::Spawn(_) => spawned += 1,
+                }
+            }
+        }
+
+        let expected_waves: Vec<_> = stage
+            .waves
+            .iter()
+            .enumerate()
+            .map(|(i, wave)| (i + 1, wave.boss))
+            .collect();
+        let expected_spawns: usize = stage
+            .waves
+            .iter()
+            .flat_map(|wave| wave.spawns)
+            .map(|spawn| spawn.count)
+            .sum();
+
+        assert_eq!(waves, expected_waves);
+        assert_eq!(spawned, expected_spawns);
+    }
+
+    #[test]
+    fn boss_waves_are_led_by_a_boss() {
+        let pack = UnitPack::builtin();
+        let index = STAGES
+            .iter()
+            .position(|stage| stage.waves.iter().any(|wave| wave.boss))
+            .expect("A stage with a boss wave");
+        let mut run = StageRun::new(index).unwrap();
+
+        let mut tick = 0;
+        let mut boss_wave = false;
+        loop {
+            tick += 1;
+            for event in run.tick(tick, &pack) {
+                match event {
+                    StageEvent::WaveStarted { boss, .. } => boss_wave = boss,
+                    StageEvent::Spawn(unit) if boss_wave => {
+                        let base = pack.get(unit.get_id()).unwrap();
+                        assert_eq!(unit, boss(*base));
+                        assert!(unit.health > base.health);
+                        return;
+                    }
+                    StageEvent::Spawn(_) => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+/// THIS FILE IS AUTOGENERATED BY BUILD.RS
+/// TO ADD NEW STAGES, ADD A NEW FILE TO THE STAGES DIRECTORY
+use std::time::Duration;
+
+use crate::game::stage::{Spawn, Stage, Victory, Wave};
+
+pub static STAGES: &[Stage] = &[
+    Stage {
+        name: "Meadow",
+        victory: Victory::Survive,
+        tower_health: 5000,
+        waves: &[
+            Wave {
+                delay: Duration::from_millis(3000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Smiley",
+                        count: 3,
+                        interval: Duration::from_millis(2000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(8000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Hamster",
+                        count: 4,
+                        interval: Duration::from_millis(1500),
+                    },
+                    Spawn {
+                        unit: "Skeleton",
+                        count: 2,
+                        interval: Duration::from_millis(2000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(10000),
+                boss: true,
+                spawns: &[
+                    Spawn {
+                        unit: "Boar",
+                        count: 1,
+                        interval: Duration::from_millis(1000),
+                    },
+                ],
+            },
+        ],
+    },
+    Stage {
+        name: "Graveyard",
+        victory: Victory::Survive,
+        tower_health: 7500,
+        waves: &[
+            Wave {
+                delay: Duration::from_millis(3000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Skeleton",
+                        count: 4,
+                        interval: Duration::from_millis(1500),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(8000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Spooked",
+                        count: 3,
+                        interval: Duration::from_millis(1500),
+                    },
+                    Spawn {
+                        unit: "Melted",
+                        count: 2,
+                        interval: Duration::from_millis(3000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(8000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Skeleton",
+                        count: 6,
+                        interval: Duration::from_millis(1000),
+                    },
+                    Spawn {
+                        unit: "Snowman",
+                        count: 2,
+                        interval: Duration::from_millis(3000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(12000),
+                boss: true,
+                spawns: &[
+                    Spawn {
+                        unit: "Demon",
+                        count: 1,
+                        interval: Duration::from_millis(1000),
+                    },
+                    Spawn {
+                        unit: "Spooked",
+                        count: 4,
+                        interval: Duration::from_millis(1000),
+                    },
+                ],
+            },
+        ],
+    },
+    Stage {
+        name: "Fortress",
+        victory: Victory::DestroyBase,
+        tower_health: 12000,
+        waves: &[
+            Wave {
+                delay: Duration::from_millis(5000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Robot",
+                        count: 3,
+                        interval: Duration::from_millis(2000),
+                    },
+                    Spawn {
+                        unit: "Ninja",
+                        count: 2,
+                        interval: Duration::from_millis(2000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(10000),
+                boss: false,
+                spawns: &[
+                    Spawn {
+                        unit: "Hippo",
+                        count: 2,
+                        interval: Duration::from_millis(4000),
+                    },
+                    Spawn {
+                        unit: "Alien",
+                        count: 2,
+                        interval: Duration::from_millis(2000),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(15000),
+                boss: true,
+                spawns: &[
+                    Spawn {
+                        unit: "Golem",
+                        count: 1,
+                        interval: Duration::from_millis(1000),
+                    },
+                    Spawn {
+                        unit: "Cowboy",
+                        count: 4,
+                        interval: Duration::from_millis(1500),
+                    },
+                ],
+            },
+            Wave {
+                delay: Duration::from_millis(20000),
+                boss: true,
+                spawns: &[
+                    Spawn {
+                        unit: "T-Rex",
+                        count: 1,
+                        interval: Duration::from_millis(1000),
+                    },
+                    Spawn {
+                        unit: "Super Hero",
+                        count: 1,
+                        interval: Duration::from_millis(1000),
+                    },
+                ],
+            },
+        ],
+    },
+];
@@ -0,0 +1,430 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::Serialize;
+
+use super::{
+    bot::{Bot, Difficulty, Observation},
+    entity::{Unit, UnitId},
+    spell::{SpellEffect, SpellId},
+};
+
+/// The strategy bots play with when nobody picks one
+pub const DEFAULT_STRATEGY: &str = "Normal";
+/// How often the built in strategies look at the board, as often as a normal bot
+const THINK_TICKS: u64 = 80;
+
+/// Something a strategy wants to do in a battle
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Action {
+    /// Play a unit card from the hand down a lane
+    Play { unit: UnitId, lane: usize },
+    /// Cast a spell card from the hand at a spot along the lane, as a fraction of the way from
+    /// the player's tower to the one they're attacking
+    Cast { spell: SpellId, spot: f32 },
+}
+
+/// Decides what a computer controlled player does, whether it's a bot on the server or an
+/// entrant in an offline tournament
+pub trait Strategy: fmt::Debug + Send + Sync {
+    /// Called every tick of the battle with what the player can see, returns what to do if
+    /// anything. Actions that turn out not to be allowed, like playing a card that's still on
+    /// cooldown, are skipped
+    fn decide(&mut self, observation: &Observation, rng: &mut dyn RngCore) -> Vec<Action>;
+}
+
+impl Strategy for Bot {
+    fn decide(&mut self, observation: &Observation, mut rng: &mut dyn RngCore) -> Vec<Action> {
+        let play = self.act(observation, &mut rng).map(|unit| Action::Play {
+            unit,
+            lane: observation.lane_to_defend(),
+        });
+        // Spells get looked at as often as the bot looks at its units
+        let cast = match self.just_thought() {
+            true => spell_to_cast(observation),
+            false => None,
+        };
+
+        cast.into_iter().chain(play).collect()
+    }
+}
+
+/// A spell worth casting right now, if there is one. Meteors land on the enemy closest to the
+/// tower and freezes go out while anything's closing in on it, heals once the tower has less
+/// health left than an enemy's
+fn spell_to_cast(observation: &Observation) -> Option<Action> {
+    let nearest = observation.nearest_threat();
+    let health = observation.tower_health.get(observation.side.index());
+    let behind = observation
+        .tower_health
+        .iter()
+        .enumerate()
+        .any(|(side, enemy)| side != observation.side.index() && Some(enemy) > health);
+
+    observation.castable().into_iter().find_map(|spell| {
+        let spot = match spell.effect() {
+            SpellEffect::Meteor { .. } => 1.0 - nearest?.progress(),
+            SpellEffect::Freeze { .. } => nearest.map(|_| 0.5)?,
+            SpellEffect::Heal { .. } => behind.then_some(0.0)?,
+        };
+
+        Some(Action::Cast {
+            spell: spell.get_id(),
+            spot,
+        })
+    })
+}
+
+/// Counts down to the next time a strategy looks at the board, so it doesn't play every
+/// card the moment it can
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Pace {
+    ticks_until_think: u64,
+}
+
+impl Default for Pace {
+    fn default() -> Self {
+        Self {
+            ticks_until_think: THINK_TICKS,
+        }
+    }
+}
+
+impl Pace {
+    fn think(&mut self) -> bool {
+        self.ticks_until_think = self.ticks_until_think.saturating_sub(1);
+        if self.ticks_until_think > 0 {
+            return false;
+        }
+
+        self.ticks_until_think = THINK_TICKS;
+        true
+    }
+}
+
+/// Plays any card it can afford down any lane, or casts any spell it can afford anywhere
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RandomStrategy {
+    pace: Pace,
+}
+
+impl Strategy for RandomStrategy {
+    fn decide(&mut self, observation: &Observation, rng: &mut dyn RngCore) -> Vec<Action> {
+        if !self.pace.think() {
+            return vec![];
+        }
+
+        let lanes = observation.field.lanes();
+        let mut options: Vec<Action> = observation
+            .playable()
+            .into_iter()
+            .map(|unit| Action::Play {
+                unit: unit.get_id(),
+                lane: rng.gen_range(0..lanes),
+            })
+            .collect();
+        options.extend(
+            observation
+                .castable()
+                .into_iter()
+                .map(|spell| Action::Cast {
+                    spell: spell.get_id(),
+                    spot: rng.gen_range(0.0..=1.0),
+                }),
+        );
+
+        options.choose(rng).copied().into_iter().collect()
+    }
+}
+
+/// Never saves up, spends its money on the most expensive card it can play right away
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GreedyStrategy {
+    pace: Pace,
+}
+
+impl Strategy for GreedyStrategy {
+    fn decide(&mut self, observation: &Observation, _rng: &mut dyn RngCore) -> Vec<Action> {
+        if !self.pace.think() {
+            return vec![];
+        }
+
+        let play = priciest(observation).map(|unit| Action::Play {
+            unit: unit.get_id(),
+            lane: observation.lane_to_defend(),
+        });
+
+        spell_to_cast(observation).into_iter().chain(play).collect()
+    }
+}
+
+/// The most expensive card that can be played right now
+fn priciest<'a>(observation: &Observation<'a, '_>) -> Option<Unit<'a>> {
+    observation
+        .playable()
+        .into_iter()
+        .max_by_key(|unit| unit.cost)
+}
+
+/// Waits to see what the enemy sends, then answers with the card that does the most damage
+/// to it for what it costs, bonus damage against tags included. Plays greedily once it's got
+/// nothing to answer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CounterStrategy {
+    pace: Pace,
+}
+
+impl Strategy for CounterStrategy {
+    fn decide(&mut self, observation: &Observation, _rng: &mut dyn RngCore) -> Vec<Action> {
+        if !self.pace.think() {
+            return vec![];
+        }
+
+        let lane = observation.lane_to_defend();
+        let enemies: Vec<Unit> = observation
+            .field
+            .units()
+            .iter()
+            .filter(|unit| unit.target == observation.side && unit.lane == lane)
+            .map(|unit| unit.unit)
+            .collect();
+
+        let choice = if enemies.is_empty() {
+            priciest(observation)
+        } else {
+            let score = |unit: &Unit| {
+                let damage: usize = enemies.iter().map(|enemy| unit.damage_against(enemy)).sum();
+                (damage * unit.health) as f32 / unit.cost.max(1) as f32
+            };
+            observation
+                .playable()
+                .into_iter()
+                .max_by(|a, b| score(a).total_cmp(&score(b)))
+        };
+
+        let play = choice.map(|unit| Action::Play {
+            unit: unit.get_id(),
+            lane,
+        });
+
+        spell_to_cast(observation).into_iter().chain(play).collect()
+    }
+}
+
+type Factory = Arc<dyn Fn() -> Box<dyn Strategy> + Send + Sync>;
+
+/// Strategies by name, so players and tools can pick one without knowing its type. Starts
+/// out with the built in ones, more can be added with `register`
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl StrategyRegistry {
+    /// A registry without any strategies, not even the built in ones
+    pub fn empty() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a strategy, or replaces the one already registered under the name.
+    /// `factory` is called for a fresh strategy every battle
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn() -> Box<dyn Strategy> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into(), Arc::new(factory));
+    }
+
+    pub fn create(&self, name: &str) -> Option<Box<dyn Strategy>> {
+        self.factories.get(name).map(|factory| factory())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            registry.register(format!("{difficulty:?}"), move || {
+                Box::new(Bot::new(difficulty))
+            });
+        }
+        registry.register("Random", || Box::<RandomStrategy>::default());
+        registry.register("Greedy", || Box::<GreedyStrategy>::default());
+        registry.register("Counter", || Box::<CounterStrategy>::default());
+
+        registry
+    }
+}
+
+impl fmt::Debug for StrategyRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{Action, Strategy, StrategyRegistry, THINK_TICKS};
+    use crate::game::{
+        bot::Observation,
+        card_gen::{SPELLS, UNITS},
+        engine::{Field, Side},
+        entity::Unit,
+        spell::{Spell, SpellEffect},
+    };
+
+    fn unit(name: &str) -> Unit<'static> {
+        *UNITS.iter().find(|unit| unit.get_name() == name).unwrap()
+    }
+
+    fn spell(effect: fn(&SpellEffect) -> bool) -> Spell<'static> {
+        *SPELLS.iter().find(|spell| effect(&spell.effect())).unwrap()
+    }
+
+    /// Runs the strategy until it next does something
+    fn decide(strategy: &mut dyn Strategy, observation: &Observation) -> Vec<Action> {
+        let mut rng = StdRng::seed_from_u64(32);
+
+        (0..THINK_TICKS)
+            .map(|_| strategy.decide(observation, &mut rng))
+            .find(|actions| !actions.is_empty())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn greedy_spends_on_the_priciest_card_it_can_afford() {
+        let hand = [unit("Smiley"), unit("Moon")];
+        let field = Field::default();
+        let mut greedy = StrategyRegistry::default().create("Greedy").unwrap();
+        let observation = |money| Observation {
+            hand: &hand,
+            ready: &[true, true],
+            money,
+            income_ticks: 3,
+            field: &field,
+            side: Side::B,
+            spells: &[],
+            spells_ready: &[],
+            tower_health: &[1000, 1000],
+        };
+
+        for (money, choice) in [
+            (unit("Moon").cost - 1, "Smiley"),
+            (unit("Moon").cost, "Moon"),
+        ] {
+            assert_eq!(
+                decide(greedy.as_mut(), &observation(money)),
+                [Action::Play {
+                    unit: unit(choice).get_id(),
+                    lane: 0
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn counter_answers_with_bonus_damage_against_what_is_coming() {
+        let hand = [unit("Cowboy"), unit("Nerd")];
+
+        for (enemy, answer) in [("Skeleton", "Cowboy"), ("Hippo", "Nerd")] {
+            let mut field = Field::default();
+            field.spawn(unit(enemy), Side::A);
+            let mut counter = StrategyRegistry::default().create("Counter").unwrap();
+            let observation = Observation {
+                hand: &hand,
+                ready: &[true, true],
+                money: 5000,
+                income_ticks: 3,
+                field: &field,
+                side: Side::B,
+                spells: &[],
+                spells_ready: &[],
+                tower_health: &[1000, 1000],
+            };
+
+            assert_eq!(
+                decide(counter.as_mut(), &observation),
+                [Action::Play {
+                    unit: unit(answer).get_id(),
+                    lane: 0
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn strategies_cast_spells_when_they_help() {
+        let meteor = spell(|effect| matches!(effect, SpellEffect::Meteor { .. }));
+        let heal = spell(|effect| matches!(effect, SpellEffect::Heal { .. }));
+        let mut incoming = Field::default();
+        incoming.spawn(unit("Boar"), Side::A);
+        for _ in 0..5000 {
+            incoming.tick();
+        }
+        let nearest = 1.0 - incoming.units()[0].progress();
+        let empty = Field::default();
+        let (meteors, heals) = ([meteor], [heal]);
+
+        for name in ["Normal", "Greedy", "Counter"] {
+            let registry = StrategyRegistry::default();
+            let observation = |field, spells, tower_health| Observation {
+                hand: &[],
+                ready: &[],
+                spells,
+                spells_ready: &[true],
+                money: 5000,
+                income_ticks: 3,
+                field,
+                side: Side::B,
+                tower_health,
+            };
+
+            assert_eq!(
+                decide(
+                    registry.create(name).unwrap().as_mut(),
+                    &observation(&incoming, &meteors, &[1000, 1000])
+                ),
+                [Action::Cast {
+                    spell: meteor.get_id(),
+                    spot: nearest
+                }]
+            );
+            assert_eq!(
+                decide(
+                    registry.create(name).unwrap().as_mut(),
+                    &observation(&empty, &heals, &[1000, 500])
+                ),
+                [Action::Cast {
+                    spell: heal.get_id(),
+                    spot: 0.0
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn custom_strategies_can_be_registered() {
+        #[derive(Debug)]
+        struct Idle;
+        impl Strategy for Idle {
+            fn decide(&mut self, _: &Observation, _: &mut dyn rand::RngCore) -> Vec<Action> {
+                vec![]
+            }
+        }
+
+        let mut registry = StrategyRegistry::default();
+        registry.register("Idle", || Box::new(Idle));
+
+        assert!(registry.create("Idle").is_some());
+        assert!(registry.create("Nobody").is_none());
+        assert_eq!(registry.names().count(), 7);
+    }
+}
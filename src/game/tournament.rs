@@ -0,0 +1,441 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::{
+    battle::{Battle, BattleEvent},
+    bot::Observation,
+    card::{Card, CardId},
+    card_gen::{pack::UnitPack, SPELLS},
+    engine::Side,
+    entity::{Drawable, Unit},
+    hand::{Hand, HandMode},
+    rules::{MatchRules, RulesError},
+    spell::Spell,
+    strategy::{Action, Strategy, StrategyRegistry},
+};
+
+/// Rating every strategy starts the tournament with
+pub const STARTING_ELO: f32 = 1500.0;
+/// Most rating a strategy can gain or lose in a single game
+const ELO_K: f32 = 32.0;
+/// Games still going after this many ticks are draws, for rules without a time limit
+const MAX_GAME_TICKS: u64 = 60_000;
+
+/// A computer player taking part in a tournament
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entrant {
+    pub name: String,
+    /// Name the strategy is registered under
+    pub strategy: String,
+}
+
+impl Entrant {
+    /// An entrant named after its strategy
+    pub fn new(strategy: &str) -> Self {
+        Self {
+            name: strategy.to_string(),
+            strategy: strategy.to_string(),
+        }
+    }
+}
+
+/// A deck entrants can be handed for a game
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentDeck {
+    pub name: String,
+    pub units: Vec<Unit<'static>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentConfig {
+    pub games: usize,
+    /// Seeds every deck choice, hand and random decision, so a tournament can be replayed
+    pub seed: u64,
+    pub rules: MatchRules,
+    pub hand_mode: HandMode,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            seed: 0,
+            rules: MatchRules::timed(),
+            hand_mode: HandMode::Cycle,
+        }
+    }
+}
+
+/// How a strategy or deck did over a tournament
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Standing {
+    pub name: String,
+    /// Only tracked for strategies, decks are scored by win rate
+    pub elo: Option<f32>,
+    pub games: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Standing {
+    /// Draws count as half a win
+    pub fn win_rate(&self) -> f32 {
+        (self.wins as f32 + self.draws as f32 / 2.0) / self.games.max(1) as f32
+    }
+
+    fn record(&mut self, score: f32) {
+        self.games += 1;
+        match score {
+            1.0 => self.wins += 1,
+            0.0 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TournamentReport {
+    /// Best rated first
+    pub strategies: Vec<Standing>,
+    /// Best win rate first
+    pub decks: Vec<Standing>,
+}
+
+/// Plays `config.games` battles between the entrants, pairing every entrant with every other
+/// in turn on both sides and handing each a random deck
+pub fn run_tournament(
+    pack: Arc<UnitPack>,
+    strategies: &StrategyRegistry,
+    entrants: &[Entrant],
+    decks: &[TournamentDeck],
+    config: &TournamentConfig,
+) -> TournamentResult<TournamentReport> {
+    config.rules.validate()?;
+    if entrants.len() < 2 {
+        return Err(TournamentError::NotEnoughEntrants);
+    }
+    if let Some(entrant) = entrants
+        .iter()
+        .find(|entrant| strategies.create(&entrant.strategy).is_none())
+    {
+        return Err(TournamentError::NoSuchStrategy(entrant.strategy.clone()));
+    }
+    if decks.is_empty() {
+        return Err(TournamentError::NoDecks);
+    }
+    if let Some(deck) = decks
+        .iter()
+        .find(|deck| deck.units.len() < config.rules.hand_size)
+    {
+        return Err(TournamentError::DeckTooSmall(deck.name.clone()));
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let pairings: Vec<(usize, usize)> = (0..entrants.len())
+        .flat_map(|a| (0..entrants.len()).map(move |b| (a, b)))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    let mut standings: Vec<Standing> = entrants
+        .iter()
+        .map(|entrant| Standing {
+            name: entrant.name.clone(),
+            elo: Some(STARTING_ELO),
+            ..Default::default()
+        })
+        .collect();
+    let mut deck_standings: Vec<Standing> = decks
+        .iter()
+        .map(|deck| Standing {
+            name: deck.name.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    for game in 0..config.games {
+        let (a, b) = pairings[game % pairings.len()];
+        let deck_a = rng.gen_range(0..decks.len());
+        let deck_b = rng.gen_range(0..decks.len());
+
+        let players = [&entrants[a], &entrants[b]].map(|entrant| {
+            strategies
+                .create(&entrant.strategy)
+                .expect("Entrants are checked to have a strategy")
+        });
+        // Spells are drawn alongside the deck, like they are for players without a saved one
+        let cards = [deck_a, deck_b].map(|deck| {
+            decks[deck]
+                .units
+                .iter()
+                .copied()
+                .map(Card::from)
+                .chain(SPELLS.iter().copied().map(Card::from))
+                .collect()
+        });
+        let result = play_game(
+            pack.clone(),
+            players,
+            cards,
+            config.rules,
+            config.hand_mode,
+            &mut rng,
+        );
+        let score_a = match result.winner {
+            Some(Side::A) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+
+        let (elo_a, elo_b) = (standings[a].elo.unwrap(), standings[b].elo.unwrap());
+        let expected_a = 1.0 / (1.0 + 10f32.powf((elo_b - elo_a) / 400.0));
+        let change = ELO_K * (score_a - expected_a);
+        standings[a].elo = Some(elo_a + change);
+        standings[b].elo = Some(elo_b - change);
+
+        standings[a].record(score_a);
+        standings[b].record(1.0 - score_a);
+        deck_standings[deck_a].record(score_a);
+        deck_standings[deck_b].record(1.0 - score_a);
+    }
+
+    standings.sort_by(|a, b| b.elo.unwrap().total_cmp(&a.elo.unwrap()));
+    deck_standings.sort_by(|a, b| b.win_rate().total_cmp(&a.win_rate()));
+
+    Ok(TournamentReport {
+        strategies: standings,
+        decks: deck_standings,
+    })
+}
+
+/// How a single game between two strategies went, indexed by side where there's one per side
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameResult {
+    /// The side that won, `None` for a draw
+    pub winner: Option<Side>,
+    pub ticks: u64,
+    /// Cost of every card each side played
+    pub spent: [usize; 2],
+    /// Cost of the enemy units each side killed
+    pub killed: [usize; 2],
+}
+
+/// Plays one battle between two strategies on the same engine live games use, dealing each
+/// its hand from one of the decks. Strategy 0 plays side A
+pub fn play_game<R: Rng>(
+    pack: Arc<UnitPack>,
+    mut strategies: [Box<dyn Strategy>; 2],
+    decks: [Vec<Card<'static>>; 2],
+    rules: MatchRules,
+    hand_mode: HandMode,
+    rng: &mut R,
+) -> GameResult {
+    // Players only need ids that differ, taking them from the rng keeps games repeatable
+    let players = [Uuid::from_u128(rng.gen()), Uuid::from_u128(rng.gen())];
+    let mut battle =
+        Battle::start_battle(vec![players[0]], vec![players[1]], pack, hand_mode, rules);
+    battle.begin_fighting();
+
+    let mut hands: Vec<Hand> = decks
+        .into_iter()
+        .map(|cards| {
+            Hand::deal_with(cards, rules.hand_size, hand_mode, &battle.draw_rules, rng)
+                .expect("Decks are checked to fill a hand")
+        })
+        .collect();
+    let mut result = GameResult {
+        winner: None,
+        ticks: 0,
+        spent: [0, 0],
+        killed: [0, 0],
+    };
+
+    // Timed games always play on to their tiebreak, however long they're given
+    for _ in 0..rules.tiebreak_tick().unwrap_or(MAX_GAME_TICKS) {
+        let tower_health: Vec<usize> = battle.teams.iter().map(|team| team.tower.health).collect();
+        for (i, player) in players.iter().enumerate() {
+            let hand: Vec<Unit> = hands[i].cards().iter().filter_map(Card::unit).collect();
+            let spells: Vec<Spell> = hands[i].cards().iter().filter_map(Card::spell).collect();
+            let economy = battle.economy(*player);
+            let ready: Vec<bool> = hand
+                .iter()
+                .map(|unit| economy.is_ready(&Card::Unit(*unit), battle.ticks))
+                .collect();
+            let spells_ready: Vec<bool> = spells
+                .iter()
+                .map(|spell| economy.is_ready(&Card::Spell(*spell), battle.ticks))
+                .collect();
+            let observation = Observation {
+                hand: &hand,
+                ready: &ready,
+                spells: &spells,
+                spells_ready: &spells_ready,
+                money: economy.money,
+                income_ticks: battle.rules.income_ticks,
+                field: &battle.field,
+                side: battle.side_of(*player),
+                tower_health: &tower_health,
+            };
+
+            let actions = strategies[i].decide(&observation, rng);
+            for action in actions {
+                let (card, played) = match action {
+                    Action::Play { unit, lane } => {
+                        let card = hands[i]
+                            .card(CardId::Unit(unit))
+                            .and_then(|card| card.unit());
+                        let played =
+                            card.is_some_and(|card| battle.play(*player, card, lane).is_ok());
+                        (CardId::Unit(unit), played)
+                    }
+                    Action::Cast { spell, spot } => {
+                        let card = hands[i]
+                            .card(CardId::Spell(spell))
+                            .and_then(|card| card.spell());
+                        let played =
+                            card.is_some_and(|card| battle.cast(*player, card, spot).is_ok());
+                        (CardId::Spell(spell), played)
+                    }
+                };
+                if played {
+                    result.spent[i] += hands[i].play(card).map_or(0, |card| card.cost());
+                }
+            }
+        }
+
+        let events = battle.tick();
+        result.ticks = battle.ticks;
+        for event in events {
+            match event {
+                BattleEvent::UnitKilled { killer, cost, .. } => {
+                    result.killed[killer.index()] += cost;
+                }
+                BattleEvent::Finished { winner } => {
+                    result.winner = Some(winner);
+                    return result;
+                }
+                BattleEvent::TimeUp { winner } => {
+                    result.winner = winner;
+                    return result;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
+/// Makes decks of random units for entrants to play with, named by their units' emojis
+pub fn random_decks<R: Rng>(
+    pack: &UnitPack,
+    count: usize,
+    size: usize,
+    rng: &mut R,
+) -> Vec<TournamentDeck> {
+    let mut decks: HashMap<String, TournamentDeck> = HashMap::new();
+    let size = size.min(pack.len());
+
+    // Give up on finding more unique decks eventually, small packs don't have many
+    for _ in 0..count * 10 {
+        if decks.len() == count {
+            break;
+        }
+
+        let mut units: Vec<Unit<'static>> =
+            pack.units().choose_multiple(rng, size).copied().collect();
+        units.sort_by_key(|unit| unit.get_id());
+        let name: String = units.iter().map(|unit| unit.emoji).collect();
+        decks
+            .entry(name.clone())
+            .or_insert(TournamentDeck { name, units });
+    }
+
+    let mut decks: Vec<TournamentDeck> = decks.into_values().collect();
+    decks.sort_by(|a, b| a.name.cmp(&b.name));
+    decks
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TournamentError {
+    #[error("A tournament needs at least two entrants")]
+    NotEnoughEntrants,
+    #[error("There is no strategy called {0}")]
+    NoSuchStrategy(String),
+    #[error("A tournament needs at least one deck")]
+    NoDecks,
+    #[error("Deck {0} doesn't have enough units to fill a hand")]
+    DeckTooSmall(String),
+    #[error("Invalid rules: {0}")]
+    InvalidRules(#[from] RulesError),
+}
+
+pub type TournamentResult<T> = std::result::Result<T, TournamentError>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{random_decks, run_tournament, Entrant, TournamentConfig, TournamentError};
+    use crate::game::{card_gen::pack::UnitPack, rules::MatchRules, strategy::StrategyRegistry};
+
+    fn config(games: usize) -> TournamentConfig {
+        TournamentConfig {
+            games,
+            seed: 7,
+            rules: MatchRules {
+                tower_health: 500,
+                time_limit: Some(20),
+                sudden_death: 10,
+                ..MatchRules::default()
+            },
+            ..TournamentConfig::default()
+        }
+    }
+
+    #[test]
+    fn tournaments_with_the_same_seed_play_out_the_same() {
+        let pack = Arc::new(UnitPack::builtin());
+        let decks = random_decks(&pack, 3, 6, &mut StdRng::seed_from_u64(1));
+        let strategies = StrategyRegistry::default();
+        let entrants = [Entrant::new("Easy"), Entrant::new("Counter")];
+
+        let first = run_tournament(pack.clone(), &strategies, &entrants, &decks, &config(4));
+        let second = run_tournament(pack, &strategies, &entrants, &decks, &config(4));
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        assert_eq!(first, second);
+        assert!(first.strategies.iter().all(|standing| standing.games == 4));
+        let elo: f32 = first.strategies.iter().filter_map(|s| s.elo).sum();
+        assert!((elo - 3000.0).abs() < 0.01);
+        assert_eq!(first.decks.iter().map(|deck| deck.games).sum::<usize>(), 8);
+    }
+
+    #[test]
+    fn tournaments_need_known_opponents_and_decks() {
+        let pack = Arc::new(UnitPack::builtin());
+        let decks = random_decks(&pack, 1, 6, &mut StdRng::seed_from_u64(1));
+        let strategies = StrategyRegistry::default();
+        let run = |entrants: &[Entrant], decks| {
+            run_tournament(pack.clone(), &strategies, entrants, decks, &config(1))
+        };
+
+        assert_eq!(
+            run(&[Entrant::new("Easy")], &decks),
+            Err(TournamentError::NotEnoughEntrants)
+        );
+        assert_eq!(
+            run(&[Entrant::new("Easy"), Entrant::new("Nobody")], &decks),
+            Err(TournamentError::NoSuchStrategy("Nobody".to_string()))
+        );
+        assert_eq!(
+            run(&[Entrant::new("Easy"), Entrant::new("Hard")], &[]),
+            Err(TournamentError::NoDecks)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use super::{engine::LANE_LENGTH, rules::MatchRules};
+
+/// Ticks a tower's cannon takes to charge up before it can fire
+pub const CANNON_CHARGE_TICKS: u64 = 2000;
+/// Damage a cannon blast deals to every unit it hits
+pub const CANNON_DAMAGE: usize = 250;
+/// How far down the lane from the tower a cannon blast reaches
+pub const CANNON_RANGE: f32 = LANE_LENGTH * 0.4;
+
+/// Each upgrade can be bought this many times
+pub const MAX_UPGRADE_LEVEL: usize = 3;
+/// Max health a tower gains with each health upgrade, it's healed by as much too
+pub const HEALTH_PER_LEVEL: usize = 2500;
+/// Most money a player can hold before buying any wallet upgrades
+pub const BASE_WALLET: usize = 3000;
+/// Extra money a player can hold with each wallet upgrade
+pub const WALLET_PER_LEVEL: usize = 1500;
+
+/// Something players can spend money on to improve their tower or economy during a battle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Upgrade {
+    /// Raises the team's tower max health. Shared by the whole team
+    MaxHealth,
+    /// Earns the player an extra coin each time income is paid
+    Income,
+    /// Raises how much money the player can hold
+    Wallet,
+}
+
+impl Upgrade {
+    /// What buying the next level costs when the upgrade is at `level`
+    pub fn cost(&self, level: usize) -> usize {
+        let base = match self {
+            Self::MaxHealth => 400,
+            Self::Income => 300,
+            Self::Wallet => 250,
+        };
+
+        base * (level + 1)
+    }
+}
+
+/// A team's tower, which they lose the battle with if it falls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tower {
+    pub health: usize,
+    pub max_health: usize,
+    /// Ticks of charge the cannon has built up, it fires once this reaches
+    /// `CANNON_CHARGE_TICKS`
+    pub cannon_charge: u64,
+    /// How many times the tower's max health has been upgraded
+    pub health_level: usize,
+}
+
+impl Tower {
+    pub fn new(health: usize) -> Self {
+        Self {
+            health,
+            max_health: health,
+            cannon_charge: 0,
+            health_level: 0,
+        }
+    }
+
+    /// Builds up the cannon's charge by one tick
+    pub fn charge_cannon(&mut self) {
+        self.cannon_charge = (self.cannon_charge + 1).min(CANNON_CHARGE_TICKS);
+    }
+
+    pub fn cannon_ready(&self) -> bool {
+        self.cannon_charge >= CANNON_CHARGE_TICKS
+    }
+
+    /// How charged the cannon is, from 0.0 to 1.0
+    pub fn cannon_progress(&self) -> f32 {
+        self.cannon_charge as f32 / CANNON_CHARGE_TICKS as f32
+    }
+
+    /// Raises the tower's max health by a level, healing it by as much
+    pub fn upgrade_health(&mut self) {
+        self.health_level += 1;
+        self.max_health += HEALTH_PER_LEVEL;
+        self.health += HEALTH_PER_LEVEL;
+    }
+}
+
+impl Default for Tower {
+    fn default() -> Self {
+        Self::new(MatchRules::default().tower_health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tower, Upgrade, CANNON_CHARGE_TICKS, HEALTH_PER_LEVEL};
+
+    #[test]
+    fn cannon_charges_up_and_stays_charged() {
+        let mut tower = Tower::new(1000);
+
+        for _ in 0..CANNON_CHARGE_TICKS - 1 {
+            tower.charge_cannon();
+        }
+        assert!(!tower.cannon_ready());
+
+        tower.charge_cannon();
+        tower.charge_cannon();
+        assert!(tower.cannon_ready());
+        assert_eq!(tower.cannon_progress(), 1.0);
+    }
+
+    #[test]
+    fn health_upgrades_heal_and_raise_max_health() {
+        let mut tower = Tower::new(1000);
+        tower.health = 10;
+        tower.upgrade_health();
+
+        assert_eq!(tower.health, 10 + HEALTH_PER_LEVEL);
+        assert_eq!(tower.max_health, 1000 + HEALTH_PER_LEVEL);
+        assert!(Upgrade::Income.cost(1) > Upgrade::Income.cost(0));
+    }
+}
@@ -1,16 +1,28 @@
+use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use hyper::server::conn::http1;
 use hyper_util::rt::TokioIo;
-use td::game::card_gen::UNITS;
+use td::game::battle::MULLIGAN_DURATION;
+use td::game::card_gen::pack::last_modified;
+use td::game::engine::TICK;
+use td::game::hand::{HandMode, MULLIGAN_REDRAWS};
+use td::game::strategy::DEFAULT_STRATEGY;
+use td::server::decks::DeckStore;
 use td::server::service::{
     MessageType, ResponseType, ServerMessage, ServerResponse, ServerService,
 };
-use td::server::state::State;
+use td::server::state::{ServerError, ServerResult, State, FFA_QUEUE_WAIT};
 use td::server::user::User;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// How often a runtime unit pack is checked for changes on disk
+const UNIT_PACK_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 #[tokio::main]
 async fn main() {
@@ -52,13 +64,77 @@ async fn main() {
         }
     });
 
-    let state = Arc::new(RwLock::new(State::default()));
+    // Setting TD_UNITS_PATH to a units directory or pack file loads units from there instead
+    // of the ones compiled in, and reloads them whenever they change
+    let mut state = match env::var("TD_UNITS_PATH") {
+        Ok(path) => State::with_unit_pack(PathBuf::from(path)).expect("Failed to load unit pack"),
+        Err(_) => State::default(),
+    };
+
+    // Saved decks are kept as json at TD_DECKS_PATH, or decks.json by default
+    let decks_path = env::var("TD_DECKS_PATH").unwrap_or("decks.json".to_string());
+    state.set_deck_store(DeckStore::load(PathBuf::from(decks_path)).expect("Failed to load decks"));
+
+    let admin_token = Arc::new(env::var("TD_ADMIN_TOKEN").ok());
+
+    let state = Arc::new(RwLock::new(state));
+
+    if let Some(path) = state.read().await.units_path().cloned() {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut last_seen = last_modified(&path).ok();
+            loop {
+                tokio::time::sleep(UNIT_PACK_POLL_INTERVAL).await;
+
+                let modified = last_modified(&path).ok();
+                if modified != last_seen {
+                    last_seen = modified;
+                    match state.write().await.reload_units() {
+                        Ok(count) => println!("Reloaded {} units from {}", count, path.display()),
+                        Err(e) => eprintln!("Failed to reload units, keeping the old ones: {}", e),
+                    }
+                }
+            }
+        });
+    }
+
+    // Battles are simulated on the server, every client just renders the snapshots it's sent
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                // Each tick runs as its own task, so a panic in one can't stop battles for good
+                let state = state.clone();
+                let ticked = tokio::spawn(async move {
+                    let deliveries = {
+                        let mut state = state.write().await;
+                        let outbox = state.tick_battles();
+                        state.deliveries(outbox)
+                    };
+                    // Sent on their own once the state is unlocked, so slow clients can't hold
+                    // up the next tick or anyone waiting on the state
+                    tokio::spawn(deliveries.send());
+                });
+                if let Err(e) = ticked.await {
+                    eprintln!("Error ticking battles: {}", e);
+                }
+            }
+        });
+    }
+
     while let Some(msg) = rx.recv().await {
         let state_clone = state.clone();
+        let admin_token = admin_token.clone();
         tokio::spawn(async move {
             let state = state_clone.clone();
-            // handle incoming message asynchronously
-            println!("{:?}", msg);
+            // handle incoming message asynchronously, keeping admin tokens out of the log
+            match msg.msg {
+                MessageType::ReloadUnits(_) => println!("ReloadUnits from {}", msg.from),
+                _ => println!("{:?}", msg),
+            }
             match msg.msg {
                 MessageType::Text(txt) => {
                     let name = match state.read().await.get_name(msg.from) {
@@ -96,17 +172,22 @@ async fn main() {
                 MessageType::Disconnect => {
                     let mut state = state.write().await;
 
-                    let possible_enemy = state.get_opponent(msg.from);
-
-                    if let Some(enemy) = possible_enemy {
+                    if let Some(winners) = state.leave_battle(msg.from) {
                         let win_by_default =
                             ServerResponse::new(ResponseType::WinByDisconnect(msg.from));
                         state
-                            .broadcast_to(win_by_default, &[enemy])
+                            .broadcast_to(win_by_default, &winners)
                             .await
                             .expect("Failed to broadcast to user")
                     }
 
+                    let party = state.leave_party(msg.from);
+                    let names = party_names(&state, &party);
+                    state
+                        .broadcast_to(ServerResponse::new(ResponseType::Party(names)), &party)
+                        .await
+                        .expect("Failed to broadcast to user");
+
                     let name = state.get_name(msg.from);
 
                     if let Some(name) = name {
@@ -118,135 +199,639 @@ async fn main() {
                     }
                     state.disconnect(msg.from);
                 }
-                MessageType::BeginGame => {
+                MessageType::BeginGame(hand_mode) => {
                     let mut state = state.write().await;
-                    let result = state.new_random(msg.from);
 
-                    match result {
-                        Ok((_battle_id, against)) => {
-                            let name_a = state.get_name(msg.from).unwrap().clone();
-                            let name_b = state.get_name(against).unwrap().clone();
+                    // Nobody else to play with, so play against a bot instead
+                    let result = match state.new_random(msg.from, hand_mode) {
+                        Err(ServerError::NotEnoughInLobbyToStartError) => {
+                            state.new_bot_battle(msg.from, DEFAULT_STRATEGY, hand_mode)
+                        }
+                        result => result,
+                    };
 
-                            let message_a = ServerResponse::new(ResponseType::StartGame(
-                                name_a.clone(),
-                                name_b.clone(),
-                            ));
-                            let message_b =
-                                ServerResponse::new(ResponseType::StartGame(name_b, name_a));
+                    let started = match result {
+                        Ok((battle_id, _)) => announce_battle(&mut state, battle_id).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = started {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                        return;
+                    }
+
+                    if let Some(battle_id) = state.get_battle_id(msg.from) {
+                        schedule_mulligan_end(state_clone.clone(), battle_id);
+                    }
+                }
+                MessageType::BeginBotGame(strategy) => {
+                    let mut state = state.write().await;
+
+                    let started = match state.new_bot_battle(msg.from, &strategy, HandMode::Fixed) {
+                        Ok((battle_id, _)) => announce_battle(&mut state, battle_id).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = started {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                        return;
+                    }
+
+                    if let Some(battle_id) = state.get_battle_id(msg.from) {
+                        schedule_mulligan_end(state_clone.clone(), battle_id);
+                    }
+                }
+                MessageType::BeginStage(stage, hand_mode) => {
+                    let mut state = state.write().await;
+
+                    let started = match state.new_stage_battle(msg.from, stage, hand_mode) {
+                        Ok(battle_id) => announce_battle(&mut state, battle_id).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = started {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                        return;
+                    }
+
+                    if let Some(battle_id) = state.get_battle_id(msg.from) {
+                        schedule_mulligan_end(state_clone.clone(), battle_id);
+                    }
+                }
+                MessageType::ListStages => {
+                    let mut state = state.write().await;
+
+                    let response = match state.get_stages(msg.from) {
+                        Ok(stages) => ResponseType::Stages(stages),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::BeginTeamGame(hand_mode) => {
+                    let mut state = state.write().await;
 
+                    let started = match state.queue_team_battle(msg.from, hand_mode) {
+                        Ok(Some(battle_id)) => announce_battle(&mut state, battle_id)
+                            .await
+                            .map(|_| Some(battle_id)),
+                        Ok(None) => Ok(None),
+                        Err(e) => Err(e),
+                    };
+
+                    match started {
+                        Ok(Some(battle_id)) => {
+                            schedule_mulligan_end(state_clone.clone(), battle_id)
+                        }
+                        Ok(None) => {
+                            let party = state.get_party(msg.from);
                             state
-                                .broadcast_to(message_a, &[against])
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Chat(
+                                        "Server".to_string(),
+                                        "Waiting for another party to join...".to_string(),
+                                    )),
+                                    &party,
+                                )
                                 .await
                                 .expect("Failed to broadcast message");
+                        }
+                        Err(e) => state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message"),
+                    }
+                }
+                MessageType::BeginFreeForAll => {
+                    let mut state = state.write().await;
+
+                    let started = match state.queue_free_for_all(msg.from) {
+                        Ok(Some(battle_id)) => announce_battle(&mut state, battle_id)
+                            .await
+                            .map(|_| Some(battle_id)),
+                        Ok(None) => Ok(None),
+                        Err(e) => Err(e),
+                    };
+
+                    match started {
+                        Ok(Some(battle_id)) => {
+                            schedule_mulligan_end(state_clone.clone(), battle_id)
+                        }
+                        Ok(None) => {
                             state
-                                .broadcast_to(message_b, &[msg.from])
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Chat(
+                                        "Server".to_string(),
+                                        "Waiting for more players to join the free-for-all..."
+                                            .to_string(),
+                                    )),
+                                    &[msg.from],
+                                )
                                 .await
                                 .expect("Failed to broadcast message");
+                            schedule_free_for_all(state_clone.clone());
+                        }
+                        Err(e) => state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message"),
+                    }
+                }
+                MessageType::Target(side) => {
+                    let mut state = state.write().await;
+
+                    if let Err(e) = state.set_target(msg.from, side) {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                    }
+                }
+                MessageType::TeamText(txt) => {
+                    let mut state = state.write().await;
+                    let name = state.get_name(msg.from).cloned().unwrap_or_default();
 
+                    match state.team_chat_recipients(msg.from) {
+                        Ok(team) => {
+                            state
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::TeamChat(name, txt)),
+                                    &team,
+                                )
+                                .await
+                        }
+                        Err(e) => {
                             state
-                                .broadcast_users_hand(against)
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Error(e.to_string())),
+                                    &[msg.from],
+                                )
                                 .await
-                                .expect("Failed to send hand");
+                        }
+                    }
+                    .expect("Failed to broadcast message");
+                }
+                MessageType::PartyInvite(name) => {
+                    let mut state = state.write().await;
+
+                    match state.invite_to_party(msg.from, &name) {
+                        Ok(Some(party)) => {
+                            let names = party_names(&state, &party);
                             state
-                                .broadcast_users_hand(msg.from)
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Party(names)),
+                                    &party,
+                                )
                                 .await
-                                .expect("Failed to send hand");
                         }
-                        Err(error) => {
-                            panic!("Starting game failed: {}\n\nPotential TODO: map error and if its a lobby full error broadcast that to the user", error);
+                        Ok(None) => {
+                            let from = state.get_name(msg.from).cloned().unwrap_or_default();
+                            let invitee = state.find_user(&name);
+                            let invite = ServerResponse::new(ResponseType::Chat(
+                                "Server".to_string(),
+                                format!(
+                                    "{} invited you to a party, invite them back to join",
+                                    from
+                                ),
+                            ));
+                            state.broadcast_to(invite, &invitee).await
+                        }
+                        Err(e) => {
+                            state
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Error(e.to_string())),
+                                    &[msg.from],
+                                )
+                                .await
                         }
                     }
+                    .expect("Failed to broadcast message");
                 }
-                MessageType::PlayUnit(card_name) => {
-                    let unit = *UNITS
-                        .iter()
-                        .find(|unit| unit.get_name() == card_name)
-                        .expect("Unit doesn't exist :/");
+                MessageType::LeaveParty => {
+                    let mut state = state.write().await;
+                    let party = state.leave_party(msg.from);
+                    let names = party_names(&state, &party);
 
-                    let response_back =
-                        ServerResponse::new(ResponseType::UnitSpawned(true, Box::new(unit)));
-                    let response_to_opponent =
-                        ServerResponse::new(ResponseType::UnitSpawned(false, Box::new(unit)));
-                    let opponent = state
-                        .read()
+                    state
+                        .broadcast_to(ServerResponse::new(ResponseType::Party(names)), &party)
+                        .await
+                        .expect("Failed to broadcast message");
+                    state
+                        .broadcast_to(
+                            ServerResponse::new(ResponseType::Party(vec![])),
+                            &[msg.from],
+                        )
                         .await
-                        .get_opponent(msg.from)
-                        .expect("Couldn't find opponent");
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::Mulligan(card) => {
+                    let mut state = state.write().await;
+
+                    match state.mulligan(msg.from, card) {
+                        Ok(()) => state.broadcast_mulligan_hand(msg.from).await,
+                        Err(e) => {
+                            state
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Error(e.to_string())),
+                                    &[msg.from],
+                                )
+                                .await
+                        }
+                    }
+                    .expect("Failed to broadcast message");
+                }
+                MessageType::MulliganDone => {
+                    let mut state = state.write().await;
 
+                    if let Ok(Some(battle_id)) = state.finish_mulligan(msg.from) {
+                        if let Err(e) = state.begin_fighting(battle_id).await {
+                            eprintln!("Failed to begin battle: {}", e);
+                        }
+                    }
+                }
+                MessageType::ReloadUnits(token) => {
                     let mut state = state.write().await;
+
+                    let authorized = admin_token
+                        .as_deref()
+                        .is_some_and(|admin_token| tokens_match(admin_token, &token));
+                    let reply = if !authorized {
+                        "Invalid admin token".to_string()
+                    } else {
+                        match state.reload_units() {
+                            Ok(count) => format!("Reloaded {} units for new battles", count),
+                            Err(e) => format!("Failed to reload units: {}", e),
+                        }
+                    };
+
                     state
-                        .broadcast_to(response_back, &[msg.from])
+                        .broadcast_to(
+                            ServerResponse::new(ResponseType::Chat("Server".to_string(), reply)),
+                            &[msg.from],
+                        )
                         .await
                         .expect("Failed to broadcast message");
+                }
+                MessageType::GetCatalog => {
+                    let mut state = state.write().await;
+                    let catalog = state.units().units().to_vec();
 
                     state
-                        .broadcast_to(response_to_opponent, &[opponent])
+                        .broadcast_to(
+                            ServerResponse::new(ResponseType::Catalog(catalog)),
+                            &[msg.from],
+                        )
                         .await
                         .expect("Failed to broadcast message");
                 }
-                MessageType::DmgPing(dmg) => {
-                    let opponent = {
+                MessageType::SaveDeck(name, units) => {
+                    let mut state = state.write().await;
+
+                    let response = match state.save_deck(msg.from, &name, &units) {
+                        Ok(()) => ResponseType::Decks(state.get_decks(msg.from).unwrap()),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::DeleteDeck(name) => {
+                    let mut state = state.write().await;
+
+                    let response = match state.delete_deck(msg.from, &name) {
+                        Ok(()) => ResponseType::Decks(state.get_decks(msg.from).unwrap()),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::ListDecks => {
+                    let mut state = state.write().await;
+
+                    let response = match state.get_decks(msg.from) {
+                        Ok(decks) => ResponseType::Decks(decks),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::SelectDeck(name) => {
+                    let mut state = state.write().await;
+
+                    let response = match state.select_deck(msg.from, name.clone()) {
+                        Ok(()) => ResponseType::DeckSelected(name),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::FireCannon => {
+                    let mut state = state.write().await;
+
+                    if let Err(e) = state.fire_cannon(msg.from).await {
                         state
-                            .read()
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
                             .await
-                            .get_opponent(msg.from)
-                            .expect("User wasn't in a battle but expected an opponent")
+                            .expect("Failed to broadcast message");
+                    }
+                }
+                MessageType::BuyUpgrade(upgrade) => {
+                    let mut state = state.write().await;
+
+                    let response = match state.buy_upgrade(msg.from, upgrade) {
+                        Ok(level) => ResponseType::Upgraded(upgrade, level),
+                        Err(e) => ResponseType::Error(e.to_string()),
                     };
 
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::LevelUp(unit) => {
                     let mut state = state.write().await;
-                    let result = state.damage(opponent, dmg);
 
-                    match result {
-                        Some(remaining_hp) => {
-                            let health_update_to_sender = ServerResponse::new(
-                                ResponseType::NewTowerHealth(false, remaining_hp),
-                            );
-                            let health_update_to_enemy = ServerResponse::new(
-                                ResponseType::NewTowerHealth(true, remaining_hp),
-                            );
+                    let response = match state.level_up_card(msg.from, unit) {
+                        Ok(leveled) => ResponseType::LeveledUp(unit, leveled.get_level()),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
+                    let leveled = matches!(response, ResponseType::LeveledUp(..));
 
-                            state
-                                .broadcast_to(health_update_to_sender, &[msg.from])
-                                .await
-                                .expect("Failed to broadcast message back to sender");
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                    if leveled {
+                        state
+                            .broadcast_hand_update(msg.from)
+                            .await
+                            .expect("Failed to send hand");
+                    }
+                }
+                MessageType::Surrender => {
+                    let mut state = state.write().await;
 
-                            state
-                                .broadcast_to(health_update_to_enemy, &[opponent])
-                                .await
-                                .expect("Failed to broadcast message back to sender");
-                        }
-                        None => {
-                            let win = ServerResponse::new(ResponseType::Win(msg.from));
-                            let lose = ServerResponse::new(ResponseType::Lose(opponent));
+                    if let Err(e) = state.surrender(msg.from).await {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                    }
+                }
+                MessageType::OfferDraw | MessageType::AcceptDraw => {
+                    let mut state = state.write().await;
+                    let accept = matches!(msg.msg, MessageType::AcceptDraw);
 
-                            let user_name = { state.get_name(msg.from).unwrap().clone() };
+                    if let Err(e) = state.offer_draw(msg.from, accept).await {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                    }
+                }
+                MessageType::Rematch => {
+                    let mut state = state.write().await;
 
-                            let opponent_name = { state.get_name(opponent).unwrap().clone() };
+                    let started = match state.request_rematch(msg.from) {
+                        Ok(Some(battle_id)) => announce_battle(&mut state, battle_id)
+                            .await
+                            .map(|_| Some(battle_id)),
+                        Ok(None) => Ok(None),
+                        Err(e) => Err(e),
+                    };
 
+                    match started {
+                        Ok(Some(battle_id)) => {
+                            schedule_mulligan_end(state_clone.clone(), battle_id)
+                        }
+                        Ok(None) => {
+                            let name = state.get_name(msg.from).cloned().unwrap_or_default();
+                            let opponent = state.rematch_opponent(msg.from);
                             state
-                                .broadcast_to(win, &[msg.from])
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::RematchRequested(name)),
+                                    opponent.as_slice(),
+                                )
                                 .await
-                                .expect("Failed to broadcast message back to sender");
+                                .expect("Failed to broadcast message");
+                        }
+                        Err(e) => state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message"),
+                    }
+                }
+                MessageType::SetRules(rules) => {
+                    let mut state = state.write().await;
 
-                            state
-                                .broadcast_to(lose, &[opponent])
-                                .await
-                                .expect("Failed to broadcast message back to sender");
+                    let response = match state.set_rules(msg.from, rules) {
+                        Ok(()) => ResponseType::RulesSet(rules),
+                        Err(e) => ResponseType::Error(e.to_string()),
+                    };
 
+                    state
+                        .broadcast_to(ServerResponse::new(response), &[msg.from])
+                        .await
+                        .expect("Failed to broadcast message");
+                }
+                MessageType::PlayUnit(unit_id, lane) => {
+                    let played = state.write().await.play_card(msg.from, unit_id, lane);
+                    let unit = match played {
+                        Ok(unit) => unit,
+                        Err(e) => {
                             state
-                                .broadcast(ServerResponse::new(ResponseType::Chat(
-                                    "Server".to_string(),
-                                    format!(
-                                        "{} has won a game against {}",
-                                        user_name, opponent_name
-                                    ),
-                                )))
+                                .write()
+                                .await
+                                .broadcast_to(
+                                    ServerResponse::new(ResponseType::Error(e.to_string())),
+                                    &[msg.from],
+                                )
                                 .await
-                                .expect("Failed to broadcast message to all users");
+                                .expect("Failed to broadcast message");
+                            return;
                         }
+                    };
+
+                    let mut state = state.write().await;
+                    state
+                        .broadcast_unit_spawned(msg.from, unit)
+                        .await
+                        .expect("Failed to broadcast message");
+
+                    state
+                        .broadcast_hand_update(msg.from)
+                        .await
+                        .expect("Failed to send hand");
+                }
+                MessageType::CastSpell(spell, spot) => {
+                    let mut state = state.write().await;
+
+                    if let Err(e) = state.cast_spell(msg.from, spell, spot).await {
+                        state
+                            .broadcast_to(
+                                ServerResponse::new(ResponseType::Error(e.to_string())),
+                                &[msg.from],
+                            )
+                            .await
+                            .expect("Failed to broadcast message");
+                        return;
                     }
+
+                    state
+                        .broadcast_hand_update(msg.from)
+                        .await
+                        .expect("Failed to send hand");
                 }
             }
         });
     }
 }
+
+/// Tells every player who they're up against, deals their hands and starts the mulligan
+async fn announce_battle(state: &mut State<'_>, battle_id: Uuid) -> ServerResult<()> {
+    let battle = state
+        .battle(battle_id)
+        .ok_or(ServerError::NotInBattleError)?;
+    let players: Vec<_> = battle
+        .players()
+        .into_iter()
+        .map(|player| (player, battle.side_of(player)))
+        .collect();
+    let rules = battle.rules;
+    let map = battle.map;
+
+    let team_names = state.team_names(battle_id);
+    for (player, side) in &players {
+        let message = ServerResponse::new(ResponseType::StartGame(
+            state.opponent_names(battle_id, *side),
+            state.team_name(battle_id, *side),
+            rules,
+            map,
+        ));
+        state.broadcast_to(message, &[*player]).await?;
+
+        let names = ServerResponse::new(ResponseType::TeamNames(team_names.clone()));
+        state.broadcast_to(names, &[*player]).await?;
+    }
+
+    for (player, _) in &players {
+        // A stage isn't a user, so has no hand to send
+        if state.get_battle_id(*player).is_some() {
+            state.broadcast_users_hand(*player).await?;
+        }
+    }
+
+    let players: Vec<_> = players.into_iter().map(|(player, _)| player).collect();
+    let mulligan = ServerResponse::new(ResponseType::MulliganStart(
+        MULLIGAN_DURATION.as_secs(),
+        MULLIGAN_REDRAWS,
+    ));
+    state.broadcast_to(mulligan, &players).await
+}
+
+/// Compares tokens in constant time, so how long it takes doesn't give away how much of a guess
+/// was right
+fn tokens_match(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    let difference = expected
+        .iter()
+        .zip(given)
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+
+    difference == 0 && expected.len() == given.len()
+}
+
+/// Names of everyone in a party, or nobody if the party broke up
+fn party_names(state: &State<'_>, party: &[Uuid]) -> Vec<String> {
+    if party.len() < 2 {
+        return vec![];
+    }
+
+    party
+        .iter()
+        .filter_map(|member| state.get_name(*member).cloned())
+        .collect()
+}
+
+/// Begins the battle once the mulligan runs out, unless both players readied up before then
+fn schedule_mulligan_end(state: Arc<RwLock<State<'static>>>, battle_id: Uuid) {
+    tokio::spawn(async move {
+        tokio::time::sleep(MULLIGAN_DURATION).await;
+        if let Err(e) = state.write().await.begin_fighting(battle_id).await {
+            eprintln!("Failed to begin battle: {}", e);
+        }
+    });
+}
+
+/// Starts a free-for-all with whoever has queued once the wait is up, if there are enough
+/// players for one
+fn schedule_free_for_all(state: Arc<RwLock<State<'static>>>) {
+    tokio::spawn(async move {
+        tokio::time::sleep(FFA_QUEUE_WAIT).await;
+        let mut state_guard = state.write().await;
+
+        let started = match state_guard.start_free_for_all() {
+            Ok(Some(battle_id)) => announce_battle(&mut state_guard, battle_id)
+                .await
+                .map(|_| Some(battle_id)),
+            other => other,
+        };
+        drop(state_guard);
+
+        match started {
+            Ok(Some(battle_id)) => schedule_mulligan_end(state, battle_id),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to start free-for-all: {}", e),
+        }
+    });
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use crate::game::deck::{Deck, DeckError};
+
+use super::state::ServerResult;
+
+/// Every player's saved decks, keyed by player name. Names aren't proven in any way, so
+/// whoever connects with a name has that player's decks. When given a path the store is
+/// written back to disk as json every time a deck changes so decks survive a server restart
+#[derive(Default, Debug)]
+pub struct DeckStore {
+    decks: HashMap<String, Vec<Deck>>,
+    path: Option<PathBuf>,
+}
+
+impl DeckStore {
+    /// Loads a deck store from disk, starting empty if the file doesn't exist yet
+    pub fn load(path: PathBuf) -> ServerResult<Self> {
+        let decks = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            decks,
+            path: Some(path),
+        })
+    }
+
+    pub fn decks(&self, player: &str) -> &[Deck] {
+        self.decks.get(player).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn get(&self, player: &str, name: &str) -> Option<&Deck> {
+        self.decks(player).iter().find(|deck| deck.name() == name)
+    }
+
+    /// Saves a deck for a player, replacing any deck they already had with the same name
+    pub fn save(&mut self, player: &str, deck: Deck) -> ServerResult<()> {
+        let decks = self.decks.entry(player.to_string()).or_default();
+        decks.retain(|saved| saved.name() != deck.name());
+        decks.push(deck);
+
+        self.persist()
+    }
+
+    pub fn delete(&mut self, player: &str, name: &str) -> ServerResult<()> {
+        let decks = self.decks.entry(player.to_string()).or_default();
+        let count = decks.len();
+        decks.retain(|saved| saved.name() != name);
+
+        if decks.len() == count {
+            return Err(DeckError::NoSuchDeck(name.to_string()).into());
+        }
+
+        self.persist()
+    }
+
+    fn persist(&self) -> ServerResult<()> {
+        if let Some(path) = &self.path {
+            fs::write(path, serde_json::to_string_pretty(&self.decks)?)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+pub mod decks;
 pub mod service;
 pub mod state;
 pub mod user;
@@ -8,15 +8,24 @@ use hyper::{
     Method, StatusCode,
 };
 use hyper::{Request, Response};
-use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Read, pin::Pin};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs::File, io::Read, pin::Pin, str::FromStr};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 use uuid::Uuid;
 
-use crate::game::entity::Unit;
-
-use super::state::GAME_HAND_SIZE;
+use crate::game::{
+    battle::BattleSnapshot,
+    card::{Card, CardId},
+    entity::{Unit, UnitId},
+    hand::HandMode,
+    map::Map,
+    rules::MatchRules,
+    spell::{Spell, SpellId},
+    stage::StageSummary,
+    strategy::DEFAULT_STRATEGY,
+    tower::Upgrade,
+};
 
 pub struct ServerService {
     pub sender: UnboundedSender<ServerMessage>,
@@ -69,44 +78,227 @@ impl Service<Request<body::Incoming>> for ServerService {
 
                         while let Some(Ok(msg)) = reader.next().await {
                             // TODO - Respond to websocket messages accordingly
-                            match msg {
-                                Message::Text(txt) => {
-                                    let parsed: ClientMessage = serde_json::from_str(&txt).unwrap();
-                                    match parsed.r#type.as_str() {
-                                        "Text" => tx.send(ServerMessage::text(
-                                            user_id,
-                                            &parsed.data.unwrap(),
-                                        ))?,
-                                        "ConnectReq" => tx.send(ServerMessage::new(
-                                            user_id,
-                                            MessageType::ConnectReq(parsed.data.unwrap()),
-                                        ))?,
-                                        "BeginGame" => {
-                                            tx.send(ServerMessage::new(
-                                                user_id,
-                                                MessageType::BeginGame,
-                                            ))?;
-                                        }
-                                        "SpawnUnit" => tx.send(ServerMessage::new(
-                                            user_id,
-                                            MessageType::PlayUnit(parsed.data.unwrap()),
-                                        ))?,
-                                        "DmgPing" => tx.send(ServerMessage::new(
-                                            user_id,
-                                            MessageType::DmgPing(
-                                                parsed.data.unwrap().parse::<usize>().unwrap(),
-                                            ),
-                                        ))?,
-                                        _ => {}
-                                    }
-                                }
-                                Message::Close(_) => {
-                                    println!("Disconnect");
-                                    tx.send(ServerMessage::new(user_id, MessageType::Disconnect))?;
+                            let Message::Text(txt) = msg else { continue };
+
+                            // Messages the server can't make sense of are ignored
+                            let parsed: ClientMessage = match serde_json::from_str(&txt) {
+                                Ok(parsed) => parsed,
+                                Err(_) => continue,
+                            };
+                            match parsed.r#type.as_str() {
+                                "Text" => {
+                                    let Some(text) = parsed.data else { continue };
+                                    tx.send(ServerMessage::text(user_id, &text))?
+                                }
+                                "ConnectReq" => {
+                                    let Some(name) = parsed.data else { continue };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::ConnectReq(name),
+                                    ))?
+                                }
+                                "BeginGame" => {
+                                    let hand_mode = match parsed.data.as_deref() {
+                                        Some("Cycle") => HandMode::Cycle,
+                                        _ => HandMode::Fixed,
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::BeginGame(hand_mode),
+                                    ))?;
+                                }
+                                "BeginTeamGame" => {
+                                    let hand_mode = match parsed.data.as_deref() {
+                                        Some("Cycle") => HandMode::Cycle,
+                                        _ => HandMode::Fixed,
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::BeginTeamGame(hand_mode),
+                                    ))?;
+                                }
+                                "BeginFreeForAll" => tx.send(ServerMessage::new(
+                                    user_id,
+                                    MessageType::BeginFreeForAll,
+                                ))?,
+                                "Target" => {
+                                    let Some(side) = parsed.parsed_data::<usize>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(user_id, MessageType::Target(side)))?
+                                }
+                                "TeamText" => {
+                                    let Some(text) = parsed.data else { continue };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::TeamText(text),
+                                    ))?
+                                }
+                                "PartyInvite" => {
+                                    let Some(name) = parsed.data else { continue };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::PartyInvite(name),
+                                    ))?
+                                }
+                                "LeaveParty" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::LeaveParty))?
+                                }
+                                "BeginBotGame" => {
+                                    let strategy =
+                                        parsed.data.unwrap_or_else(|| DEFAULT_STRATEGY.to_string());
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::BeginBotGame(strategy),
+                                    ))?;
+                                }
+                                "BeginStage" => {
+                                    let Some(request) = parsed.json_data::<StageRequest>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::BeginStage(request.stage, request.hand_mode),
+                                    ))?;
+                                }
+                                "ListStages" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::ListStages))?
+                                }
+                                "SpawnUnit" => {
+                                    // Either a unit id on its own, or json with the
+                                    // lane to send it down
+                                    let play = match parsed.parsed_data::<UnitId>() {
+                                        Some(unit) => PlayRequest { unit, lane: None },
+                                        None => match parsed.json_data::<PlayRequest>() {
+                                            Some(play) => play,
+                                            None => continue,
+                                        },
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::PlayUnit(play.unit, play.lane),
+                                    ))?
+                                }
+                                "CastSpell" => {
+                                    let Some(cast) = parsed.json_data::<CastRequest>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::CastSpell(cast.spell, cast.spot),
+                                    ))?
+                                }
+                                "ReloadUnits" => {
+                                    let Some(token) = parsed.data else { continue };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::ReloadUnits(token),
+                                    ))?
+                                }
+                                "GetCatalog" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::GetCatalog))?
+                                }
+                                "SaveDeck" => {
+                                    let Some(deck) = parsed.json_data::<DeckRequest>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::SaveDeck(deck.name, deck.units),
+                                    ))?
+                                }
+                                "DeleteDeck" => {
+                                    let Some(name) = parsed.data else { continue };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::DeleteDeck(name),
+                                    ))?
+                                }
+                                "ListDecks" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::ListDecks))?
+                                }
+                                "SetRules" => {
+                                    // No data resets the rules, data that isn't rules is ignored
+                                    let rules = match parsed.data {
+                                        None => MatchRules::default(),
+                                        Some(_) => match parsed.json_data::<MatchRules>() {
+                                            Some(rules) => rules,
+                                            None => continue,
+                                        },
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::SetRules(rules),
+                                    ))?
+                                }
+                                "SelectDeck" => tx.send(ServerMessage::new(
+                                    user_id,
+                                    MessageType::SelectDeck(parsed.data),
+                                ))?,
+                                "Mulligan" => {
+                                    let Some(unit) = parsed.parsed_data::<UnitId>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::Mulligan(CardId::Unit(unit)),
+                                    ))?
+                                }
+                                "MulliganSpell" => {
+                                    let Some(spell) = parsed.parsed_data::<SpellId>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::Mulligan(CardId::Spell(spell)),
+                                    ))?
+                                }
+                                "MulliganDone" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::MulliganDone))?
+                                }
+                                "FireCannon" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::FireCannon))?
+                                }
+                                "BuyUpgrade" => {
+                                    let upgrade = match parsed.data.as_deref() {
+                                        Some("MaxHealth") => Upgrade::MaxHealth,
+                                        Some("Income") => Upgrade::Income,
+                                        Some("Wallet") => Upgrade::Wallet,
+                                        _ => continue,
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::BuyUpgrade(upgrade),
+                                    ))?
+                                }
+                                "LevelUp" => {
+                                    let Some(unit) = parsed.parsed_data::<UnitId>() else {
+                                        continue;
+                                    };
+                                    tx.send(ServerMessage::new(
+                                        user_id,
+                                        MessageType::LevelUp(unit),
+                                    ))?
+                                }
+                                "Surrender" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::Surrender))?
+                                }
+                                "OfferDraw" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::OfferDraw))?
+                                }
+                                "AcceptDraw" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::AcceptDraw))?
+                                }
+                                "Rematch" => {
+                                    tx.send(ServerMessage::new(user_id, MessageType::Rematch))?
                                 }
                                 _ => {}
                             }
                         }
+
+                        // However the connection ended, closed cleanly or dropped, the user's gone
+                        println!("Disconnect");
+                        tx.send(ServerMessage::new(user_id, MessageType::Disconnect))?;
                     }
                     Err(err) => {
                         eprintln!("Failed to establish WebSocket Connection: {}", err)
@@ -179,14 +371,45 @@ pub enum MessageType {
     ConnectReq(String),
     Text(String),
     ConnectWs(WebSocketWriteStream),
-    PlayUnit(String),
-    DmgPing(usize),
-    BeginGame,
+    // The lane to send the unit down, or the map's middle lane if none was picked
+    PlayUnit(UnitId, Option<usize>),
+    ReloadUnits(String),
+    GetCatalog,
+    SaveDeck(String, Vec<UnitId>),
+    DeleteDeck(String),
+    ListDecks,
+    // None to stop using a saved deck
+    SelectDeck(Option<String>),
+    // Rules for the battles this user starts
+    SetRules(MatchRules),
+    BeginGame(HandMode),
+    // Name of the strategy the bot plays with
+    BeginBotGame(String),
+    BeginTeamGame(HandMode),
+    BeginFreeForAll,
+    // Side of the opponent to send units at
+    Target(usize),
+    TeamText(String),
+    PartyInvite(String),
+    LeaveParty,
+    BeginStage(usize, HandMode),
+    ListStages,
+    CastSpell(SpellId, f32),
+    Mulligan(CardId),
+    MulliganDone,
+    FireCannon,
+    BuyUpgrade(Upgrade),
+    LevelUp(UnitId),
+    Surrender,
+    OfferDraw,
+    AcceptDraw,
+    Rematch,
     Disconnect,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ServerResponse<'a> {
+    #[serde(borrow)]
     message: ResponseType<'a>,
 }
 
@@ -194,22 +417,113 @@ impl<'a> ServerResponse<'a> {
     pub fn new(response: ResponseType<'a>) -> Self {
         Self { message: response }
     }
+
+    pub fn into_message(self) -> ResponseType<'a> {
+        self.message
+    }
 }
 
-#[derive(Serialize, Debug)]
+/// Everything the server sends to clients. Cards and units borrow their names from the
+/// message they're read from
+#[derive(Serialize, Deserialize, Debug)]
 pub enum ResponseType<'a> {
     Chat(String, String),
+    // Like chat, but only sent to the sender's team or party
+    TeamChat(String, String),
+    // Names of everyone in the user's party, empty when they're not in one
+    Party(Vec<String>),
     GameStart(Uuid),
     UserJoin(String),
     UserLeave(String),
-    StartGame(String, String),
-    DrawnHand(Box<[Unit<'a>; GAME_HAND_SIZE]>),
+    // Opponents' names, the player's own team name, the rules the battle is played with and
+    // the map it's fought on
+    StartGame(String, String, MatchRules, Map),
+    // Names of every team in the battle, indexed by side
+    TeamNames(Vec<String>),
+    DrawnHand(#[serde(borrow)] Vec<Card<'a>>),
+    // Seconds players have to redraw, and how many cards they can redraw
+    MulliganStart(u64, usize),
+    // Hand after a redraw, and how many redraws are left
+    MulliganHand(#[serde(borrow)] Vec<Card<'a>>, usize),
+    BattleBegin,
+    // Hand after a card was cycled out, and the card that will be drawn next
+    HandUpdate(
+        #[serde(borrow)] Vec<Card<'a>>,
+        #[serde(borrow)] Option<Box<Card<'a>>>,
+    ),
     // True if spawned from client, false if not
-    UnitSpawned(bool, Box<Unit<'a>>),
+    UnitSpawned(bool, #[serde(borrow)] Box<Unit<'a>>),
     NewTowerHealth(bool, usize),
+    // Side of the player who cast a spell, the spell, how far down their lane it landed, and
+    // how many units it froze or killed or how much it healed
+    SpellCast(usize, #[serde(borrow)] Box<Spell<'a>>, f32, usize),
+    // Side whose tower fired its cannon, and how many units the blast killed
+    CannonFired(usize, usize),
+    // An upgrade the player bought, and the level it's at now
+    Upgraded(Upgrade, usize),
+    // A unit card the player leveled up in their hand, and the level it's at now
+    LeveledUp(UnitId, usize),
+    // Name of the team whose tower fell while the battle goes on without them
+    Eliminated(String),
+    // Wave number counting from 1, how many waves the stage has, and whether it's a boss wave
+    WaveStarted(usize, usize, bool),
+    BattleSnapshot(BattleSnapshot),
+    // The time limit ran out and towers take extra damage until the tiebreak
+    SuddenDeath,
+    // Sudden death is over, sent just before the battle's result
+    TimeUp,
     Win(Uuid),
     WinByDisconnect(Uuid),
     Lose(Uuid),
+    // Time ran out with the player's tower tied for the most health, or everyone agreed to
+    // a draw
+    Draw(Uuid),
+    // Name of the team offering a draw
+    DrawOffered(String),
+    // Name of the player asking for a rematch
+    RematchRequested(String),
+    Catalog(#[serde(borrow)] Vec<Unit<'a>>),
+    Decks(#[serde(borrow)] Vec<(String, Vec<Unit<'a>>)>),
+    DeckSelected(Option<String>),
+    RulesSet(MatchRules),
+    Stages(Vec<StageSummary>),
+    Error(String),
+}
+
+/// Data sent along with a `SaveDeck` message
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeckRequest {
+    pub name: String,
+    pub units: Vec<UnitId>,
+}
+
+/// Data sent along with a `SpawnUnit` message when picking a lane
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayRequest {
+    pub unit: UnitId,
+    #[serde(default)]
+    pub lane: Option<usize>,
+}
+
+/// Data sent along with a `CastSpell` message
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CastRequest {
+    pub spell: SpellId,
+    /// How far down the lane an aimed spell lands, from the caster's tower
+    #[serde(default = "default_spot")]
+    pub spot: f32,
+}
+
+fn default_spot() -> f32 {
+    0.5
+}
+
+/// Data sent along with a `BeginStage` message
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StageRequest {
+    pub stage: usize,
+    #[serde(default)]
+    pub hand_mode: HandMode,
 }
 
 /// Type for interfacing with TypeScript WebSocket
@@ -218,3 +532,23 @@ pub struct ClientMessage {
     pub r#type: String,
     pub data: Option<String>,
 }
+
+impl ClientMessage {
+    pub fn new(r#type: &str, data: Option<String>) -> Self {
+        Self {
+            r#type: r#type.to_string(),
+            data,
+        }
+    }
+
+    /// The message's data parsed from a string, like a unit id, or `None` if there isn't any
+    /// or it doesn't parse
+    pub fn parsed_data<T: FromStr>(&self) -> Option<T> {
+        self.data.as_deref()?.parse().ok()
+    }
+
+    /// The message's data read as json, or `None` if there isn't any or it doesn't fit
+    pub fn json_data<T: DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_str(self.data.as_deref()?).ok()
+    }
+}
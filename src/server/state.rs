@@ -1,26 +1,150 @@
 use super::{
+    decks::DeckStore,
     service::{ResponseType, ServerResponse},
-    user::{User, UserStatus},
+    user::{SharedSocket, User, UserStatus},
 };
 use crate::game::{
-    battle::Battle,
-    entity::{draw_hand, Unit},
+    battle::{Battle, BattleEvent, BattlePhase, SNAPSHOT_TICKS},
+    bot::Observation,
+    card::{Card, CardId},
+    card_gen::{
+        pack::{PackError, UnitPack},
+        SPELLS,
+    },
+    deck::{Deck, DeckError},
+    engine::PlayError,
+    engine::Side,
+    entity::{Unit, UnitId},
+    hand::{Hand, HandMode, CYCLE_DECK_SIZE},
+    rules::{MatchRules, RulesError},
+    spell::{Spell, SpellId},
+    stage::{StageRun, StageSummary, STAGES},
+    strategy::{Action, StrategyRegistry},
+    tower::Upgrade,
 };
+use futures::SinkExt;
 use rand::Rng;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
+/// How many cards players hold unless a battle's rules say otherwise
 pub const GAME_HAND_SIZE: usize = 5;
+/// How many players are in a party, and on each team in a team battle
+pub const TEAM_SIZE: usize = 2;
+/// Free-for-alls start right away once this many players have queued
+pub const FFA_MAX_PLAYERS: usize = 4;
+/// Free-for-alls that haven't filled up start anyways after a wait, as long as there are at
+/// least this many players
+pub const FFA_MIN_PLAYERS: usize = 3;
+/// How long the first player in the free-for-all queue waits for it to fill up
+pub const FFA_QUEUE_WAIT: Duration = Duration::from_secs(15);
+
+/// Messages worked out while the state is locked, each with who it goes to
+pub type Outbox<'a> = Vec<(Vec<Uuid>, ServerResponse<'a>)>;
+
+/// An outbox with every message serialized and every recipient's socket looked up, so it can
+/// be sent once the state is unlocked and a slow client only holds up its own messages
+pub struct Deliveries(Vec<(SharedSocket, Vec<String>)>);
+
+impl Deliveries {
+    /// Sends every socket its messages in order, logging any that can't be reached
+    pub async fn send(self) {
+        let sends = self.0.into_iter().map(|(socket, messages)| async move {
+            let mut socket = socket.lock().await;
+            for message in messages {
+                if let Err(e) = socket.send(Message::text(message)).await {
+                    eprintln!("Error sending a message: {}", e);
+                    return;
+                }
+            }
+        });
+
+        futures::future::join_all(sends).await;
+    }
+}
 
 #[derive(Default)]
 pub struct State<'a> {
     users: HashMap<Uuid, User<'a>>,
     battles: HashMap<Uuid, Battle>,
+    units: Arc<UnitPack>,
+    units_path: Option<PathBuf>,
+    decks: DeckStore,
+    /// How many stages each player has cleared, keyed by player name. Clearing a stage
+    /// unlocks the next one
+    stage_progress: HashMap<String, usize>,
+    /// Groups of players who queue for team battles together, keyed by party id
+    parties: HashMap<Uuid, Vec<Uuid>>,
+    /// Who each player has invited to a party
+    party_invites: HashMap<Uuid, Uuid>,
+    /// A full party waiting for another party to play a team battle against
+    team_queue: Option<Uuid>,
+    /// Players waiting for a free-for-all, in the order they queued
+    ffa_queue: Vec<Uuid>,
+    /// The last one on one battle each player finished, so it can be played again
+    last_matches: HashMap<Uuid, LastMatch>,
+    /// Who each player has asked for a rematch
+    rematch_requests: HashMap<Uuid, Uuid>,
+    /// Strategies bots can be started with
+    strategies: StrategyRegistry,
+}
+
+/// Who played a one on one battle and how, so a rematch can be set up the same way
+#[derive(Clone, Copy, Debug)]
+struct LastMatch {
+    players: [Uuid; 2],
+    hand_mode: HandMode,
+    rules: MatchRules,
 }
 
 impl<'a> State<'a> {
+    /// Creates a state whose units are loaded from a units directory or pack file instead of
+    /// the ones compiled in, allowing them to be reloaded while the server is running
+    pub fn with_unit_pack(path: PathBuf) -> ServerResult<Self> {
+        let units = UnitPack::load(&path)?;
+
+        Ok(Self {
+            units: Arc::new(units),
+            units_path: Some(path),
+            ..Default::default()
+        })
+    }
+
+    pub fn set_deck_store(&mut self, decks: DeckStore) {
+        self.decks = decks
+    }
+
+    /// Strategies players can pick a bot to play against with, register more here
+    pub fn strategies_mut(&mut self) -> &mut StrategyRegistry {
+        &mut self.strategies
+    }
+
+    /// Units new battles are started with, and that decks are built from
+    pub fn units(&self) -> &Arc<UnitPack> {
+        &self.units
+    }
+
+    pub fn units_path(&self) -> Option<&PathBuf> {
+        self.units_path.as_ref()
+    }
+
+    /// Reloads the unit pack from disk, returning how many units it now has. Battles that
+    /// are already running keep the units they started with
+    pub fn reload_units(&mut self) -> ServerResult<usize> {
+        let path = self
+            .units_path
+            .as_ref()
+            .ok_or(ServerError::NoUnitPackError)?;
+        let units = UnitPack::load(path)?;
+        let count = units.len();
+
+        self.units = Arc::new(units);
+        Ok(count)
+    }
+
     pub fn get_name(&self, id: Uuid) -> Option<&String> {
-        self.users[&id].name()
+        self.users.get(&id)?.name()
     }
 
     pub fn connect(&mut self, id: Uuid, user: User<'a>) {
@@ -28,40 +152,1078 @@ impl<'a> State<'a> {
     }
 
     pub fn disconnect(&mut self, id: Uuid) {
+        if let Some(battle_id) = self.get_battle_id(id) {
+            self.end_battle(battle_id);
+        }
+
+        self.leave_party(id);
+        self.party_invites
+            .retain(|from, to| *from != id && *to != id);
+        self.ffa_queue.retain(|player| *player != id);
+        self.rematch_requests
+            .retain(|from, to| *from != id && *to != id);
+        self.last_matches.remove(&id);
+
         if self.users.contains_key(&id) {
             self.users.remove(&id);
         }
     }
 
-    pub fn damage(&mut self, to: Uuid, dmg: usize) -> Option<usize> {
-        if let UserStatus::InGame(battle_id) = *self.users[&to].status() {
-            let battle = self.battles.get_mut(&battle_id).unwrap();
+    /// Takes a player out of their battle. Their team keeps fighting as long as anyone is
+    /// left on it, otherwise their tower falls and, if only one side is left standing, the
+    /// battle ends and the players who won by default are returned
+    pub fn leave_battle(&mut self, id: Uuid) -> Option<Vec<Uuid>> {
+        let battle_id = self.get_battle_id(id)?;
+        let battle = self.battles.get_mut(&battle_id)?;
+        let side = battle.side_of(id);
+
+        battle.teams[side.index()]
+            .players
+            .retain(|player| *player != id);
+        if let Some(user) = self.users.get_mut(&id) {
+            user.leave_game();
+        }
+
+        let battle = &self.battles[&battle_id];
+        let abandoned = battle
+            .team(side)
+            .players
+            .iter()
+            .all(|player| self.users.get(player).is_none_or(|user| user.is_bot()));
+        if !abandoned {
+            return None;
+        }
+
+        // In a free-for-all the others keep fighting until only one of them is left
+        let battle = self.battles.get_mut(&battle_id)?;
+        let winner = battle.eliminate(side)?;
+        let winners = battle.team(winner).players.clone();
+        self.end_battle(battle_id);
+
+        Some(winners)
+    }
+
+    /// Removes a battle, sending its players back to the lobby and removing any bots
+    pub fn end_battle(&mut self, battle_id: Uuid) {
+        if let Some(battle) = self.battles.remove(&battle_id) {
+            self.remember_match(&battle);
+
+            for player in battle.players() {
+                match self.users.get_mut(&player) {
+                    Some(user) if user.is_bot() => {
+                        self.users.remove(&player);
+                    }
+                    Some(user) => user.leave_game(),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// Remembers a battle between two users so either of them can ask for a rematch
+    fn remember_match(&mut self, battle: &Battle) {
+        let players = battle.players();
+        let humans = players
+            .iter()
+            .all(|player| self.users.get(player).is_some_and(|user| !user.is_bot()));
+
+        if let ([a, b], true, None) = (&players[..], humans, &battle.stage) {
+            let last = LastMatch {
+                players: [*a, *b],
+                hand_mode: battle.hand_mode,
+                rules: battle.rules,
+            };
+
+            self.last_matches.insert(*a, last);
+            self.last_matches.insert(*b, last);
+        }
+    }
+
+    /// Everyone on the other team in the user's battle
+    pub fn get_enemies(&self, id: Uuid) -> Vec<Uuid> {
+        match self.get_battle_id(id) {
+            Some(battle_id) => self.battles[&battle_id].enemies(id).to_vec(),
+            None => vec![],
+        }
+    }
+
+    /// Everyone on the user's team in their battle, including the user
+    pub fn get_teammates(&self, id: Uuid) -> Vec<Uuid> {
+        match self.get_battle_id(id) {
+            Some(battle_id) => self.battles[&battle_id].teammates(id).to_vec(),
+            None => vec![id],
+        }
+    }
+
+    /// Names of everyone on one side of a battle, or the stage's name for a PvE stage
+    pub fn team_name(&self, battle_id: Uuid, side: Side) -> String {
+        let battle = &self.battles[&battle_id];
+        if let (Some(stage), Side::B) = (&battle.stage, side) {
+            return stage.stage().name.to_string();
+        }
+
+        battle
+            .team(side)
+            .players
+            .iter()
+            .filter_map(|player| self.get_name(*player).cloned())
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    /// Names of every team in a battle other than `side`, the opponents they're up against
+    pub fn opponent_names(&self, battle_id: Uuid, side: Side) -> String {
+        self.battles[&battle_id]
+            .sides()
+            .filter(|other| *other != side)
+            .map(|other| self.team_name(battle_id, other))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Names of every team in a battle, indexed by their side
+    pub fn team_names(&self, battle_id: Uuid) -> Vec<String> {
+        self.battles[&battle_id]
+            .sides()
+            .map(|side| self.team_name(battle_id, side))
+            .collect()
+    }
+
+    /// Picks which opponent a player's units are sent at
+    pub fn set_target(&mut self, id: Uuid, target: usize) -> ServerResult<()> {
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+
+        Ok(battle.set_target(id, Side(target))?)
+    }
+
+    pub fn battle(&self, battle_id: Uuid) -> Option<&Battle> {
+        self.battles.get(&battle_id)
+    }
+
+    pub fn get_battle_id(&self, id: Uuid) -> Option<Uuid> {
+        match self.users.get(&id)?.status() {
+            UserStatus::InGame(battle_id) => Some(*battle_id),
+            UserStatus::Lobby => None,
+        }
+    }
+
+    /// Builds a deck from unit ids in the current unit pack and saves it under the user's name
+    pub fn save_deck(&mut self, id: Uuid, name: &str, units: &[UnitId]) -> ServerResult<()> {
+        let player = self
+            .get_name(id)
+            .ok_or(ServerError::NoNameYetError)?
+            .clone();
+        let deck = Deck::build(name, units, &self.units)?;
+
+        self.decks.save(&player, deck)
+    }
+
+    pub fn delete_deck(&mut self, id: Uuid, name: &str) -> ServerResult<()> {
+        let player = self
+            .get_name(id)
+            .ok_or(ServerError::NoNameYetError)?
+            .clone();
+        self.decks.delete(&player, name)?;
+
+        if let Some(user) = self.users.get_mut(&id) {
+            if user
+                .selected_deck()
+                .is_some_and(|selected| selected == name)
+            {
+                user.select_deck(None)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The user's saved decks with their units resolved against the current unit pack. Decks
+    /// holding units that were removed by a reload are left out
+    pub fn get_decks(&self, id: Uuid) -> ServerResult<Vec<(String, Vec<Unit<'a>>)>> {
+        let player = self.get_name(id).ok_or(ServerError::NoNameYetError)?;
+
+        Ok(self
+            .decks
+            .decks(player)
+            .iter()
+            .filter_map(|deck| {
+                let units = deck.units(&self.units).ok()?;
+                Some((deck.name().to_string(), units))
+            })
+            .collect())
+    }
+
+    /// Every stage, and whether the user has unlocked and cleared it
+    pub fn get_stages(&self, id: Uuid) -> ServerResult<Vec<StageSummary>> {
+        let cleared = self.stages_cleared(id)?;
+
+        Ok(STAGES
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| StageSummary {
+                index,
+                name: stage.name,
+                victory: stage.victory,
+                waves: stage.waves.len(),
+                unlocked: index <= cleared,
+                cleared: index < cleared,
+            })
+            .collect())
+    }
+
+    fn stages_cleared(&self, id: Uuid) -> ServerResult<usize> {
+        let player = self.get_name(id).ok_or(ServerError::NoNameYetError)?;
+
+        Ok(self.stage_progress.get(player).copied().unwrap_or(0))
+    }
+
+    /// Picks which saved deck the user draws from in their next battles, or `None` to go
+    /// back to drawing from every unit
+    pub fn select_deck(&mut self, id: Uuid, name: Option<String>) -> ServerResult<()> {
+        if let Some(name) = &name {
+            let player = self.get_name(id).ok_or(ServerError::NoNameYetError)?;
+            let deck = self
+                .decks
+                .get(player, name)
+                .ok_or_else(|| DeckError::NoSuchDeck(name.clone()))?;
+
+            deck.validate(&self.units)?;
+        }
+
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .select_deck(name);
+
+        Ok(())
+    }
+
+    /// The cards in a user's selected deck, or `None` if they haven't picked one. Fails with
+    /// the reason if the deck is gone, no longer valid for this pack or too small for the
+    /// battle's hand size, rather than quietly dealing them something else
+    fn selected_cards(
+        &self,
+        id: Uuid,
+        battle: &Battle,
+    ) -> ServerResult<Option<Vec<Card<'static>>>> {
+        let Some(deck) = self.users[&id].selected_deck() else {
+            return Ok(None);
+        };
+        let player = self.get_name(id).ok_or(ServerError::NoNameYetError)?;
+        let unusable =
+            |reason: String| ServerError::UnusableDeckError(player.clone(), deck.clone(), reason);
+
+        let units = self
+            .decks
+            .get(player, deck)
+            .ok_or_else(|| unusable(DeckError::NoSuchDeck(deck.clone()).to_string()))?
+            .units(&battle.units)
+            .map_err(|e| unusable(e.to_string()))?;
+        if units.len() < battle.rules.hand_size {
+            return Err(unusable(format!(
+                "it has {} units but this battle's hands hold {}",
+                units.len(),
+                battle.rules.hand_size
+            )));
+        }
+
+        Ok(Some(units.into_iter().map(Card::from).collect()))
+    }
+
+    /// Deals a hand for a user from their selected deck, or from every unit in the pack and
+    /// every spell if they haven't picked one. Cycling players without a deck get a random
+    /// deck of `CYCLE_DECK_SIZE` cards
+    fn deal_user_hand(&self, id: Uuid, battle: &Battle) -> ServerResult<Hand<'static>> {
+        let pack = &battle.units;
+        let hand_size = battle.rules.hand_size;
+
+        let deck = self.selected_cards(id, battle)?.unwrap_or_else(|| {
+            let every_card: Vec<Card<'static>> = pack
+                .units()
+                .iter()
+                .copied()
+                .map(Card::from)
+                .chain(SPELLS.iter().copied().map(Card::from))
+                .collect();
+
+            match battle.hand_mode {
+                HandMode::Fixed => every_card,
+                HandMode::Cycle => {
+                    let mut cards = battle.draw_rules.draw_order(
+                        &every_card,
+                        hand_size,
+                        &mut rand::thread_rng(),
+                    );
+                    cards.truncate(CYCLE_DECK_SIZE.max(hand_size));
+                    cards
+                }
+            }
+        });
+
+        Hand::deal(deck, hand_size, battle.hand_mode, &battle.draw_rules)
+            .ok_or(ServerError::NotEnoughUnitsError)
+    }
+
+    /// Plays a card from the user's hand onto the field in the given lane, or the middle lane
+    /// if they didn't pick one, failing if it isn't a card they can play right now
+    pub fn play_card(
+        &mut self,
+        id: Uuid,
+        unit: UnitId,
+        lane: Option<usize>,
+    ) -> ServerResult<Unit<'static>> {
+        if self.get_battle_phase(id)? != BattlePhase::Fighting {
+            return Err(ServerError::BattleNotStartedError);
+        }
+
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let unit = Self::held_unit(&self.users[&id], battle, unit)?;
+
+        let lane = lane.unwrap_or(battle.map.middle_lane());
+
+        // Check before playing from the hand so a cycling hand doesn't cycle a card that was
+        // never paid for
+        battle.can_play(id, &Card::Unit(unit))?;
+        if lane >= battle.map.lanes {
+            return Err(PlayError::InvalidLane.into());
+        }
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .play_card(CardId::Unit(unit.get_id()))
+            .ok_or(ServerError::CardNotInHandError)?;
+        battle.play(id, unit, lane)?;
+
+        Ok(unit)
+    }
+
+    /// A unit card in the user's hand, taken from the battle's pack at the level of the card
+    fn held_unit(user: &User, battle: &Battle, unit: UnitId) -> ServerResult<Unit<'static>> {
+        let held = user
+            .hand()
+            .and_then(|hand| hand.card(CardId::Unit(unit)))
+            .and_then(|card| card.unit())
+            .ok_or(ServerError::CardNotInHandError)?;
+        let unit = battle
+            .units
+            .get(unit)
+            .ok_or(ServerError::CardNotInHandError)?;
+
+        Ok(Unit {
+            level: held.get_level(),
+            ..*unit
+        })
+    }
+
+    /// Casts a spell from the user's hand, landing `spot` of the way down their lane if it's
+    /// aimed, then lets everyone in the battle know
+    pub async fn cast_spell(&mut self, id: Uuid, spell: SpellId, spot: f32) -> ServerResult<()> {
+        let (players, response) = self.play_spell(id, spell, spot)?;
+        self.broadcast_to(response, &players).await
+    }
+
+    /// Casts a spell from the user's hand, returning the news of it for everyone in the battle
+    fn play_spell(
+        &mut self,
+        id: Uuid,
+        spell: SpellId,
+        spot: f32,
+    ) -> ServerResult<(Vec<Uuid>, ServerResponse<'a>)> {
+        if self.get_battle_phase(id)? != BattlePhase::Fighting {
+            return Err(ServerError::BattleNotStartedError);
+        }
+
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let spell: Spell<'static> = *SPELLS.get(spell).ok_or(ServerError::CardNotInHandError)?;
+
+        battle.can_play(id, &Card::Spell(spell))?;
+        if !self.users[&id]
+            .hand()
+            .is_some_and(|hand| hand.cards().contains(&Card::Spell(spell)))
+        {
+            return Err(ServerError::CardNotInHandError);
+        }
+        let affected = battle.cast(id, spell, spot)?;
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .play_card(CardId::Spell(spell.get_id()));
+
+        let side = battle.side_of(id);
+        let players = battle.players();
+        let response = ServerResponse::new(ResponseType::SpellCast(
+            side.index(),
+            Box::new(spell),
+            spot,
+            affected,
+        ));
+        Ok((players, response))
+    }
+
+    /// Fires the cannon on the player's tower, letting everyone in the battle know. Returns
+    /// how many units the blast killed
+    pub async fn fire_cannon(&mut self, id: Uuid) -> ServerResult<usize> {
+        if self.get_battle_phase(id)? != BattlePhase::Fighting {
+            return Err(ServerError::BattleNotStartedError);
+        }
+
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let kills = battle.fire_cannon(id)?;
+
+        let side = battle.side_of(id);
+        let players = battle.players();
+        let response = ServerResponse::new(ResponseType::CannonFired(side.index(), kills));
+        self.broadcast_to(response, &players).await?;
+
+        Ok(kills)
+    }
+
+    /// Pays to level up a unit card in the player's hand, returning the leveled card
+    pub fn level_up_card(&mut self, id: Uuid, unit: UnitId) -> ServerResult<Unit<'static>> {
+        if self.get_battle_phase(id)? != BattlePhase::Fighting {
+            return Err(ServerError::BattleNotStartedError);
+        }
+
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let card = CardId::Unit(unit);
+        let unit = Self::held_unit(&self.users[&id], battle, unit)?;
+
+        let leveled = battle.level_up(id, unit)?;
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .replace_card(card, Card::Unit(leveled));
+
+        Ok(leveled)
+    }
+
+    /// Buys the next level of an upgrade for the player, returning the new level
+    pub fn buy_upgrade(&mut self, id: Uuid, upgrade: Upgrade) -> ServerResult<usize> {
+        if self.get_battle_phase(id)? != BattlePhase::Fighting {
+            return Err(ServerError::BattleNotStartedError);
+        }
+
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+
+        Ok(battle.buy_upgrade(id, upgrade)?)
+    }
+
+    /// Advances every battle that's being fought by one tick, letting bots play and finishing
+    /// battles whose tower has fallen. Returns the snapshots and everything else players need
+    /// to hear about, to be sent once the state is unlocked
+    pub fn tick_battles(&mut self) -> Outbox<'a> {
+        let fighting: Vec<Uuid> = self
+            .battles
+            .iter()
+            .filter(|(_, battle)| battle.phase == BattlePhase::Fighting)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut outbox = vec![];
+
+        for battle_id in fighting {
+            outbox.extend(self.bot_turns(battle_id));
+
+            let Some(battle) = self.battles.get_mut(&battle_id) else {
+                continue;
+            };
+            let events = battle.tick();
+            let send_snapshot = battle.ticks.is_multiple_of(SNAPSHOT_TICKS);
+
+            for event in events {
+                outbox.extend(self.battle_event(battle_id, event));
+            }
+
+            if send_snapshot && self.battles.contains_key(&battle_id) {
+                for player in self.battles[&battle_id].players() {
+                    let snapshot = self.battles[&battle_id].snapshot(player);
+                    let response = ServerResponse::new(ResponseType::BattleSnapshot(snapshot));
+                    outbox.push((vec![player], response));
+                }
+            }
+        }
+
+        outbox
+    }
+
+    /// Serializes an outbox for sending, skipping bots and anyone without a socket
+    pub fn deliveries(&self, outbox: Outbox<'_>) -> Deliveries {
+        let mut deliveries: Vec<(Uuid, SharedSocket, Vec<String>)> = vec![];
+
+        for (to, response) in outbox {
+            let message = match serde_json::to_string(&response) {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!("Error serializing a message: {}", e);
+                    continue;
+                }
+            };
+
+            for id in to {
+                if let Some(delivery) = deliveries.iter_mut().find(|(user, ..)| *user == id) {
+                    delivery.2.push(message.clone());
+                } else if let Some(socket) = self.users.get(&id).and_then(User::socket) {
+                    deliveries.push((id, socket, vec![message.clone()]));
+                }
+            }
+        }
+
+        Deliveries(
+            deliveries
+                .into_iter()
+                .map(|(_, socket, messages)| (socket, messages))
+                .collect(),
+        )
+    }
+
+    /// Sends an outbox while the state is still held, for the rare events that aren't part of
+    /// a tick
+    async fn send_outbox(&mut self, outbox: Outbox<'_>) -> ServerResult<()> {
+        for (to, response) in outbox {
+            self.broadcast_to(response, &to).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Works out what the players in a battle need to hear about something that happened in
+    /// it
+    fn battle_event(&mut self, battle_id: Uuid, event: BattleEvent) -> Outbox<'a> {
+        let mut outbox = vec![];
+        match event {
+            BattleEvent::TowerDamaged { side, health } => {
+                let battle = &self.battles[&battle_id];
+                let owners = battle.team(side).players.clone();
+                // Only the players attacking this tower show it as the enemy's, the
+                // rest of a free-for-all sees it in their next snapshot
+                let enemies: Vec<Uuid> = battle
+                    .players()
+                    .into_iter()
+                    .filter(|player| {
+                        battle.side_of(*player) != side && battle.target_of(*player) == side
+                    })
+                    .collect();
+                let to_owners = ResponseType::NewTowerHealth(true, health);
+                let to_enemies = ResponseType::NewTowerHealth(false, health);
+
+                outbox.push((owners, ServerResponse::new(to_owners)));
+                outbox.push((enemies, ServerResponse::new(to_enemies)));
+            }
+            BattleEvent::Finished { winner } => {
+                outbox.extend(self.finish_battle(battle_id, winner));
+            }
+            BattleEvent::SuddenDeath => {
+                let players = self.battles[&battle_id].players();
+                outbox.push((players, ServerResponse::new(ResponseType::SuddenDeath)));
+            }
+            BattleEvent::TimeUp { winner } => {
+                let players = self.battles[&battle_id].players();
+                outbox.push((players, ServerResponse::new(ResponseType::TimeUp)));
+
+                outbox.extend(match winner {
+                    Some(winner) => self.finish_battle(battle_id, winner),
+                    None => self.draw_battle(battle_id, "ran out of time in a draw"),
+                });
+            }
+            BattleEvent::Eliminated { side } => {
+                let name = self.team_name(battle_id, side);
+                let players = self.battles[&battle_id].players();
+                outbox.push((players, ServerResponse::new(ResponseType::Eliminated(name))));
+            }
+            // Players see units die in their next snapshot
+            BattleEvent::UnitKilled { .. } => {}
+            BattleEvent::WaveStarted { wave, waves, boss } => {
+                let players = self.battles[&battle_id].players();
+                let response = ServerResponse::new(ResponseType::WaveStarted(wave, waves, boss));
+                outbox.push((players, response));
+            }
+        }
+
+        outbox
+    }
+
+    /// Every user who isn't a bot, for messages that go to the whole lobby
+    fn everyone(&self) -> Vec<Uuid> {
+        self.users
+            .iter()
+            .filter(|(_, user)| !user.is_bot())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Ends the battle, returning what tells every player whether their team won and
+    /// announces it to the lobby
+    fn finish_battle(&mut self, battle_id: Uuid, winner: Side) -> Outbox<'a> {
+        let winner_name = self.team_name(battle_id, winner);
+        let loser_name = self.opponent_names(battle_id, winner);
+
+        let battle = &self.battles[&battle_id];
+        let winners = battle.team(winner).players.clone();
+        let losers: Vec<Uuid> = battle
+            .sides()
+            .filter(|side| *side != winner)
+            .flat_map(|side| battle.team(side).players.clone())
+            .collect();
+
+        let announcement = match &battle.stage {
+            Some(stage) if winner == Side::A => {
+                let index = stage.index();
+                let name = stage.stage().name;
+                let progress = self.stage_progress.entry(winner_name.clone()).or_default();
+                *progress = (*progress).max(index + 1);
+
+                format!("{} has cleared {}", winner_name, name)
+            }
+            Some(stage) => format!("{} was overrun on {}", loser_name, stage.stage().name),
+            None => format!("{} has won a game against {}", winner_name, loser_name),
+        };
+        // Ended before anyone's told, so a player who can't be reached can't keep it going
+        self.end_battle(battle_id);
+
+        let mut outbox: Outbox = winners
+            .into_iter()
+            .map(|player| (vec![player], ServerResponse::new(ResponseType::Win(player))))
+            .collect();
+        outbox.extend(losers.into_iter().map(|player| {
+            (
+                vec![player],
+                ServerResponse::new(ResponseType::Lose(player)),
+            )
+        }));
+
+        let announcement = ResponseType::Chat("Server".to_string(), announcement);
+        outbox.push((self.everyone(), ServerResponse::new(announcement)));
+        outbox
+    }
+
+    /// Ends a battle in a draw, either because every side agreed to one or it ran out of time
+    /// with the best towers tied. Everyone still standing draws, anyone already eliminated
+    /// has lost. Returns what tells the players and the lobby
+    fn draw_battle(&mut self, battle_id: Uuid, reason: &str) -> Outbox<'a> {
+        let battle = &self.battles[&battle_id];
+        let left = battle.sides_left();
+        let names: Vec<String> = left
+            .iter()
+            .map(|side| self.team_name(battle_id, *side))
+            .collect();
+
+        let results: Vec<(Uuid, bool)> = battle
+            .players()
+            .into_iter()
+            .map(|player| (player, left.contains(&battle.side_of(player))))
+            .collect();
+        self.end_battle(battle_id);
+
+        let mut outbox: Outbox = results
+            .into_iter()
+            .map(|(player, standing)| {
+                let response = match standing {
+                    true => ResponseType::Draw(player),
+                    false => ResponseType::Lose(player),
+                };
+                (vec![player], ServerResponse::new(response))
+            })
+            .collect();
+
+        let announcement = ResponseType::Chat(
+            "Server".to_string(),
+            format!("{} {}", names.join(" and "), reason),
+        );
+        outbox.push((self.everyone(), ServerResponse::new(announcement)));
+        outbox
+    }
+
+    /// Gives up the battle for the player's whole team, knocking their tower out
+    pub async fn surrender(&mut self, id: Uuid) -> ServerResult<()> {
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let side = battle.side_of(id);
+        if battle.team(side).is_eliminated() {
+            return Err(PlayError::Eliminated.into());
+        }
+
+        let winner = battle.eliminate(side);
+        let name = self.team_name(battle_id, side);
+        self.broadcast(ServerResponse::new(ResponseType::Chat(
+            "Server".to_string(),
+            format!("{} surrendered", name),
+        )))
+        .await?;
+
+        match winner {
+            Some(winner) => {
+                let outbox = self.finish_battle(battle_id, winner);
+                self.send_outbox(outbox).await
+            }
+            None => {
+                let players = self.battles[&battle_id].players();
+                let response = ServerResponse::new(ResponseType::Eliminated(name));
+
+                self.broadcast_to(response, &players).await
+            }
+        }
+    }
+
+    /// Offers the other sides a draw, or accepts one if `accept` is set. The battle ends in a
+    /// draw once every side still standing has offered one
+    pub async fn offer_draw(&mut self, id: Uuid, accept: bool) -> ServerResult<()> {
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+        let side = battle.side_of(id);
+
+        if battle.stage.is_some() {
+            return Err(ServerError::DrawNotAllowedError);
+        }
+        if battle.team(side).is_eliminated() {
+            return Err(PlayError::Eliminated.into());
+        }
+        if accept && !battle.draw_offered_to(side) {
+            return Err(ServerError::NoDrawOfferError);
+        }
+
+        if battle.offer_draw(side) {
+            let outbox = self.draw_battle(battle_id, "agreed to a draw");
+            return self.send_outbox(outbox).await;
+        }
+
+        let name = self.team_name(battle_id, side);
+        let others: Vec<Uuid> = self.battles[&battle_id]
+            .players()
+            .into_iter()
+            .filter(|player| !self.battles[&battle_id].teammates(id).contains(player))
+            .collect();
+        let response = ServerResponse::new(ResponseType::DrawOffered(name));
+
+        self.broadcast_to(response, &others).await
+    }
+
+    /// Asks the opponent from the user's last one on one battle for a rematch. Once both have
+    /// asked, a new battle is started between them with fresh hands and its id is returned
+    pub fn request_rematch(&mut self, id: Uuid) -> ServerResult<Option<Uuid>> {
+        let last = *self
+            .last_matches
+            .get(&id)
+            .ok_or(ServerError::NoRematchError)?;
+        let opponent = last
+            .players
+            .into_iter()
+            .find(|player| *player != id)
+            .ok_or(ServerError::NoRematchError)?;
+
+        let opponent_user = self
+            .users
+            .get(&opponent)
+            .ok_or(ServerError::NoRematchError)?;
+        if opponent_user.status() != &UserStatus::Lobby {
+            let name = opponent_user.name().cloned().unwrap_or_default();
+            return Err(ServerError::RematchUnavailableError(name));
+        }
+        if self.users[&id].status() != &UserStatus::Lobby {
+            return Err(ServerError::AttemptedStartWhenNotInLobbyError);
+        }
+
+        if self.rematch_requests.get(&opponent) != Some(&id) {
+            self.rematch_requests.insert(id, opponent);
+            return Ok(None);
+        }
+
+        self.rematch_requests.remove(&opponent);
+        self.rematch_requests.remove(&id);
+
+        let [a, b] = last.players;
+        let battle = Battle::start_battle(
+            vec![a],
+            vec![b],
+            self.units.clone(),
+            last.hand_mode,
+            last.rules,
+        );
+
+        self.insert_battle(battle).map(Some)
+    }
+
+    /// The opponent a user would get a rematch against
+    pub fn rematch_opponent(&self, id: Uuid) -> Option<Uuid> {
+        self.last_matches
+            .get(&id)?
+            .players
+            .into_iter()
+            .find(|player| *player != id)
+    }
+
+    /// Lets every bot in a battle look at the board and play a card if it wants to
+    fn bot_turns(&mut self, battle_id: Uuid) -> Outbox<'a> {
+        let battle = &self.battles[&battle_id];
+        let tower_health: Vec<usize> = battle.teams.iter().map(|team| team.tower.health).collect();
+        let mut plays = vec![];
+
+        for player in battle.players() {
+            let Some(user) = self.users.get_mut(&player) else {
+                continue;
+            };
+            let Some(cards) = user.get_hand() else {
+                continue;
+            };
+            let hand: Vec<Unit> = cards.iter().filter_map(Card::unit).collect();
+            let spells: Vec<Spell> = cards.iter().filter_map(Card::spell).collect();
+
+            let economy = battle.economy(player);
+            let ready: Vec<bool> = hand
+                .iter()
+                .map(|unit| economy.is_ready(&Card::Unit(*unit), battle.ticks))
+                .collect();
+            let spells_ready: Vec<bool> = spells
+                .iter()
+                .map(|spell| economy.is_ready(&Card::Spell(*spell), battle.ticks))
+                .collect();
+            let observation = Observation {
+                hand: &hand,
+                ready: &ready,
+                spells: &spells,
+                spells_ready: &spells_ready,
+                money: economy.money,
+                income_ticks: battle.rules.income_ticks,
+                field: &battle.field,
+                side: battle.side_of(player),
+                tower_health: &tower_health,
+            };
+
+            if let Some(bot) = user.bot_mut() {
+                let actions = bot.decide(&observation, &mut rand::thread_rng());
+                plays.extend(actions.into_iter().map(|action| (player, action)));
+            }
+        }
+
+        // Anything a bot isn't allowed to do is skipped
+        let mut outbox = vec![];
+        for (bot, action) in plays {
+            match action {
+                Action::Play { unit, lane } => {
+                    if let Ok(unit) = self.play_card(bot, unit, Some(lane)) {
+                        outbox.extend(self.unit_spawned(bot, unit));
+                    }
+                }
+                Action::Cast { spell, spot } => {
+                    if let Ok(cast) = self.play_spell(bot, spell, spot) {
+                        outbox.push(cast);
+                    }
+                }
+            }
+        }
+
+        outbox
+    }
+
+    /// Tells the player's team they played a unit, and the other team that an enemy did
+    pub async fn broadcast_unit_spawned(&mut self, id: Uuid, unit: Unit<'a>) -> ServerResult<()> {
+        let outbox = self.unit_spawned(id, unit);
+        self.send_outbox(outbox).await
+    }
+
+    fn unit_spawned(&self, id: Uuid, unit: Unit<'a>) -> Outbox<'a> {
+        let ours = ServerResponse::new(ResponseType::UnitSpawned(true, Box::new(unit)));
+        let theirs = ServerResponse::new(ResponseType::UnitSpawned(false, Box::new(unit)));
+
+        vec![
+            (self.get_teammates(id), ours),
+            (self.get_enemies(id), theirs),
+        ]
+    }
+
+    /// Who a team chat message from the user goes to: their team while in a battle, otherwise
+    /// their party
+    pub fn team_chat_recipients(&self, id: Uuid) -> ServerResult<Vec<Uuid>> {
+        if self.get_battle_id(id).is_some() {
+            return Ok(self.get_teammates(id));
+        }
+
+        self.party_of(id)
+            .map(|party| self.parties[&party].clone())
+            .ok_or(ServerError::NotInPartyError)
+    }
+
+    fn party_of(&self, id: Uuid) -> Option<Uuid> {
+        self.parties
+            .iter()
+            .find(|(_, members)| members.contains(&id))
+            .map(|(party, _)| *party)
+    }
+
+    pub fn get_party(&self, id: Uuid) -> Vec<Uuid> {
+        self.party_of(id)
+            .map(|party| self.parties[&party].clone())
+            .unwrap_or_default()
+    }
+
+    /// Ids of every user with the given name
+    pub fn find_user(&self, name: &str) -> Vec<Uuid> {
+        self.users
+            .iter()
+            .filter(|(_, user)| !user.is_bot() && user.name().is_some_and(|n| n == name))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Invites the player with the given name to the user's party. Inviting someone who has
+    /// already invited you accepts their invite, returning the party's members
+    pub fn invite_to_party(&mut self, id: Uuid, name: &str) -> ServerResult<Option<Vec<Uuid>>> {
+        let target = self
+            .find_user(name)
+            .into_iter()
+            .find(|other| *other != id)
+            .ok_or_else(|| ServerError::NoSuchPlayerError(name.to_string()))?;
+
+        if self.party_invites.get(&target) != Some(&id) {
+            self.party_invites.insert(id, target);
+            return Ok(None);
+        }
+
+        let mut members = self.get_party(target);
+        if members.is_empty() {
+            members.push(target);
+        }
+        if members.contains(&id) {
+            return Ok(Some(members));
+        }
+        if members.len() >= TEAM_SIZE {
+            return Err(ServerError::PartyFullError);
+        }
+
+        self.party_invites.remove(&target);
+        self.leave_party(id);
+        self.leave_party(target);
+        members.push(id);
+        self.parties.insert(Uuid::new_v4(), members.clone());
+
+        Ok(Some(members))
+    }
+
+    /// Takes the user out of their party, returning whoever is left in it
+    pub fn leave_party(&mut self, id: Uuid) -> Vec<Uuid> {
+        let Some(party) = self.party_of(id) else {
+            return vec![];
+        };
+
+        if self.team_queue == Some(party) {
+            self.team_queue = None;
+        }
+
+        let members = self.parties.get_mut(&party).unwrap();
+        members.retain(|member| *member != id);
+        let left = members.clone();
+        if left.len() < 2 {
+            self.parties.remove(&party);
+        }
+
+        left
+    }
+
+    /// Queues the user's party for a team battle, starting one as soon as another full party
+    /// is waiting. Returns the battle's id if one started
+    pub fn queue_team_battle(
+        &mut self,
+        id: Uuid,
+        hand_mode: HandMode,
+    ) -> ServerResult<Option<Uuid>> {
+        let party = self.party_of(id).ok_or(ServerError::NotInPartyError)?;
+        let members = self.parties[&party].clone();
 
-            battle.damage_tick(to, dmg)
-        } else {
-            None
+        if members.len() != TEAM_SIZE {
+            return Err(ServerError::PartyNotFullError);
+        }
+        if members
+            .iter()
+            .any(|member| self.users[member].status() != &UserStatus::Lobby)
+        {
+            return Err(ServerError::AttemptedStartWhenNotInLobbyError);
+        }
+
+        match self.team_queue {
+            Some(waiting) if waiting != party && self.parties.contains_key(&waiting) => {
+                self.team_queue = None;
+                let enemies = self.parties[&waiting].clone();
+
+                self.new_team_battle(enemies, members, hand_mode).map(Some)
+            }
+            _ => {
+                self.team_queue = Some(party);
+                Ok(None)
+            }
         }
     }
 
-    pub fn get_opponent(&self, id: Uuid) -> Option<Uuid> {
-        let user_state = *self.users[&id].status();
-        if let UserStatus::InGame(battle_id) = user_state {
-            let battle = self.battles[&battle_id];
+    /// Puts a player in the free-for-all queue, starting a battle if it's now full
+    pub fn queue_free_for_all(&mut self, id: Uuid) -> ServerResult<Option<Uuid>> {
+        if self.users[&id].status() != &UserStatus::Lobby {
+            return Err(ServerError::AttemptedStartWhenNotInLobbyError);
+        }
+        if !self.ffa_queue.contains(&id) {
+            self.ffa_queue.push(id);
+        }
 
-            Some(battle.get_enemy(id))
-        } else {
-            None
+        if self.ffa_queue.len() < FFA_MAX_PLAYERS {
+            return Ok(None);
         }
+
+        self.start_free_for_all()
     }
 
-    pub fn get_card_from_user(&self, id: Uuid, card: usize) -> ServerResult<Unit> {
-        let user = &self.users[&id];
+    /// Starts a free-for-all with whoever is queued, as long as there are enough of them
+    pub fn start_free_for_all(&mut self) -> ServerResult<Option<Uuid>> {
+        let users = &self.users;
+        self.ffa_queue.retain(|player| {
+            users
+                .get(player)
+                .is_some_and(|user| user.status() == &UserStatus::Lobby)
+        });
 
-        if let Some(card_in_hand) = user.get_card(card) {
-            Ok(card_in_hand.clone())
-        } else {
-            Err(ServerError::NoHandYetError)
+        if self.ffa_queue.len() < FFA_MIN_PLAYERS {
+            return Ok(None);
         }
+
+        let count = self.ffa_queue.len().min(FFA_MAX_PLAYERS);
+        let players: Vec<Uuid> = self.ffa_queue.drain(..count).collect();
+        let battle = Battle::start_free_for_all(
+            players,
+            self.units.clone(),
+            HandMode::Fixed,
+            MatchRules::default(),
+        );
+
+        self.insert_battle(battle).map(Some)
+    }
+
+    /// Sets the rules for battles a user starts
+    pub fn set_rules(&mut self, id: Uuid, rules: MatchRules) -> ServerResult<()> {
+        rules.validate()?;
+
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .set_rules(rules);
+
+        Ok(())
     }
 
     pub fn set_name(&mut self, id: Uuid, name: String) {
@@ -82,16 +1244,20 @@ impl<'a> State<'a> {
         for (_, user) in self
             .users
             .iter_mut()
-            .filter(|(id, _)| !exclude.contains(id))
+            .filter(|(id, user)| !exclude.contains(id) && !user.is_bot())
         {
-            user.message(&msg)?.await?
+            user.message(&msg).await?
         }
         Ok(())
     }
 
     pub async fn broadcast_to(&mut self, msg: ServerResponse<'_>, to: &[Uuid]) -> ServerResult<()> {
-        for (_, user) in self.users.iter_mut().filter(|(id, _)| to.contains(id)) {
-            user.message(&msg)?.await?
+        for (_, user) in self
+            .users
+            .iter_mut()
+            .filter(|(id, user)| to.contains(id) && !user.is_bot())
+        {
+            user.message(&msg).await?
         }
         Ok(())
     }
@@ -102,6 +1268,10 @@ impl<'a> State<'a> {
         }
 
         let user = self.users.get_mut(&id).unwrap();
+        if user.is_bot() {
+            return Ok(());
+        }
+
         let hand = user.get_hand();
 
         if hand.is_none() {
@@ -109,14 +1279,103 @@ impl<'a> State<'a> {
         }
 
         let hand = hand.unwrap();
-        let response = ServerResponse::new(ResponseType::DrawnHand(Box::new(hand)));
+        let response = ServerResponse::new(ResponseType::DrawnHand(hand));
+
+        user.message(&response).await?;
+
+        Ok(())
+    }
+
+    fn get_battle_phase(&self, id: Uuid) -> ServerResult<BattlePhase> {
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+
+        Ok(self.battles[&battle_id].phase)
+    }
+
+    /// Sends a card in the user's hand back to their deck and draws another, as long as the
+    /// battle hasn't begun and they have redraws left
+    pub fn mulligan(&mut self, id: Uuid, card: CardId) -> ServerResult<()> {
+        if self.get_battle_phase(id)? != BattlePhase::Mulligan {
+            return Err(ServerError::MulliganOverError);
+        }
+
+        self.users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?
+            .redraw_card(card)
+            .ok_or(ServerError::CannotRedrawError)?;
+
+        Ok(())
+    }
+
+    /// Marks the user as done redrawing, returning their battle's id if both players are
+    pub fn finish_mulligan(&mut self, id: Uuid) -> ServerResult<Option<Uuid>> {
+        let battle_id = self
+            .get_battle_id(id)
+            .ok_or(ServerError::NotInBattleError)?;
+        let battle = self.battles.get_mut(&battle_id).unwrap();
+
+        Ok(battle.mark_ready(id).then_some(battle_id))
+    }
+
+    /// Ends a battle's mulligan phase and tells both players the fighting has begun. Does
+    /// nothing if it had already begun, so the mulligan timer and both players readying up
+    /// can race each other
+    pub async fn begin_fighting(&mut self, battle_id: Uuid) -> ServerResult<()> {
+        let players = match self.battles.get_mut(&battle_id) {
+            Some(battle) => {
+                if !battle.begin_fighting() {
+                    return Ok(());
+                }
+                battle.players()
+            }
+            None => return Ok(()),
+        };
+
+        self.broadcast_to(ServerResponse::new(ResponseType::BattleBegin), &players)
+            .await
+    }
+
+    pub async fn broadcast_mulligan_hand(&mut self, id: Uuid) -> ServerResult<()> {
+        let user = self
+            .users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?;
+        let hand = user.hand().ok_or(ServerError::NoHandYetError)?;
 
-        user.message(&response)?.await?;
+        let response = ServerResponse::new(ResponseType::MulliganHand(
+            hand.cards().to_vec(),
+            hand.redraws_left(),
+        ));
+        user.message(&response).await?;
 
         Ok(())
     }
 
-    pub fn new_random(&mut self, id: Uuid) -> ServerResult<(Uuid, Uuid)> {
+    /// Sends a cycling user their hand after a card was played, along with the next card
+    /// they'll draw. Does nothing for users with a fixed hand
+    pub async fn broadcast_hand_update(&mut self, id: Uuid) -> ServerResult<()> {
+        let user = self
+            .users
+            .get_mut(&id)
+            .ok_or(ServerError::InvalidUserIdError)?;
+        let hand = user.hand().ok_or(ServerError::NoHandYetError)?;
+
+        if hand.mode() == HandMode::Cycle {
+            let response = ServerResponse::new(ResponseType::HandUpdate(
+                hand.cards().to_vec(),
+                hand.next_card().map(Box::new),
+            ));
+
+            user.message(&response).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn new_random(&mut self, id: Uuid, hand_mode: HandMode) -> ServerResult<(Uuid, Uuid)> {
         let mut rng = rand::thread_rng();
         let users: Vec<Uuid> = self.available_users(id);
 
@@ -126,10 +1385,71 @@ impl<'a> State<'a> {
 
         let oponent = users[rng.gen_range(0..users.len())];
 
-        self.new_battle(id, oponent)
+        self.new_battle(id, oponent, hand_mode)
+    }
+
+    /// Starts a battle against a new bot playing a registered strategy, for when there's
+    /// nobody else to play with
+    pub fn new_bot_battle(
+        &mut self,
+        id: Uuid,
+        strategy: &str,
+        hand_mode: HandMode,
+    ) -> ServerResult<(Uuid, Uuid)> {
+        let bot_strategy = self
+            .strategies
+            .create(strategy)
+            .ok_or_else(|| ServerError::NoSuchStrategyError(strategy.to_string()))?;
+
+        let bot_id = Uuid::new_v4();
+        let mut bot = User::default();
+        bot.set_id(bot_id);
+        bot.set_name(format!("Bot ({strategy})"));
+        bot.set_bot(bot_strategy);
+
+        self.users.insert(bot_id, bot);
+
+        let result = self.new_battle(id, bot_id, hand_mode);
+        if result.is_err() {
+            self.users.remove(&bot_id);
+        }
+
+        result
+    }
+
+    /// Starts a PvE battle against one of the stages the user has unlocked
+    pub fn new_stage_battle(
+        &mut self,
+        id: Uuid,
+        stage: usize,
+        hand_mode: HandMode,
+    ) -> ServerResult<Uuid> {
+        if self.users[&id].status() != &UserStatus::Lobby {
+            return Err(ServerError::AttemptedStartWhenNotInLobbyError);
+        }
+
+        let run = StageRun::new(stage).ok_or(ServerError::NoSuchStageError(stage))?;
+        if stage > self.stages_cleared(id)? {
+            return Err(ServerError::StageLockedError(run.stage().name.to_string()));
+        }
+
+        let battle_id = Uuid::new_v4();
+        let rules = *self.users[&id].rules();
+        let battle = Battle::start_stage(id, run, self.units.clone(), hand_mode, rules);
+        let hand = self.deal_user_hand(id, &battle)?;
+
+        self.battles.insert(battle_id, battle);
+        self.users.get_mut(&id).unwrap().enter_game(battle_id, hand);
+
+        Ok(battle_id)
     }
 
-    pub fn new_battle(&mut self, user_a_id: Uuid, user_b_id: Uuid) -> ServerResult<(Uuid, Uuid)> {
+    pub fn new_battle(
+        &mut self,
+        user_a_id: Uuid,
+        user_b_id: Uuid,
+        hand_mode: HandMode,
+    ) -> ServerResult<(Uuid, Uuid)> {
         {
             let user_a = &self.users[&user_a_id];
             let user_b = &self.users[&user_b_id];
@@ -139,26 +1459,58 @@ impl<'a> State<'a> {
             }
         }
 
+        let battle_id = self.new_team_battle(vec![user_a_id], vec![user_b_id], hand_mode)?;
+
+        Ok((battle_id, user_b_id))
+    }
+
+    /// Starts a battle between two teams, dealing every player their own hand. The first
+    /// player on team a hosts the battle, so it's played with their rules
+    pub fn new_team_battle(
+        &mut self,
+        team_a: Vec<Uuid>,
+        team_b: Vec<Uuid>,
+        hand_mode: HandMode,
+    ) -> ServerResult<Uuid> {
+        let rules = team_a
+            .first()
+            .and_then(|host| self.users.get(host))
+            .map(|host| *host.rules())
+            .unwrap_or_default();
+        let new_battle = Battle::start_battle(team_a, team_b, self.units.clone(), hand_mode, rules);
+
+        self.insert_battle(new_battle)
+    }
+
+    /// Deals every player in a new battle their hand and moves them into it
+    fn insert_battle(&mut self, mut new_battle: Battle) -> ServerResult<Uuid> {
         let battle_id = Uuid::new_v4();
-        let new_battle = Battle::start_battle(user_a_id, user_b_id);
 
-        self.battles.insert(battle_id, new_battle);
+        let players = new_battle.players();
+        self.rematch_requests
+            .retain(|from, to| !players.contains(from) && !players.contains(to));
+        let hands = players
+            .iter()
+            .map(|player| self.deal_user_hand(*player, &new_battle))
+            .collect::<ServerResult<Vec<_>>>()?;
 
-        let hand_a = draw_hand::<GAME_HAND_SIZE>().unwrap();
-        let hand_b = draw_hand::<GAME_HAND_SIZE>().unwrap();
+        for player in &players {
+            // Bots are happy with whatever they're dealt
+            if self.users[player].is_bot() {
+                new_battle.mark_ready(*player);
+            }
+        }
 
-        {
-            self.users
-                .get_mut(&user_a_id)
-                .unwrap()
-                .enter_game(battle_id, hand_a);
+        self.battles.insert(battle_id, new_battle);
+
+        for (player, hand) in players.into_iter().zip(hands) {
             self.users
-                .get_mut(&user_b_id)
+                .get_mut(&player)
                 .unwrap()
-                .enter_game(battle_id, hand_b);
+                .enter_game(battle_id, hand);
         }
 
-        Ok((battle_id, user_b_id))
+        Ok(battle_id)
     }
 
     pub fn available_users(&self, exclude: Uuid) -> Vec<Uuid> {
@@ -187,11 +1539,67 @@ pub enum ServerError {
     #[error("No websocket attached to user")]
     SocketDisconnectedError,
     #[error("Tungstenite socket send error")]
-    TungstentiteError(#[from] hyper_tungstenite::tungstenite::Error),
+    TungstentiteError(Box<hyper_tungstenite::tungstenite::Error>),
     #[error("Requested hand when it did not exist")]
     NoHandYetError,
     #[error("User does not exist")]
     InvalidUserIdError,
+    #[error("Unit pack error: {0}")]
+    UnitPackError(#[from] PackError),
+    #[error("Server is using the built in units, there is no unit pack to reload")]
+    NoUnitPackError,
+    #[error("Not enough units in the unit pack to draw a hand")]
+    NotEnoughUnitsError,
+    #[error("{0}")]
+    DeckError(#[from] DeckError),
+    #[error("{0}'s deck {1} can't be used: {2}")]
+    UnusableDeckError(String, String, String),
+    #[error("That card is not in your hand")]
+    CardNotInHandError,
+    #[error("User is not in a battle")]
+    NotInBattleError,
+    #[error("The battle hasn't begun yet")]
+    BattleNotStartedError,
+    #[error("The battle has already begun, cards can't be redrawn")]
+    MulliganOverError,
+    #[error("No redraws left, or that card is not in your hand")]
+    CannotRedrawError,
+    #[error("{0}")]
+    PlayError(#[from] PlayError),
+    #[error("{0}")]
+    RulesError(#[from] RulesError),
+    #[error("Nobody has offered you a draw")]
+    NoDrawOfferError,
+    #[error("Stages can't end in a draw")]
+    DrawNotAllowedError,
+    #[error("You haven't finished a battle against anyone to rematch")]
+    NoRematchError,
+    #[error("{0} isn't available for a rematch right now")]
+    RematchUnavailableError(String),
+    #[error("No player named {0} is online")]
+    NoSuchPlayerError(String),
+    #[error("That party is already full")]
+    PartyFullError,
+    #[error("You need to be in a party to do that")]
+    NotInPartyError,
+    #[error("Team battles need a party of {TEAM_SIZE}")]
+    PartyNotFullError,
+    #[error("There is no stage {0}")]
+    NoSuchStageError(usize),
+    #[error("Clear the stages before {0} to unlock it")]
+    StageLockedError(String),
+    #[error("There is no bot strategy called {0}")]
+    NoSuchStrategyError(String),
+    #[error("User has not picked a name yet")]
+    NoNameYetError,
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl From<hyper_tungstenite::tungstenite::Error> for ServerError {
+    fn from(error: hyper_tungstenite::tungstenite::Error) -> Self {
+        Self::TungstentiteError(Box::new(error))
+    }
 }
 
 pub type ServerResult<T> = std::result::Result<T, ServerError>;
@@ -1,30 +1,37 @@
-use futures::{sink::Send, SinkExt};
+use std::sync::Arc;
+
+use futures::SinkExt;
+use tokio::sync::Mutex;
 use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
-use crate::game::entity::Unit;
+use crate::game::{
+    card::{Card, CardId},
+    hand::Hand,
+    rules::MatchRules,
+    strategy::Strategy,
+};
 
 use super::{
     service::{ServerResponse, WebSocketWriteStream},
-    state::{ServerError, ServerResult, GAME_HAND_SIZE},
+    state::{ServerError, ServerResult},
 };
 
-pub type WebsocketMessageFuture<'a> = Send<
-    'a,
-    futures::prelude::stream::SplitSink<
-        tokio_tungstenite::WebSocketStream<hyper_util::rt::TokioIo<hyper::upgrade::Upgraded>>,
-        Message,
-    >,
-    Message,
->;
+/// A user's socket, shared so messages can be sent to it without holding the server state
+pub type SharedSocket = Arc<Mutex<WebSocketWriteStream>>;
 
 #[derive(Default, Debug)]
 pub struct User<'a> {
     id: Uuid,
     name: Option<String>,
     status: UserStatus,
-    spawn_hand: Option<[Unit<'a>; GAME_HAND_SIZE]>,
-    socket: Option<WebSocketWriteStream>,
+    spawn_hand: Option<Hand<'a>>,
+    selected_deck: Option<String>,
+    /// Rules for battles this user starts
+    rules: MatchRules,
+    socket: Option<SharedSocket>,
+    /// Set for computer controlled users, who have no socket and play from the server
+    bot: Option<Box<dyn Strategy>>,
 }
 
 impl<'a> User<'a> {
@@ -32,8 +39,24 @@ impl<'a> User<'a> {
         self.name.as_ref()
     }
     pub fn set_socket(&mut self, socket: WebSocketWriteStream) {
-        self.socket = Some(socket)
+        self.socket = Some(Arc::new(Mutex::new(socket)))
+    }
+
+    pub fn socket(&self) -> Option<SharedSocket> {
+        self.socket.clone()
+    }
+    pub fn set_bot(&mut self, bot: Box<dyn Strategy>) {
+        self.bot = Some(bot)
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.bot.is_some()
     }
+
+    pub fn bot_mut(&mut self) -> Option<&mut (dyn Strategy + 'static)> {
+        self.bot.as_deref_mut()
+    }
+
     pub fn id(&self) -> &Uuid {
         &self.id
     }
@@ -46,23 +69,51 @@ impl<'a> User<'a> {
         self.id = id
     }
 
-    pub fn get_card(&self, card: usize) -> Option<Unit<'a>> {
-        if let Some(hand) = self.spawn_hand {
-            Some(hand[card].clone())
-        } else {
-            None
-        }
+    pub fn get_hand(&self) -> Option<Vec<Card<'a>>> {
+        self.spawn_hand.as_ref().map(|hand| hand.cards().to_vec())
+    }
+
+    pub fn hand(&self) -> Option<&Hand<'a>> {
+        self.spawn_hand.as_ref()
+    }
+
+    /// Plays a card from the user's hand, cycling in the next card if the battle cycles hands
+    pub fn play_card(&mut self, card: CardId) -> Option<Card<'a>> {
+        self.spawn_hand.as_mut()?.play(card)
+    }
+
+    /// Swaps a card in the user's hand for another, returning the card that was there
+    pub fn replace_card(&mut self, card: CardId, with: Card<'a>) -> Option<Card<'a>> {
+        self.spawn_hand.as_mut()?.replace(card, with)
+    }
+
+    /// Name of the saved deck this user's next battle draws from, if they picked one
+    pub fn selected_deck(&self) -> Option<&String> {
+        self.selected_deck.as_ref()
     }
 
-    pub fn get_hand(&self) -> Option<[Unit<'a>; GAME_HAND_SIZE]> {
-        self.spawn_hand
+    pub fn select_deck(&mut self, deck: Option<String>) {
+        self.selected_deck = deck
+    }
+
+    pub fn rules(&self) -> &MatchRules {
+        &self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: MatchRules) {
+        self.rules = rules
     }
 
     pub fn status(&self) -> &UserStatus {
         &self.status
     }
 
-    pub fn enter_game(&mut self, battle: Uuid, hand: [Unit<'a>; 5]) {
+    /// Redraws a card during the mulligan phase, returning the card drawn in its place
+    pub fn redraw_card(&mut self, card: CardId) -> Option<Card<'a>> {
+        self.spawn_hand.as_mut()?.redraw(card)
+    }
+
+    pub fn enter_game(&mut self, battle: Uuid, hand: Hand<'a>) {
         self.status = UserStatus::InGame(battle);
         self.set_hand(hand)
     }
@@ -71,14 +122,15 @@ impl<'a> User<'a> {
         self.status = UserStatus::Lobby
     }
 
-    pub fn set_hand(&mut self, hand: [Unit<'a>; 5]) {
-        self.spawn_hand = Some(hand.to_owned());
+    pub fn set_hand(&mut self, hand: Hand<'a>) {
+        self.spawn_hand = Some(hand);
     }
 
-    pub fn message(&mut self, message: &ServerResponse) -> ServerResult<WebsocketMessageFuture> {
-        if let Some(socket) = &mut self.socket {
+    pub async fn message(&self, message: &ServerResponse<'_>) -> ServerResult<()> {
+        if let Some(socket) = &self.socket {
             let msg = serde_json::to_string(&message)?;
-            Ok(socket.send(Message::text(msg)))
+            socket.lock().await.send(Message::text(msg)).await?;
+            Ok(())
         } else {
             Err(ServerError::SocketDisconnectedError)
         }